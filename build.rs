@@ -0,0 +1,14 @@
+// Generate the gRPC messages and service from proto/ldml.proto when the grpc
+// feature is enabled. PROTOC may name the protoc to use, otherwise a vendored
+// copy is used so none need be installed.
+fn main() -> std::io::Result<()> {
+    #[cfg(feature = "grpc")]
+    {
+        if std::env::var_os("PROTOC").is_none() {
+            let protoc = protoc_bin_vendored::protoc_bin_path().map_err(std::io::Error::other)?;
+            std::env::set_var("PROTOC", protoc);
+        }
+        tonic_build::compile_protos("proto/ldml.proto")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use std::{fs::File, io::BufReader, path::PathBuf};
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use langtags::json::LangTags;
+use language_tag::Tag;
+use rand::{rngs::StdRng, seq::IteratorRandom, SeedableRng};
+
+fn fixture_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("langtags.json")
+}
+
+#[cfg(feature = "bench-production")]
+fn langtags_path() -> PathBuf {
+    std::env::var_os("LANGTAGS_JSON")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            eprintln!("LANGTAGS_JSON is not set, benchmarking tests/langtags.json");
+            fixture_path()
+        })
+}
+
+#[cfg(not(feature = "bench-production"))]
+fn langtags_path() -> PathBuf {
+    fixture_path()
+}
+
+fn load() -> LangTags {
+    let file = File::open(langtags_path()).expect("open langtags.json");
+    LangTags::from_reader(BufReader::new(file)).expect("read langtags.json")
+}
+
+fn from_reader(c: &mut Criterion) {
+    let src = std::fs::read(langtags_path()).expect("read langtags.json");
+    let mut group = c.benchmark_group("load");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(src.len() as u64));
+    group.bench_function("from_reader", |b| {
+        b.iter(|| LangTags::from_reader(black_box(&src[..])).expect("parsed langtags.json"))
+    });
+    group.finish();
+}
+
+fn lookups(c: &mut Criterion) {
+    let langtags = load();
+    let mut rng = StdRng::seed_from_u64(0x1d41);
+    let tags: Vec<Tag> = langtags
+        .tagsets()
+        .flat_map(|ts| ts.all_tags())
+        .choose_multiple(&mut rng, 100);
+
+    let mut group = c.benchmark_group("lookup");
+    group.throughput(Throughput::Elements(tags.len() as u64));
+    group.bench_function("orthographic_normal_form", |b| {
+        b.iter(|| {
+            for tag in &tags {
+                black_box(langtags.orthographic_normal_form(black_box(tag)));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn indexing(c: &mut Criterion) {
+    let langtags = load();
+    c.bench_function("iterate all tags", |b| {
+        b.iter(|| langtags.tagsets().flat_map(|ts| ts.all_tags()).count())
+    });
+    c.bench_function("build_all_tags_index", |b| {
+        b.iter_batched(
+            load,
+            |mut langtags| {
+                langtags.build_all_tags_index();
+                langtags
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = from_reader, lookups, indexing
+}
+criterion_main!(benches);
@@ -0,0 +1,150 @@
+use crate::json::LangTags;
+use bincode::config::{self, Configuration, Fixint, LittleEndian};
+use std::io::{self, Read, Write};
+
+// Identifies a cache file, followed by FORMAT_VERSION as a little endian u32.
+const MAGIC: &[u8; 4] = b"LTDB";
+// Bump this whenever LangTags, TagSet or Tag change shape, so caches written
+// by older builds are rejected rather than misread.
+const FORMAT_VERSION: u32 = 3;
+
+// Decoded collections take more memory than their encoding does, so allow
+// decoding to allocate up to this multiple of the cache size. Any more and a
+// damaged length prefix fails the load rather than aborting on allocation.
+const EXPANSION: usize = 16;
+
+fn config() -> Configuration<LittleEndian, Fixint> {
+    config::standard()
+        .with_little_endian()
+        .with_fixed_int_encoding()
+}
+
+fn decode<const LIMIT: usize>(body: &[u8]) -> io::Result<LangTags> {
+    bincode::decode_from_slice(body, config().with_limit::<LIMIT>())
+        .map(|(langtags, _)| langtags)
+        .map_err(invalid_data)
+}
+
+fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+// A compact binary cache of a parsed langtags.json database, with its caches
+// already built, which loads considerably faster than the JSON it came from.
+// A load failing with InvalidData means the cache is stale or damaged and the
+// caller should fall back to parsing langtags.json.
+pub struct BinaryLangTags;
+
+impl BinaryLangTags {
+    pub fn save<W: Write>(langtags: &LangTags, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        bincode::encode_into_std_write(langtags, &mut writer, config()).map_err(invalid_data)?;
+        writer.flush()
+    }
+
+    pub fn load<R: Read>(mut reader: R) -> io::Result<LangTags> {
+        let mut header = [0; 8];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(invalid_data("not a langtags binary cache"));
+        }
+        let version = u32::from_le_bytes(header[4..].try_into().unwrap());
+        if version != FORMAT_VERSION {
+            return Err(invalid_data(format!(
+                "langtags binary cache version {version}, expected {FORMAT_VERSION}"
+            )));
+        }
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body)?;
+        // bincode only takes its limit as a constant, so use the smallest of
+        // these that fits the cache.
+        const MIB: usize = 1 << 20;
+        match body.len() {
+            n if n <= 4 * MIB => decode::<{ 4 * MIB * EXPANSION }>(&body),
+            n if n <= 16 * MIB => decode::<{ 16 * MIB * EXPANSION }>(&body),
+            n if n <= 64 * MIB => decode::<{ 64 * MIB * EXPANSION }>(&body),
+            n => Err(invalid_data(format!(
+                "langtags binary cache of {n} bytes is too large"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BinaryLangTags, FORMAT_VERSION, MAGIC};
+    use crate::json::LangTags;
+    use language_tag::Tag;
+    use std::{io, str::FromStr};
+
+    const SRC: &str = r#"[
+        {"regions": ["AA"], "scripts": ["Qaaa"], "tag": "_conformance"},
+        {"api": "1.2.1", "date": "2023-01-01", "tag": "_version"},
+        {"full": "aa-Latn-ET", "regions": ["DJ", "ER"], "sldr": true, "tag": "aa",
+         "tags": ["aa-ET", "aa-Latn"], "windows": "aa-Latn-ET"},
+        {"full": "aa-Arab-ET", "tag": "aa-Arab", "windows": "aa-Arab-ET", "obsolete": true}
+    ]"#;
+
+    #[test]
+    fn roundtrip() {
+        let mut langtags = LangTags::from_str(SRC).expect("LangTags");
+        langtags.build_all_tags_index();
+        let mut cache = Vec::new();
+        BinaryLangTags::save(&langtags, &mut cache).expect("saved cache");
+        assert!(cache.starts_with(MAGIC));
+
+        let loaded = BinaryLangTags::load(cache.as_slice()).expect("loaded cache");
+        assert_eq!(loaded, langtags);
+        assert_eq!(loaded.version(), "1.2.1");
+        let aa = Tag::from_str("aa-DJ").unwrap();
+        assert_eq!(loaded.find_any_form(&aa), langtags.find_any_form(&aa));
+    }
+
+    #[test]
+    fn rejects_stale() {
+        let langtags = LangTags::from_str(SRC).expect("LangTags");
+        let mut cache = Vec::new();
+        BinaryLangTags::save(&langtags, &mut cache).expect("saved cache");
+
+        let mut stale = cache.clone();
+        stale[4..8].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        let err = BinaryLangTags::load(stale.as_slice()).expect_err("stale version");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let err = BinaryLangTags::load(SRC.as_bytes()).expect_err("not a cache");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        cache.truncate(cache.len() / 2);
+        assert!(BinaryLangTags::load(cache.as_slice()).is_err());
+    }
+
+    #[test]
+    fn rejects_huge_length() {
+        let langtags = LangTags::from_str(SRC).expect("LangTags");
+        let mut cache = Vec::new();
+        BinaryLangTags::save(&langtags, &mut cache).expect("saved cache");
+
+        // The version string's length prefix follows the header.
+        cache[8..16].copy_from_slice(&(1u64 << 40).to_le_bytes());
+        let err = BinaryLangTags::load(cache.as_slice()).expect_err("huge length");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn production_data_within_limit() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/langtags.json");
+        let mut langtags =
+            LangTags::from_str(&std::fs::read_to_string(path).unwrap()).expect("LangTags");
+        langtags.build_all_tags_index();
+        let mut cache = Vec::new();
+        BinaryLangTags::save(&langtags, &mut cache).expect("saved cache");
+        assert_eq!(
+            BinaryLangTags::load(cache.as_slice()).expect("loaded"),
+            langtags
+        );
+    }
+}
@@ -1,12 +1,27 @@
-use crate::tagset::TagSet;
+use crate::{tagset::TagSet, windows_locale};
+#[cfg(feature = "binary-cache")]
+use bincode::{Decode, Encode};
 use language_tag::{ExtensionRef, Tag};
-use serde::Deserialize;
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use smallvec::SmallVec;
 use std::{
+    cmp::Ordering,
     collections::{HashMap as Map, HashSet as Set},
-    io::{self, BufRead},
+    fmt::{self, Display},
+    fs,
+    io::{self, BufRead, Write},
+    mem::size_of,
+    path::{Path, PathBuf},
+    ptr,
+    str::FromStr,
+    sync::Arc,
 };
 
 #[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "binary-cache", derive(Encode, Decode))]
 pub struct LangTags {
     version: String,
     date: String,
@@ -16,6 +31,231 @@ pub struct LangTags {
     latn_variants: Set<String>,
     tagsets: Vec<TagSet>,
     full: Map<String, u32>,
+    script_regions: Map<(String, String), Vec<u32>>,
+    deprecated: Vec<u32>,
+    languages: Map<String, Vec<u32>>,
+    rods: Map<String, Vec<u32>>,
+    all_tags: Option<Map<String, u32>>,
+}
+
+// Ways in which a tagset can contradict itself, see verify_consistency.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConsistencyError {
+    ScriptMismatch {
+        tagset_full: Tag,
+        computed_script: String,
+        stored_script: String,
+    },
+    RegionInExtraList {
+        tagset_full: Tag,
+        region: String,
+    },
+}
+
+impl Display for ConsistencyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConsistencyError::ScriptMismatch {
+                tagset_full,
+                computed_script,
+                stored_script,
+            } => write!(
+                f,
+                "{tagset_full}: tag with script {computed_script}, expected {stored_script}"
+            ),
+            ConsistencyError::RegionInExtraList {
+                tagset_full,
+                region,
+            } => write!(f, "{tagset_full}: primary region {region} in regions"),
+        }
+    }
+}
+
+// How merge_from settles a tagset whose full tag is in both databases.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConflictStrategy {
+    PreferSelf,
+    PreferOther,
+    Error,
+}
+
+// The full tags of the tagsets found in both databases by try_merge_from.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MergeError {
+    pub conflicts: Vec<Tag>,
+}
+
+impl Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("conflicting tagsets:")?;
+        for full in &self.conflicts {
+            write!(f, " {full}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MergeError {}
+
+// A serde_json::Error, which is neither Clone nor PartialEq. Two compare equal
+// when their messages, position included, are the same.
+#[derive(Clone, Debug)]
+pub struct SerdeJsonError(Arc<serde_json::Error>);
+
+impl SerdeJsonError {
+    pub fn inner(&self) -> &serde_json::Error {
+        &self.0
+    }
+}
+
+impl PartialEq for SerdeJsonError {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_string() == other.0.to_string()
+    }
+}
+
+// Why a langtags.json database could not be loaded.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErrorKind {
+    // Unreadable, not JSON, or not a langtags.json array of tagsets.
+    Json(SerdeJsonError),
+    // An entry tagged with a header's name, eg. _version, that is missing
+    // fields the header needs.
+    MissingHeader(String),
+    // from_readers was given no files.
+    NoInput,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Error {
+    kind: ErrorKind,
+}
+
+impl Error {
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    pub fn is_json_error(&self) -> bool {
+        matches!(self.kind, ErrorKind::Json(_))
+    }
+
+    pub fn is_missing_header(&self) -> bool {
+        matches!(self.kind, ErrorKind::MissingHeader(_))
+    }
+
+    pub fn missing_header_name(&self) -> Option<&str> {
+        match &self.kind {
+            ErrorKind::MissingHeader(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ErrorKind::Json(err) => err.0.fmt(f),
+            ErrorKind::MissingHeader(name) => write!(f, "{name} header is incomplete"),
+            ErrorKind::NoInput => f.write_str("no langtags files to load"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Json(err) => Some(err.inner()),
+            _ => None,
+        }
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Error { kind }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        ErrorKind::Json(SerdeJsonError(Arc::new(err))).into()
+    }
+}
+
+// For callers loading langtags.json alongside other files. A JSON error keeps
+// the io::ErrorKind serde_json would give it.
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match err.kind {
+            ErrorKind::Json(SerdeJsonError(json)) => match Arc::try_unwrap(json) {
+                Ok(json) => json.into(),
+                Err(json) => io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    Error::from(ErrorKind::Json(SerdeJsonError(json))),
+                ),
+            },
+            kind @ ErrorKind::NoInput => {
+                io::Error::new(io::ErrorKind::InvalidInput, Error::from(kind))
+            }
+            kind => io::Error::new(io::ErrorKind::InvalidData, Error::from(kind)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LookupResult<'a> {
+    pub tagset: &'a TagSet,
+    pub matched_tag: Tag,
+    pub stripped_components: usize,
+}
+
+// The disagreements between a database and an SLDR found by
+// validate_tag_file_correspondence.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ValidationReport {
+    // Tagsets marked as having SLDR data with no LDML file in one of the trees.
+    pub missing_files: Vec<Tag>,
+    // LDML files whose names are not a tag in any tagset.
+    pub extra_files: Vec<PathBuf>,
+    // The tag, the database's revid and the file's revid. langtags.json
+    // records no revids, so nothing is ever reported here at present.
+    pub mismatched_revids: Vec<(Tag, String, String)>,
+}
+
+impl ValidationReport {
+    #[inline]
+    pub fn is_ok(&self) -> bool {
+        self.missing_files.is_empty()
+            && self.extra_files.is_empty()
+            && self.mismatched_revids.is_empty()
+    }
+}
+
+// The ways in which a tag can fail conformant_strict.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConformanceError {
+    UnknownScript(String),
+    UnknownRegion(String),
+    UnknownVariant(String),
+    InvalidExtension(String),
+    PrivateUseConflict(String),
+    Obsolete,
+}
+
+impl Display for ConformanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConformanceError::UnknownScript(s) => write!(f, "unknown script: {s}"),
+            ConformanceError::UnknownRegion(r) => write!(f, "unknown region: {r}"),
+            ConformanceError::UnknownVariant(v) => write!(f, "unknown variant: {v}"),
+            ConformanceError::InvalidExtension(e) => write!(f, "invalid extension: {e}"),
+            ConformanceError::PrivateUseConflict(p) => {
+                write!(f, "private use conflicts with a registered tag: {p}")
+            }
+            ConformanceError::Obsolete => f.write_str("obsolete tag"),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Eq, PartialEq)]
@@ -34,50 +274,216 @@ enum Header {
     },
 }
 
-impl LangTags {
-    pub fn from_reader<R: BufRead>(reader: R) -> io::Result<Self> {
+impl Header {
+    const NAMES: [&'static str; 4] = ["_globalvar", "_phonvar", "_version", "_conformance"];
+
+    // The header an entry is tagged as, whether or not it is complete.
+    fn name(value: &serde_json::Value) -> Option<&str> {
+        value
+            .get("tag")
+            .and_then(serde_json::Value::as_str)
+            .filter(|tag| Header::NAMES.contains(tag))
+    }
+}
+
+// Builds a LangTags from the langtags.json array one entry at a time, so the
+// file is never held in memory as a whole. Header entries are only
+// recognised at the start of the array, everything after the first entry
+// that is not a header must be a TagSet. An incomplete header is named in
+// missing_header, so it can be told apart from other errors.
+struct LangTagsVisitor<'a> {
+    missing_header: &'a mut Option<String>,
+}
+
+impl<'de> Visitor<'de> for LangTagsVisitor<'_> {
+    type Value = LangTags;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a langtags.json array")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
         use serde_json::Value;
 
-        let mut values: Vec<Value> = serde_json::from_reader(reader)?;
-        // This processes the everything at the start of the langtags.json file
-        // that looks like a header, stopping at the first TagSet.
-        let mut tagset_start = 0usize;
-        let mut langtags = values
-            .iter()
-            .cloned()
-            // Convert JSON values into Header values until they stop being headers.
-            .map_while(|v| serde_json::from_value(v).ok())
-            // Process the Header values updating the LangTags struct members as each header directs.
-            .fold(Default::default(), |mut lts, header| {
-                tagset_start += 1;
-                match header {
-                    Header::GlobalVar { variants } => LangTags { variants, ..lts },
-                    Header::PhonVar {
-                        variants: latn_variants,
-                    } => LangTags {
-                        latn_variants,
-                        ..lts
-                    },
-                    Header::Version { api, date } => LangTags {
-                        version: api,
-                        date,
-                        ..lts
-                    },
-                    Header::Conformance { scripts, regions } => {
-                        lts.scripts.extend(scripts);
-                        lts.regions.extend(regions);
-                        lts
-                    }
+        let mut langtags = LangTags::default();
+        while let Some(value) = seq.next_element::<Value>()? {
+            match Header::deserialize(&value) {
+                Ok(header) => langtags.apply_header(header),
+                Err(err) if Header::name(&value).is_some() => {
+                    *self.missing_header = Header::name(&value).map(str::to_owned);
+                    return Err(de::Error::custom(err));
                 }
-            });
-        // Remove the values that were headers, leaving only the valid TagSets.
-        values.drain(..tagset_start);
-        langtags.tagsets = serde_json::from_value(Value::Array(values))?;
+                Err(_) => {
+                    let tagset = TagSet::deserialize(value).map_err(de::Error::custom)?;
+                    langtags.tagsets.push(tagset);
+                    break;
+                }
+            }
+        }
+        while let Some(tagset) = seq.next_element()? {
+            langtags.tagsets.push(tagset);
+        }
+        Ok(langtags)
+    }
+}
+
+impl LangTags {
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, Error> {
+        let mut missing_header = None;
+        let mut de = serde_json::Deserializer::from_reader(reader);
+        let mut langtags = de
+            .deserialize_seq(LangTagsVisitor {
+                missing_header: &mut missing_header,
+            })
+            .map_err(|err| match missing_header {
+                Some(name) => ErrorKind::MissingHeader(name).into(),
+                None => Error::from(err),
+            })?;
+        de.end()?;
         langtags.build_caches();
         langtags.shrink_to_fit();
         Ok(langtags)
     }
 
+    fn apply_header(&mut self, header: Header) {
+        match header {
+            Header::GlobalVar { variants } => self.variants = variants,
+            Header::PhonVar { variants } => self.latn_variants = variants,
+            Header::Version { api, date } => {
+                self.version = api;
+                self.date = date;
+            }
+            Header::Conformance { scripts, regions } => {
+                self.scripts.extend(scripts);
+                self.regions.extend(regions);
+            }
+        }
+    }
+
+    /// Parse a langtags.json database held in memory, such as from
+    /// `include_bytes!` or a memory map.
+    ///
+    /// ```
+    /// use langtags::json::LangTags;
+    ///
+    /// let data = br#"[{"tag": "_version", "api": "1.3", "date": "2023-02-20"}]"#;
+    /// let langtags = LangTags::from_slice(data).expect("langtags.json");
+    /// assert!(langtags.is_empty());
+    /// ```
+    pub fn from_slice(data: &[u8]) -> Result<Self, Error> {
+        LangTags::from_reader(io::Cursor::new(data))
+    }
+
+    // Load a database split across several langtags.json style files. The
+    // headers come from the first, later files need only hold tagsets, and a
+    // tagset found in more than one file is taken from the last.
+    pub fn from_readers<I, R>(readers: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = R>,
+        R: BufRead,
+    {
+        let mut readers = readers.into_iter();
+        let first = readers.next().ok_or(ErrorKind::NoInput)?;
+        let mut langtags = LangTags::from_reader(first)?;
+        for reader in readers {
+            langtags.extend(LangTags::from_reader(reader)?);
+        }
+        Ok(langtags)
+    }
+
+    /// Parse a langtags.json database held in a string.
+    ///
+    /// ```
+    /// use langtags::json::LangTags;
+    ///
+    /// let langtags = LangTags::from_str_slice(
+    ///     r#"[{"full": "aa-Latn-ET", "sldr": true, "tag": "aa", "windows": "aa-Latn-ET"}]"#,
+    /// )
+    /// .expect("langtags.json");
+    /// assert_eq!(langtags.len(), 1);
+    /// ```
+    pub fn from_str_slice(data: &str) -> Result<Self, Error> {
+        LangTags::from_slice(data.as_bytes())
+    }
+
+    pub fn to_writer<W: Write>(&self, writer: W) -> io::Result<()> {
+        self.to_writer_filtered(writer, |_| true)
+    }
+
+    // Write a langtags.json compatible database containing only the tagsets
+    // accepted by filter. The headers are always written in full.
+    pub fn to_writer_filtered<W, F>(&self, writer: W, mut filter: F) -> io::Result<()>
+    where
+        W: Write,
+        F: FnMut(&TagSet) -> bool,
+    {
+        use serde_json::{json, Value};
+
+        fn sorted(set: &Set<String>) -> Vec<&String> {
+            let mut v: Vec<_> = set.iter().collect();
+            v.sort_unstable();
+            v
+        }
+
+        let mut values = vec![
+            json!({
+                "tag": "_conformance",
+                "scripts": sorted(&self.scripts),
+                "regions": sorted(&self.regions),
+            }),
+            json!({ "tag": "_globalvar", "variants": sorted(&self.variants) }),
+            json!({ "tag": "_phonvar", "variants": sorted(&self.latn_variants) }),
+            json!({ "tag": "_version", "api": self.version, "date": self.date }),
+        ];
+        for ts in self.tagsets.iter().filter(|ts| filter(ts)) {
+            let mut value = serde_json::to_value(ts)?;
+            if let Value::Object(ref mut record) = value {
+                record.insert("script".into(), ts.script().into());
+                record.insert("region".into(), ts.region().into());
+            }
+            values.push(value);
+        }
+        serde_json::to_writer_pretty(writer, &values)?;
+        Ok(())
+    }
+
+    // Write the tagsets as CSV, one row per tagset under a header row, for
+    // spreadsheets and data frames. The regions column is ; separated.
+    pub fn to_csv_writer<W: Write>(&self, w: &mut W) -> csv::Result<()> {
+        let mut csv = csv::Writer::from_writer(w);
+        csv.write_record([
+            "tag",
+            "full",
+            "name",
+            "localname",
+            "iso639_3",
+            "script",
+            "region",
+            "regions",
+            "sldr",
+            "obsolete",
+            "windows",
+        ])?;
+        let flag = |b: bool| if b { "true" } else { "false" };
+        for ts in &self.tagsets {
+            csv.write_record([
+                ts.tag.as_ref(),
+                ts.full.as_ref(),
+                &ts.name,
+                &ts.localname,
+                &ts.iso639_3,
+                ts.script().unwrap_or_default(),
+                ts.region().unwrap_or_default(),
+                &ts.regions.join(";"),
+                flag(ts.sldr),
+                flag(ts.obsolete),
+                ts.windows.as_ref(),
+            ])?;
+        }
+        csv.flush()?;
+        Ok(())
+    }
+
     fn build_caches(&mut self) {
         for (i, ts) in self.tagsets.iter().enumerate() {
             self.full
@@ -85,17 +491,285 @@ impl LangTags {
             self.scripts.insert(ts.script().unwrap().to_owned());
             self.regions.insert(ts.region().unwrap().to_owned());
             self.regions.extend(ts.regions.iter().cloned());
+            let script = ts.script().unwrap();
+            for region in ts
+                .region()
+                .into_iter()
+                .chain(ts.regions.iter().map(String::as_str))
+            {
+                self.script_regions
+                    .entry((script.to_owned(), region.to_owned()))
+                    .or_default()
+                    .push(i as u32);
+            }
+            if ts.is_obsolete() {
+                self.deprecated.push(i as u32);
+            }
+            self.languages
+                .entry(ts.full.primary_lang().to_owned())
+                .or_default()
+                .push(i as u32);
+            if let Some(rod) = ts.rod_code() {
+                self.rods.entry(rod.to_owned()).or_default().push(i as u32);
+            }
+        }
+        debug_assert_eq!(self.verify_consistency(), []);
+    }
+
+    // Empty every index build_caches fills from the tagsets, ready for it to
+    // rebuild them after the tagsets change.
+    fn reset_caches(&mut self) {
+        self.full.clear();
+        self.script_regions.clear();
+        self.deprecated.clear();
+        self.languages.clear();
+        self.rods.clear();
+    }
+
+    // The tagsets whose full tag is also the full tag of a tagset in other.
+    pub fn intersect(&self, other: &LangTags) -> LangTags {
+        self.filtered(|ts| other.has_full(&ts.full))
+    }
+
+    // The tagsets whose full tag is not the full tag of any tagset in other.
+    pub fn difference(&self, other: &LangTags) -> LangTags {
+        self.filtered(|ts| !other.has_full(&ts.full))
+    }
+
+    fn has_full(&self, full: &Tag) -> bool {
+        self.position_of_full(full).is_some()
+    }
+
+    fn position_of_full(&self, full: &Tag) -> Option<usize> {
+        self.full
+            .get(&full.to_string())
+            .map(|&i| i as usize)
+            .filter(|&i| self.tagsets[i].full == *full)
+    }
+
+    // Add other's tagsets to this database, settling any whose full tag is
+    // already present as conflict says. Panics if there are conflicts under
+    // ConflictStrategy::Error, which is what try_merge_from is for.
+    #[track_caller]
+    pub fn merge_from(&mut self, other: &LangTags, conflict: ConflictStrategy) {
+        if let Err(err) = self.try_merge_from(other, conflict) {
+            panic!("{err}, use try_merge_from to handle conflicts");
+        }
+    }
+
+    // As merge_from, but under ConflictStrategy::Error any conflicts are
+    // returned instead, leaving this database unchanged.
+    pub fn try_merge_from(
+        &mut self,
+        other: &LangTags,
+        conflict: ConflictStrategy,
+    ) -> Result<(), MergeError> {
+        if conflict == ConflictStrategy::Error {
+            let conflicts: Vec<_> = other
+                .tagsets
+                .iter()
+                .filter(|ts| self.has_full(&ts.full))
+                .map(|ts| ts.full.clone())
+                .collect();
+            if !conflicts.is_empty() {
+                return Err(MergeError { conflicts });
+            }
+        }
+        self.scripts.extend(other.scripts.iter().cloned());
+        self.regions.extend(other.regions.iter().cloned());
+        self.variants.extend(other.variants.iter().cloned());
+        self.latn_variants
+            .extend(other.latn_variants.iter().cloned());
+        self.merge_tagsets(
+            other.tagsets.iter().cloned(),
+            conflict == ConflictStrategy::PreferOther,
+        );
+        Ok(())
+    }
+
+    // Merge other into this database, preferring other's tagsets where both
+    // have the same full tag.
+    pub fn extend(&mut self, other: LangTags) {
+        self.scripts.extend(other.scripts);
+        self.regions.extend(other.regions);
+        self.variants.extend(other.variants);
+        self.latn_variants.extend(other.latn_variants);
+        self.merge_tagsets(other.tagsets, true);
+    }
+
+    fn merge_tagsets(&mut self, tagsets: impl IntoIterator<Item = TagSet>, prefer_other: bool) {
+        let mut added = Vec::new();
+        for ts in tagsets {
+            match self.position_of_full(&ts.full) {
+                Some(i) if prefer_other => self.tagsets[i] = ts,
+                Some(_) => {}
+                None => added.push(ts),
+            }
+        }
+        self.tagsets.extend(added);
+        self.reset_caches();
+        self.build_caches();
+        if self.all_tags.is_some() {
+            self.build_all_tags_index();
         }
     }
 
-    fn shrink_to_fit(&mut self) {
+    // A copy of this database holding only the tagsets accepted by keep, with
+    // the same headers and freshly built caches.
+    fn filtered(&self, keep: impl Fn(&TagSet) -> bool) -> LangTags {
+        let mut langtags = LangTags {
+            version: self.version.clone(),
+            date: self.date.clone(),
+            scripts: self.scripts.clone(),
+            regions: self.regions.clone(),
+            variants: self.variants.clone(),
+            latn_variants: self.latn_variants.clone(),
+            tagsets: self.tagsets.iter().filter(|ts| keep(ts)).cloned().collect(),
+            ..Default::default()
+        };
+        langtags.build_caches();
+        if self.all_tags.is_some() {
+            langtags.build_all_tags_index();
+        }
+        langtags.shrink_to_fit();
+        langtags
+    }
+
+    // Check each tagset agrees with its full tag: every tag in it written with
+    // the same script, and the full tag's region not repeated in regions.
+    pub fn verify_consistency(&self) -> Vec<ConsistencyError> {
+        let mut errors = Vec::new();
+        for ts in &self.tagsets {
+            let stored_script = ts.script().unwrap_or_default();
+            for script in ts.iter().filter_map(Tag::script) {
+                if !script.eq_ignore_ascii_case(stored_script) {
+                    errors.push(ConsistencyError::ScriptMismatch {
+                        tagset_full: ts.full.clone(),
+                        computed_script: script.to_owned(),
+                        stored_script: stored_script.to_owned(),
+                    });
+                }
+            }
+            if let Some(region) = ts.region().filter(|r| ts.regions.iter().any(|x| x == r)) {
+                errors.push(ConsistencyError::RegionInExtraList {
+                    tagset_full: ts.full.clone(),
+                    region: region.to_owned(),
+                });
+            }
+        }
+        errors
+    }
+
+    // Index every tag each tagset can generate. This trades a considerable
+    // amount of memory for constant time lookups in find_any_form.
+    pub fn build_all_tags_index(&mut self) {
+        let mut index = Map::new();
+        for (i, ts) in self.tagsets.iter().enumerate() {
+            index.extend(ts.all_tags().map(|tag| (tag.to_string(), i as u32)));
+        }
+        index.shrink_to_fit();
+        self.all_tags = Some(index);
+    }
+
+    // The langtags.json API version, from its _version header.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn date(&self) -> &str {
+        &self.date
+    }
+
+    // Compares the database date with other_date. Both are ISO 8601 dates,
+    // eg. "2023-02-20", so they order lexicographically.
+    pub fn version_date_cmp(&self, other_date: &str) -> Ordering {
+        self.date.as_str().cmp(other_date)
+    }
+
+    // The tagsets that may have changed after since, an ISO 8601 date. Tagsets
+    // carry no dates of their own, so this is every tagset when the database
+    // is newer than since and none otherwise.
+    pub fn tagsets_changed_since<'a>(&'a self, since: &str) -> impl Iterator<Item = &'a TagSet> {
+        let changed = self.version_date_cmp(since).is_gt();
+        self.tagsets.iter().filter(move |_| changed)
+    }
+
+    pub fn shrink_to_fit(&mut self) {
         self.scripts.shrink_to_fit();
         self.regions.shrink_to_fit();
         self.variants.shrink_to_fit();
         self.latn_variants.shrink_to_fit();
         self.tagsets.shrink_to_fit();
+        self.full.shrink_to_fit();
+        self.script_regions.shrink_to_fit();
+        self.script_regions
+            .values_mut()
+            .for_each(Vec::shrink_to_fit);
+        self.deprecated.shrink_to_fit();
+        self.languages.shrink_to_fit();
+        self.languages.values_mut().for_each(Vec::shrink_to_fit);
+        self.rods.shrink_to_fit();
+        self.rods.values_mut().for_each(Vec::shrink_to_fit);
+        if let Some(index) = self.all_tags.as_mut() {
+            index.shrink_to_fit();
+        }
     }
 
+    // Rough bytes allocated by the collections held, from their capacities,
+    // and by the tagsets and strings in them. Still an estimate: allocator
+    // overhead and hash table control bytes are not included.
+    pub fn memory_usage_estimate(&self) -> usize {
+        fn strings<'a>(s: impl IntoIterator<Item = &'a String>) -> usize {
+            s.into_iter().map(String::capacity).sum()
+        }
+        fn set<T>(s: &Set<T>) -> usize {
+            s.capacity() * size_of::<T>()
+        }
+        fn map<K, V>(m: &Map<K, V>) -> usize {
+            m.capacity() * size_of::<(K, V)>()
+        }
+        fn vec<T>(v: &Vec<T>) -> usize {
+            v.capacity() * size_of::<T>()
+        }
+        set(&self.scripts)
+            + set(&self.regions)
+            + set(&self.variants)
+            + set(&self.latn_variants)
+            + vec(&self.tagsets)
+            + map(&self.full)
+            + map(&self.script_regions)
+            + self.script_regions.values().map(vec).sum::<usize>()
+            + vec(&self.deprecated)
+            + map(&self.languages)
+            + self.languages.values().map(vec).sum::<usize>()
+            + map(&self.rods)
+            + self.rods.values().map(vec).sum::<usize>()
+            + self.all_tags.as_ref().map_or(0, map)
+            + self.version.capacity()
+            + self.date.capacity()
+            + strings(&self.scripts)
+            + strings(&self.regions)
+            + strings(&self.variants)
+            + strings(&self.latn_variants)
+            + self.tagsets.iter().map(TagSet::heap_size).sum::<usize>()
+            + strings(self.full.keys())
+            + strings(self.script_regions.keys().flat_map(|(s, r)| [s, r]))
+            + strings(self.languages.keys())
+            + strings(self.rods.keys())
+            + self
+                .all_tags
+                .as_ref()
+                .map_or(0, |index| strings(index.keys()))
+    }
+
+    // The same estimate as memory_usage_estimate, in bytes.
+    pub fn estimate_memory_bytes(&self) -> usize {
+        self.memory_usage_estimate()
+    }
+
+    // A tag is conformant when its script and region are known, and it does
+    // not resolve to an obsolete tagset. Suppressed tagsets remain
+    // conformant, as suppress only marks a suppressed script subtag.
     pub fn conformant(&self, tag: &Tag) -> bool {
         let valid_script = tag
             .script()
@@ -105,7 +779,64 @@ impl LangTags {
             .region()
             .map(|s| self.regions.contains(s))
             .unwrap_or(true);
-        valid_script && valid_region
+        valid_script
+            && valid_region
+            && !self
+                .orthographic_normal_form(tag)
+                .is_some_and(TagSet::is_obsolete)
+    }
+
+    // As conformant, but also requiring variants to be known, extensions to
+    // sit under single letter namespaces, and any private use subtags not
+    // to be those of a registered tag for some other language.
+    pub fn conformant_strict(&self, tag: &Tag) -> bool {
+        self.why_not_conformant(tag).is_empty()
+    }
+
+    pub fn why_not_conformant(&self, tag: &Tag) -> Vec<ConformanceError> {
+        let mut errors = Vec::new();
+        let ts = self.orthographic_normal_form(tag);
+        if let Some(script) = tag.script().filter(|s| !self.scripts.contains(*s)) {
+            errors.push(ConformanceError::UnknownScript(script.into()));
+        }
+        if let Some(region) = tag.region().filter(|r| !self.regions.contains(*r)) {
+            errors.push(ConformanceError::UnknownRegion(region.into()));
+        }
+        if ts.is_some_and(TagSet::is_obsolete) {
+            errors.push(ConformanceError::Obsolete);
+        }
+
+        let latin = tag.script().or_else(|| ts.and_then(|ts| ts.script())) == Some("Latn");
+        errors.extend(
+            tag.variants()
+                .filter(|&v| {
+                    !(self.variants.contains(v)
+                        || latin && self.latn_variants.contains(v)
+                        || ts.is_some_and(|ts| ts.variants.iter().any(|tv| tv == v)))
+                })
+                .map(|v| ConformanceError::UnknownVariant(v.into())),
+        );
+        errors.extend(tag.extensions().map(|e| e.to_string()).filter_map(|e| {
+            let ns = e.split('-').next().unwrap_or_default();
+            let singleton = ns.len() == 1
+                && ns.bytes().all(|b| b.is_ascii_alphanumeric())
+                && !ns.eq_ignore_ascii_case("x");
+            (!singleton).then_some(ConformanceError::InvalidExtension(e))
+        }));
+
+        if let Some(private) = tag.private() {
+            let registered = self
+                .tagsets
+                .iter()
+                .filter(|reg| reg.full.private() == Some(private))
+                .collect::<Vec<_>>();
+            if !registered.is_empty()
+                && !ts.is_some_and(|ts| registered.iter().any(|&reg| ptr::eq(reg, ts)))
+            {
+                errors.push(ConformanceError::PrivateUseConflict(private.into()));
+            }
+        }
+        errors
     }
 
     fn valid_region(ts: &TagSet, region: Option<&str>) -> bool {
@@ -122,7 +853,7 @@ impl LangTags {
                 let v = v.to_owned();
                 ts.variants.contains(&v)
                     || self.variants.contains(&v)
-                    || (!ts.nophonvars
+                    || (ts.supports_phonetic_variants()
                         && (ts.tag.script().is_none() || ts.script() == Some("Latn"))
                         && self.latn_variants.contains(&v))
             })
@@ -145,15 +876,15 @@ impl LangTags {
             .full
             .get(&key.to_string())
             .or_else(|| {
-                key.set_private("");
+                key.clear_private();
                 self.full.get(&key.to_string())
             })
             .or_else(|| {
-                key.set_extensions([]);
+                key.clear_extensions();
                 self.full.get(&key.to_string())
             })
             .or_else(|| {
-                key.set_variants([]);
+                key.clear_variants();
                 self.full.get(&key.to_string())
             })
             .or_else(|| {
@@ -163,9 +894,7 @@ impl LangTags {
         let idx = *idx? as usize;
         let ts = self.tagsets.get(idx)?;
 
-        let private_is_valid = ts.full.private().map_or(true, |ts_priv| {
-            tag.private().is_some_and(|tag_priv| tag_priv == ts_priv)
-        });
+        let private_is_valid = ts.full.private().is_none() || ts.full.private() == tag.private();
         if key == *tag
             || LangTags::valid_region(ts, tag.region())
                 && self.valid_variants(ts, tag)
@@ -178,12 +907,127 @@ impl LangTags {
         }
     }
 
+    // Truncate the tag one subtag at a time, least significant first, in the
+    // manner of RFC 4647 lookup: private use, extensions, variants, region,
+    // script and finally extlangs, until an orthographic normal form is found.
+    pub fn lookup_with_fallback<'a>(&'a self, tag: &Tag) -> Option<LookupResult<'a>> {
+        let mut key = tag.clone();
+        let mut stripped_components = 0;
+        loop {
+            if let Some(tagset) = self.orthographic_normal_form(&key) {
+                return Some(LookupResult {
+                    tagset,
+                    matched_tag: key,
+                    stripped_components,
+                });
+            }
+            if key.private().is_some() {
+                key.clear_private();
+            } else if let Some(ext) = key.extensions().last().map(|e| e.to_string()) {
+                key.remove_extension(&ext);
+            } else if key.pop_variant().is_some() {
+            } else if key.region().is_some() {
+                key.set_region("");
+            } else if key.script().is_some() {
+                key.set_script("");
+            } else if let Some(lang) = key.lang().rsplit_once('-').map(|(l, _)| l.to_owned()) {
+                key.set_lang(&lang);
+            } else {
+                return None;
+            }
+            stripped_components += 1;
+        }
+    }
+
+    // Every distinct tagset tag could fall back to, best match first, found by
+    // stripping phonetic variants, then all other variants, extensions and
+    // private use, then region and finally script.
+    pub fn orthographic_chain<'a>(&'a self, tag: &Tag) -> impl Iterator<Item = &'a TagSet> {
+        let mut chain = SmallVec::<[&TagSet; 4]>::new();
+        let mut push = |key: &Tag| {
+            if let Some(ts) = self.orthographic_normal_form(key) {
+                if !chain.iter().any(|&c| ptr::eq(c, ts)) {
+                    chain.push(ts);
+                }
+            }
+        };
+
+        let mut key = tag.clone();
+        push(&key);
+        let others = tag
+            .variants()
+            .filter(|&v| !self.latn_variants.contains(v))
+            .collect::<Vec<_>>();
+        if others.len() != tag.count_variants() {
+            key.set_variants(others);
+            push(&key);
+        }
+        if key.has_variants() || key.has_extensions() || key.private().is_some() {
+            key.clear_variants();
+            key.clear_extensions();
+            key.clear_private();
+            push(&key);
+        }
+        if key.region().is_some() {
+            key.set_region("");
+            push(&key);
+        }
+        if key.script().is_some() {
+            key.set_script("");
+            push(&key);
+        }
+        chain.into_iter()
+    }
+
+    pub fn find_any_form<'a>(&'a self, tag: &Tag) -> Option<&'a TagSet> {
+        self.all_tags
+            .as_ref()
+            .and_then(|index| index.get(&tag.to_string()))
+            .map(|&i| &self.tagsets[i as usize])
+            .or_else(|| self.orthographic_normal_form(tag))
+    }
+
+    // The tagset Windows uses tag for, falling back to the tagset tag
+    // normalises to, since Windows names such as en-US are often the region
+    // specific forms of a tagset whose windows field is simply en.
+    pub fn find_by_windows_tag<'a>(&'a self, tag: &Tag) -> Option<&'a TagSet> {
+        self.tagsets
+            .iter()
+            .find(|ts| ts.windows == *tag)
+            .or_else(|| self.orthographic_normal_form(tag))
+    }
+
+    pub fn find_by_lcid(&self, lcid: u32) -> Option<&TagSet> {
+        let tag = Tag::from_windows_tag(windows_locale::windows_tag(lcid)?).ok()?;
+        self.find_by_windows_tag(&tag)
+    }
+
+    // Pick the name for tag best suited to a reader of display_locale: the
+    // local name for speakers of the language itself, the English name for
+    // English or unknown locales, otherwise the first alternative local name.
+    pub fn best_name<'a>(&'a self, tag: &Tag, display_locale: &Tag) -> Option<&'a str> {
+        let ts = self.orthographic_normal_form(tag)?;
+        let non_empty = |s: &'a String| Some(s.as_str()).filter(|s| !s.is_empty());
+        let name = if display_locale.lang().eq_ignore_ascii_case(ts.lang()) {
+            non_empty(&ts.localname)
+        } else if display_locale.lang().eq_ignore_ascii_case("en")
+            || self.orthographic_normal_form(display_locale).is_none()
+        {
+            None
+        } else {
+            ts.localnames.first().and_then(non_empty)
+        };
+        name.or_else(|| non_empty(&ts.name))
+    }
+
     pub fn locale_normal_form(&self, tag: &Tag) -> Option<TagSet> {
         self.orthographic_normal_form(tag).map(|ortho_tagset| {
             let mut ts = ortho_tagset.clone();
             if let Some(region) = tag.region() {
-                let ri = ts.regions.iter().position(|x| x == region).unwrap();
-                ts.regions[ri] = ts.region().unwrap().to_owned();
+                // Already in the tagset's own region there is nothing to swap.
+                if let Some(ri) = ts.regions.iter().position(|x| x == region) {
+                    ts.regions[ri] = ts.region().unwrap().to_owned();
+                }
                 ts.full.set_region(region);
                 ts.tag.set_region(region);
                 for i in (0..ts.tags.len()).rev() {
@@ -199,6 +1043,17 @@ impl LangTags {
         })
     }
 
+    // Whether the tagset tag resolves to, in any of its forms, has LDML in
+    // the SLDR. Unknown tags have none.
+    pub fn is_sldr_enabled_for(&self, tag: &Tag) -> bool {
+        self.orthographic_normal_form(tag).is_some_and(|ts| ts.sldr)
+    }
+
+    // As is_sldr_enabled_for, but resolving tag by its locale normal form.
+    pub fn is_sldr_enabled_for_locale_form(&self, tag: &Tag) -> bool {
+        self.locale_normal_form(tag).is_some_and(|ts| ts.sldr)
+    }
+
     pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&Tag, &TagSet)> + Clone {
         self.tagsets
             .iter()
@@ -208,13 +1063,206 @@ impl LangTags {
     pub fn tagsets(&self) -> impl DoubleEndedIterator<Item = &TagSet> + Clone {
         self.tagsets.iter()
     }
+
+    pub fn find_for_script_region<'a>(
+        &'a self,
+        script: &str,
+        region: &str,
+    ) -> impl Iterator<Item = &'a TagSet> {
+        self.script_regions
+            .get(&(script.to_owned(), region.to_owned()))
+            .into_iter()
+            .flatten()
+            .map(|&i| &self.tagsets[i as usize])
+    }
+
+    // Every tagset whose primary language subtag is lang.
+    pub fn iter_by_language<'a>(&'a self, lang: &str) -> impl Iterator<Item = &'a TagSet> {
+        self.languages
+            .get(lang)
+            .into_iter()
+            .flatten()
+            .map(|&i| &self.tagsets[i as usize])
+    }
+
+    // Every tagset with the Registry of Dialects code rod.
+    pub fn find_by_rod<'a>(&'a self, rod: &str) -> impl Iterator<Item = &'a TagSet> {
+        self.rods
+            .get(rod)
+            .into_iter()
+            .flatten()
+            .map(|&i| &self.tagsets[i as usize])
+    }
+
+    // The member tagsets of a macrolanguage, given by its ISO 639-3 code or
+    // language subtag, eg. ara or ar for arb, apc and the other Arabics.
+    pub fn tagsets_for_macrolang<'a>(
+        &'a self,
+        macrolang_code: &str,
+    ) -> impl Iterator<Item = &'a TagSet> {
+        let macrolang = self
+            .tagsets
+            .iter()
+            .find(|ts| ts.iso639_3.eq_ignore_ascii_case(macrolang_code))
+            .map_or(macrolang_code, |ts| ts.tag.lang())
+            .to_owned();
+        self.tagsets
+            .iter()
+            .filter(move |ts| ts.macrolang.eq_ignore_ascii_case(&macrolang))
+    }
+
+    // The tagset for the macrolanguage tag belongs to, if any, eg. tmh for
+    // thv-Latn-DZ.
+    pub fn macrolang_of<'a>(&'a self, tag: &Tag) -> Option<&'a TagSet> {
+        let ts = self.orthographic_normal_form(tag)?;
+        if ts.macrolang.is_empty() {
+            return None;
+        }
+        self.orthographic_normal_form(&Tag::with_lang(&ts.macrolang))
+    }
+
+    // The distinct primary language subtags, in sorted order.
+    pub fn languages(&self) -> impl Iterator<Item = &str> {
+        let mut languages: Vec<_> = self.languages.keys().map(String::as_str).collect();
+        languages.sort_unstable();
+        languages.into_iter()
+    }
+
+    pub fn deprecated_tagsets(&self) -> impl Iterator<Item = &TagSet> {
+        self.deprecated.iter().map(|&i| &self.tagsets[i as usize])
+    }
+
+    // When tag resolves to an obsolete tagset, the first current tagset it
+    // falls back to once subtags are stripped, eg. ab for ab-Geor.
+    pub fn has_replacement(&self, tag: &Tag) -> Option<&TagSet> {
+        let mut chain = self.orthographic_chain(tag);
+        let obsolete = chain.next().filter(|ts| ts.is_obsolete())?;
+        chain.find(|&ts| !ts.is_obsolete() && !ptr::eq(ts, obsolete))
+    }
+
+    // The LDML file in sldr_dir for tag, trying each tag in its tagset
+    // from the most to the least specific.
+    pub fn resolve_sldr_path(&self, tag: &Tag, sldr_dir: &Path) -> Option<PathBuf> {
+        let tagset = self.orthographic_normal_form(tag)?;
+        let (lang, _) = tagset.full.split_at_script();
+        let dir = sldr_dir.join(&lang[0..1]);
+        tagset
+            .iter()
+            .map(|tag| dir.join(tag.to_string().replace('-', "_") + ".xml"))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rfind(|path| path.exists())
+    }
+
+    // Cross check the database against the flat and unflat SLDR trees: every
+    // tagset with SLDR data should have an LDML file in both, and every LDML
+    // file should be named for a tag the database knows. An unreadable tree
+    // has no files.
+    pub fn validate_tag_file_correspondence(
+        &self,
+        sldr_flat: &Path,
+        sldr_unflat: &Path,
+    ) -> ValidationReport {
+        let trees = [sldr_flat, sldr_unflat];
+        let missing_files = self
+            .tagsets_with_sldr()
+            .filter(|ts| {
+                trees
+                    .iter()
+                    .any(|dir| self.resolve_sldr_path(&ts.full, dir).is_none())
+            })
+            .map(|ts| ts.full.clone())
+            .collect();
+
+        let mut extra_files: Vec<_> = trees
+            .iter()
+            .filter_map(|dir| fs::read_dir(dir).ok())
+            .flatten()
+            .flatten()
+            .filter_map(|entry| fs::read_dir(entry.path()).ok())
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "xml"))
+            .filter(|path| {
+                path.file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.replace('_', "-").parse::<Tag>().ok())
+                    .and_then(|tag| self.orthographic_normal_form(&tag))
+                    .is_none()
+            })
+            .collect();
+        extra_files.sort_unstable();
+
+        ValidationReport {
+            missing_files,
+            extra_files,
+            mismatched_revids: Vec::new(),
+        }
+    }
+
+    // How many tagsets have each script in their full tag.
+    pub fn tagset_count_by_script(&self) -> Map<&str, usize> {
+        self.count_by(|ts| ts.full.script())
+    }
+
+    // How many tagsets have each region in their full tag. Tagsets whose
+    // full tag has no region are not counted.
+    pub fn tagset_count_by_region(&self) -> Map<&str, usize> {
+        self.count_by(|ts| ts.full.region())
+    }
+
+    fn count_by<'a>(&'a self, key: impl Fn(&'a TagSet) -> Option<&'a str>) -> Map<&'a str, usize> {
+        let mut counts = Map::new();
+        for k in self.tagsets.iter().filter_map(key) {
+            *counts.entry(k).or_default() += 1;
+        }
+        counts
+    }
+
+    pub fn tagsets_with_sldr(&self) -> impl DoubleEndedIterator<Item = &TagSet> + Clone {
+        self.tagsets.iter().filter(|ts| ts.sldr)
+    }
+
+    pub fn tagsets_without_sldr(&self) -> impl DoubleEndedIterator<Item = &TagSet> + Clone {
+        self.tagsets.iter().filter(|ts| !ts.sldr)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tagsets.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.tagsets.is_empty()
+    }
+
+    pub fn count_sldr(&self) -> usize {
+        self.tagsets_with_sldr().count()
+    }
+
+    pub fn count_non_sldr(&self) -> usize {
+        self.tagsets_without_sldr().count()
+    }
+}
+
+impl FromStr for LangTags {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LangTags::from_str_slice(s)
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{Header, TagSet};
+    use super::{
+        ConflictStrategy, ConsistencyError, ErrorKind, Header, LangTags, MergeError, TagSet,
+    };
     use language_tag::Tag;
-    use serde_json::{json, Value};
+    use serde_json::{error::Category, json, Value};
+    use std::{fs, io, str::FromStr};
 
     #[test]
     fn headers() {
@@ -351,4 +1399,353 @@ mod test {
             }
         )
     }
+
+    #[test]
+    fn from_str() {
+        let src = json!([
+            {
+                "api": "1.3",
+                "date": "2023-02-20",
+                "tag": "_version"
+            },
+            {
+                "full": "aa-Latn-ET",
+                "iso639_3": "aar",
+                "name": "Afar",
+                "region": "ET",
+                "script": "Latn",
+                "sldr": true,
+                "tag": "aa",
+                "tags": [ "aa-ET", "aa-Latn" ],
+                "windows": "aa-Latn-ET"
+            }
+        ])
+        .to_string();
+        let test = src.parse::<LangTags>().expect("LangTags test case.");
+        assert_eq!(
+            test,
+            LangTags::from_reader(src.as_bytes()).expect("LangTags test case.")
+        );
+        assert_eq!(test.len(), 1);
+        assert!("hang on this isn't JSON!".parse::<LangTags>().is_err());
+    }
+
+    #[test]
+    fn errors() {
+        let json_error = |src: &str| {
+            let err = src.parse::<LangTags>().expect_err("invalid langtags.json");
+            assert!(err.is_json_error());
+            assert!(!err.is_missing_header());
+            assert_eq!(err.missing_header_name(), None);
+            let ErrorKind::Json(json) = err.kind() else {
+                unreachable!()
+            };
+            assert_eq!(err.clone(), err);
+            json.inner().classify()
+        };
+
+        assert_eq!(json_error("hang on this isn't JSON!"), Category::Syntax);
+        assert_eq!(json_error(r#"[{"tag": "aa"}]"#), Category::Data);
+        assert_eq!(
+            json_error(r##"[{"full": "#aa", "tag": "aa", "windows": "aa"}]"##),
+            Category::Data
+        );
+        // Errors report where in the file the bad entry is.
+        let src = "[\n{\"tag\": \"_version\", \"api\": \"1.3\", \"date\": \"2023-02-20\"},\n{\"tag\": \"aa\"}]";
+        let err = src.parse::<LangTags>().expect_err("invalid langtags.json");
+        let ErrorKind::Json(json) = err.kind() else {
+            panic!("expected a JSON error: {err:?}")
+        };
+        assert_eq!(json.inner().classify(), Category::Data);
+        assert_eq!(json.inner().line(), 3);
+        // The same mistake in the same place is the same error.
+        assert_eq!(src.parse::<LangTags>().unwrap_err(), err);
+        assert_ne!(r#"[{"tag": "aa"}]"#.parse::<LangTags>().unwrap_err(), err);
+        assert_eq!(
+            json_error(r#"[{"full": "aa-Latn-ET", "tag": "aa", "windows": "aa-Latn-ET"}] []"#),
+            Category::Syntax
+        );
+
+        // A header missing fields it needs is reported by name.
+        let err = r#"[{"tag": "_version", "api": "1.3"}]"#
+            .parse::<LangTags>()
+            .expect_err("incomplete header");
+        assert!(err.is_missing_header());
+        assert!(!err.is_json_error());
+        assert_eq!(err.missing_header_name(), Some("_version"));
+        assert_eq!(err.kind(), &ErrorKind::MissingHeader("_version".into()));
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::InvalidData);
+        // Every header is optional.
+        assert!(LangTags::from_str("[]")
+            .expect("empty langtags.json")
+            .is_empty());
+    }
+
+    #[test]
+    fn to_writer() {
+        let src = json!([
+            {
+                "regions": [ "AA" ],
+                "scripts": [ "Qaaa" ],
+                "tag": "_conformance"
+            },
+            {
+                "tag": "_globalvar",
+                "variants": [ "simple" ]
+            },
+            {
+                "tag": "_phonvar",
+                "variants": [ "fonipa" ]
+            },
+            {
+                "api": "1.3",
+                "date": "2023-02-20",
+                "tag": "_version"
+            },
+            {
+                "full": "aa-Latn-ET",
+                "iso639_3": "aar",
+                "name": "Afar",
+                "region": "ET",
+                "script": "Latn",
+                "sldr": true,
+                "tag": "aa",
+                "tags": [ "aa-ET", "aa-Latn" ],
+                "windows": "aa-Latn-ET"
+            },
+            {
+                "full": "aa-Arab-ET",
+                "iso639_3": "aar",
+                "name": "Afar",
+                "region": "ET",
+                "regions": [ "DJ", "ER" ],
+                "script": "Arab",
+                "sldr": false,
+                "tag": "aa-Arab",
+                "windows": "aa-Arab-ET"
+            }
+        ])
+        .to_string();
+        let test = src.parse::<LangTags>().expect("LangTags test case.");
+
+        let mut out = Vec::new();
+        test.to_writer(&mut out).expect("written LangTags");
+        assert_eq!(
+            LangTags::from_reader(&out[..]).expect("reparsed LangTags"),
+            test
+        );
+
+        let mut out = Vec::new();
+        test.to_writer_filtered(&mut out, |ts| ts.sldr)
+            .expect("written LangTags");
+        let sldr_only = LangTags::from_reader(&out[..]).expect("reparsed LangTags");
+        assert_eq!(sldr_only.len(), 1);
+        assert_eq!(sldr_only.count_non_sldr(), 0);
+    }
+
+    #[test]
+    fn resolve_sldr_path() {
+        let langtags: LangTags = json!([
+            {
+                "full": "aa-Latn-ET",
+                "sldr": true,
+                "tag": "aa",
+                "tags": ["aa-ET", "aa-Latn"],
+                "windows": "aa-Latn-ET"
+            }
+        ])
+        .to_string()
+        .parse()
+        .expect("LangTags");
+        let tmp = tempfile::tempdir().expect("temp dir");
+        let sldr = tmp.path();
+        let aa = Tag::from_str("aa").unwrap();
+        let aa_et = Tag::from_str("aa-ET").unwrap();
+        fs::create_dir_all(sldr.join("a")).expect("SLDR test directory");
+
+        assert_eq!(langtags.resolve_sldr_path(&aa, sldr), None);
+        fs::write(sldr.join("a/aa.xml"), "").unwrap();
+        assert_eq!(
+            langtags.resolve_sldr_path(&aa_et, sldr),
+            Some(sldr.join("a/aa.xml"))
+        );
+        fs::write(sldr.join("a/aa_Latn_ET.xml"), "").unwrap();
+        assert_eq!(
+            langtags.resolve_sldr_path(&aa, sldr),
+            Some(sldr.join("a/aa_Latn_ET.xml"))
+        );
+        assert_eq!(
+            langtags.resolve_sldr_path(&Tag::from_str("qqq").unwrap(), sldr),
+            None
+        );
+    }
+
+    #[test]
+    fn verify_consistency() {
+        let tagset: TagSet = serde_json::from_value(json!({
+            "full": "aa-Latn-ET",
+            "regions": ["DJ", "ER"],
+            "tag": "aa",
+            "tags": ["aa-ET", "aa-Latn"],
+            "windows": "aa-Latn-ET"
+        }))
+        .expect("TagSet");
+        let consistent = LangTags {
+            tagsets: vec![tagset.clone()],
+            ..Default::default()
+        };
+        assert_eq!(consistent.verify_consistency(), []);
+
+        let mut broken = tagset;
+        broken.tags.push(Tag::from_str("aa-Ethi").unwrap());
+        broken.regions.push("ET".into());
+        let inconsistent = LangTags {
+            tagsets: vec![broken],
+            ..Default::default()
+        };
+        let full = Tag::from_str("aa-Latn-ET").unwrap();
+        assert_eq!(
+            inconsistent.verify_consistency(),
+            [
+                ConsistencyError::ScriptMismatch {
+                    tagset_full: full.clone(),
+                    computed_script: "Ethi".into(),
+                    stored_script: "Latn".into(),
+                },
+                ConsistencyError::RegionInExtraList {
+                    tagset_full: full,
+                    region: "ET".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn merge() {
+        let tagset = |full: &str, tag: &str, name: &str| json!({"full": full, "tag": tag, "name": name, "windows": full, "sldr": true});
+        let header = json!({"api": "1.2.1", "date": "2023-01-01", "tag": "_version"});
+        let production = || -> LangTags {
+            json!([
+                header,
+                tagset("aa-Latn-ET", "aa", "Afar"),
+                tagset("en-Latn-US", "en", "English")
+            ])
+            .to_string()
+            .parse()
+            .expect("LangTags")
+        };
+        let staging: LangTags = json!([
+            header,
+            tagset("en-Latn-US", "en", "English (staging)"),
+            tagset("thv-Latn-DZ", "thv", "Tamahaq")
+        ])
+        .to_string()
+        .parse()
+        .expect("LangTags");
+        let names = |lts: &LangTags| {
+            lts.tagsets()
+                .map(|ts| format!("{}: {}", ts.full, ts.name))
+                .collect::<Vec<_>>()
+        };
+        let thv = Tag::from_str("thv").unwrap();
+
+        let mut merged = production();
+        merged.merge_from(&staging, ConflictStrategy::PreferSelf);
+        assert_eq!(
+            names(&merged),
+            [
+                "aa-Latn-ET: Afar",
+                "en-Latn-US: English",
+                "thv-Latn-DZ: Tamahaq"
+            ]
+        );
+        assert!(merged.orthographic_normal_form(&thv).is_some());
+
+        let mut merged = production();
+        merged.merge_from(&staging, ConflictStrategy::PreferOther);
+        assert_eq!(
+            names(&merged),
+            [
+                "aa-Latn-ET: Afar",
+                "en-Latn-US: English (staging)",
+                "thv-Latn-DZ: Tamahaq"
+            ]
+        );
+
+        let mut merged = production();
+        assert_eq!(
+            merged.try_merge_from(&staging, ConflictStrategy::Error),
+            Err(MergeError {
+                conflicts: vec![Tag::from_str("en-Latn-US").unwrap()]
+            })
+        );
+        assert_eq!(merged, production());
+        let conflicting = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            merged.merge_from(&staging, ConflictStrategy::Error)
+        }));
+        assert!(conflicting.is_err());
+        // Without any conflicts, Error merges as the other strategies do.
+        let mut merged = production();
+        merged.merge_from(&LangTags::default(), ConflictStrategy::Error);
+        assert_eq!(merged, production());
+
+        let mut merged = production();
+        merged.extend(staging);
+        assert_eq!(
+            names(&merged),
+            [
+                "aa-Latn-ET: Afar",
+                "en-Latn-US: English (staging)",
+                "thv-Latn-DZ: Tamahaq"
+            ]
+        );
+        assert!(merged.orthographic_normal_form(&thv).is_some());
+    }
+
+    #[test]
+    fn set_operations() {
+        let tagset = |full: &str, tag: &str| json!({"full": full, "tag": tag, "windows": full, "sldr": true});
+        let header = json!({"api": "1.2.1", "date": "2023-01-01", "tag": "_version"});
+        let production: LangTags = json!([
+            header,
+            tagset("aa-Latn-ET", "aa"),
+            tagset("aa-Arab-ET", "aa-Arab"),
+            tagset("en-Latn-US", "en")
+        ])
+        .to_string()
+        .parse()
+        .expect("LangTags");
+        let staging: LangTags = json!([
+            header,
+            tagset("aa-Latn-ET", "aa"),
+            tagset("en-Latn-US", "en-Latn"),
+            tagset("thv-Latn-DZ", "thv")
+        ])
+        .to_string()
+        .parse()
+        .expect("LangTags");
+        let fulls = |lts: &LangTags| {
+            lts.tagsets()
+                .map(|ts| ts.full.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let common = production.intersect(&staging);
+        assert_eq!(fulls(&common), ["aa-Latn-ET", "en-Latn-US"]);
+        assert_eq!(common.version(), "1.2.1");
+        let en = Tag::from_str("en").unwrap();
+        assert_eq!(
+            common.orthographic_normal_form(&en).map(|ts| &ts.tag),
+            Some(&en)
+        );
+        assert_eq!(
+            common.orthographic_normal_form(&Tag::from_str("aa-Arab").unwrap()),
+            None
+        );
+
+        assert_eq!(fulls(&production.difference(&staging)), ["aa-Arab-ET"]);
+        assert_eq!(fulls(&staging.difference(&production)), ["thv-Latn-DZ"]);
+        assert!(production.difference(&production).is_empty());
+        assert_eq!(production.intersect(&production), production);
+    }
 }
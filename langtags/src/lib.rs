@@ -1,3 +1,6 @@
+#[cfg(feature = "binary-cache")]
+pub mod binary;
 pub mod json;
 pub mod tagset;
 pub mod text;
+pub mod windows_locale;
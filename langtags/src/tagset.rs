@@ -1,50 +1,66 @@
+#[cfg(feature = "binary-cache")]
+use bincode::{Decode, Encode};
 use language_tag::Tag;
-use serde::Deserialize;
-use std::{borrow::Borrow, fmt::Display, iter::once, ops::Deref, path::PathBuf};
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Borrow, cmp::Reverse, fmt::Display, iter::once, mem::size_of, ops::Deref, path::PathBuf,
+};
 
-#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[cfg_attr(feature = "binary-cache", derive(Encode, Decode))]
 // #[serde(default)]
 pub struct TagSet {
     // Required keys
     pub full: Tag,
     #[serde(default)]
     pub iana: Vec<String>,
+    // Omitted when false on output, so it must be defaultable on input.
+    #[serde(default, skip_serializing_if = "is_false")]
     pub sldr: bool,
     pub tag: Tag,
     pub windows: Tag,
 
     // Defaultable keys
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub iso639_3: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub latnnames: Vec<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub localname: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub localnames: Vec<String>,
-    #[serde(default)]
+    // The language subtag of the macrolanguage this belongs to, eg. ar.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub macrolang: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub name: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub names: Vec<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_false")]
     pub nophonvars: bool,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_false")]
     pub obsolete: bool,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub regionname: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub regions: Vec<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub rod: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_false")]
     pub suppress: bool,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub tags: Vec<Tag>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_false")]
     pub unwritten: bool,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub variants: Vec<String>,
 }
+
+#[inline]
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
 pub trait Iter<T>: Iterator<Item = T> + Clone + DoubleEndedIterator {}
 impl<T> Iter<Tag> for T where T: Iterator<Item = Tag> + Clone + DoubleEndedIterator {}
 impl<'a, T> Iter<&'a Tag> for T where T: Iterator<Item = &'a Tag> + Clone + DoubleEndedIterator {}
@@ -63,6 +79,19 @@ impl TagSet {
             .chain(once(&self.full))
     }
 
+    // The tags in this set from most to least specific, by component count.
+    // Tags of equal specificity keep their iter order.
+    pub fn iter_by_specificity(&self) -> impl Iter<&Tag> {
+        let mut tags: Vec<_> = self.iter().collect();
+        tags.sort_by_key(|tag| Reverse(tag.component_count()));
+        tags.into_iter()
+    }
+
+    #[inline]
+    pub fn canonical_tag(&self) -> &Tag {
+        &self.full
+    }
+
     pub fn region_sets(&self) -> impl DoubleEndedIterator<Item = impl Iter<Tag> + '_> + Clone {
         let prototypes = self
             .iter()
@@ -82,6 +111,8 @@ impl TagSet {
     ) -> impl DoubleEndedIterator<Item = impl Iter<Tag> + '_> + Clone + '_ {
         let prototypes = once(self.iter().cloned().collect::<Vec<Tag>>())
             .chain(self.region_sets().map(|rs| rs.collect::<Vec<Tag>>()));
+        // A variant that would take a tag past Tag::MAX_LEN panics in
+        // push_variant, rather than the tag quietly going missing.
         prototypes.flat_map(|prototype| {
             self.variants.iter().map(move |variant| {
                 prototype.clone().into_iter().map(move |mut tag| {
@@ -92,6 +123,79 @@ impl TagSet {
         })
     }
 
+    pub fn all_names(&self) -> impl Iterator<Item = &str> {
+        once(&self.name)
+            .chain(once(&self.localname))
+            .chain(&self.localnames)
+            .chain(&self.names)
+            .chain(&self.latnnames)
+            .map(String::as_str)
+            .filter(|name| !name.is_empty())
+    }
+
+    // Rough bytes allocated on the heap by this tagset's tags, strings and
+    // lists, from their capacities.
+    pub fn heap_size(&self) -> usize {
+        fn strings(v: &Vec<String>) -> usize {
+            v.capacity() * size_of::<String>() + v.iter().map(String::capacity).sum::<usize>()
+        }
+        self.full.heap_size()
+            + self.tag.heap_size()
+            + self.windows.heap_size()
+            + self.tags.capacity() * size_of::<Tag>()
+            + self.tags.iter().map(Tag::heap_size).sum::<usize>()
+            + [
+                &self.iso639_3,
+                &self.localname,
+                &self.macrolang,
+                &self.name,
+                &self.regionname,
+                &self.rod,
+            ]
+            .iter()
+            .map(|s| s.capacity())
+            .sum::<usize>()
+            + [
+                &self.iana,
+                &self.latnnames,
+                &self.localnames,
+                &self.names,
+                &self.regions,
+                &self.variants,
+            ]
+            .into_iter()
+            .map(strings)
+            .sum::<usize>()
+    }
+
+    #[inline]
+    pub fn supports_phonetic_variants(&self) -> bool {
+        !self.nophonvars
+    }
+
+    // The script subtag is suppressed (IANA Suppress-Script) in the
+    // preferred form of tags for this tagset.
+    #[inline]
+    pub fn is_suppressed(&self) -> bool {
+        self.suppress
+    }
+
+    #[inline]
+    pub fn is_unwritten(&self) -> bool {
+        self.unwritten
+    }
+
+    // The Registry of Dialects code, if one is given.
+    #[inline]
+    pub fn rod_code(&self) -> Option<&str> {
+        Some(self.rod.as_str()).filter(|rod| !rod.is_empty())
+    }
+
+    #[inline]
+    pub fn is_obsolete(&self) -> bool {
+        self.obsolete
+    }
+
     pub fn sldr_file_name(&self) -> Option<PathBuf> {
         if self.sldr {
             let path = self.windows.to_string().replace('-', "_") + ".xml";
@@ -102,7 +206,18 @@ impl TagSet {
     }
 }
 
+// An equivalence set as one line of a ?query=tags response, most specific
+// tag first as iter_by_specificity orders them.
 pub fn render_equivalence_set<I: IntoIterator>(set: I) -> String
+where
+    I::Item: Borrow<Tag>,
+{
+    let mut tags: Vec<_> = set.into_iter().collect();
+    tags.sort_by_key(|tag| Reverse(tag.borrow().component_count()));
+    join_tags(tags)
+}
+
+fn join_tags<I: IntoIterator>(set: I) -> String
 where
     I::Item: Borrow<Tag>,
 {
@@ -112,9 +227,10 @@ where
         .unwrap()
 }
 
+// Tags in iter order, the tag to full tag form langtags.txt uses.
 impl Display for TagSet {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        f.write_str(&render_equivalence_set(self.iter()))
+        f.write_str(&join_tags(self.iter()))
     }
 }
 
@@ -128,7 +244,7 @@ impl Deref for TagSet {
 
 #[cfg(test)]
 mod test {
-    use super::TagSet;
+    use super::{render_equivalence_set, TagSet};
     use language_tag::Tag;
     use serde_json::json;
 
@@ -200,6 +316,58 @@ mod test {
         )
     }
 
+    #[test]
+    fn round_trip() {
+        let src = json!({
+            "full": "aa-Arab-ET",
+            "iana": [ "Afar" ],
+            "iso639_3": "aar",
+            "name": "Afar",
+            "nophonvars": true,
+            "regionname": "Ethiopia",
+            "regions": [ "DJ", "ER" ],
+            "sldr": true,
+            "tag": "aa-Arab",
+            "tags": [ "aa-Arab-DJ" ],
+            "windows": "aa-Arab-ET"
+        });
+        let ts: TagSet = serde_json::from_value(src.clone()).expect("TagSet value");
+        let value = serde_json::to_value(&ts).expect("Serialized TagSet");
+        assert_eq!(value, src);
+        assert_eq!(
+            serde_json::from_value::<TagSet>(value).expect("Round tripped TagSet"),
+            ts
+        );
+
+        let ts = TagSet { sldr: false, ..ts };
+        let value = serde_json::to_value(&ts).expect("Serialized TagSet");
+        assert!(value.get("sldr").is_none());
+        assert!(value.get("obsolete").is_none());
+        assert_eq!(
+            serde_json::from_value::<TagSet>(value).expect("Round tripped TagSet"),
+            ts
+        );
+    }
+
+    #[test]
+    fn all_names() {
+        let ts: TagSet = serde_json::from_value(json!({
+            "full": "aa-Latn-ET",
+            "localname": "Qafar",
+            "localnames": [ "Qafar af" ],
+            "name": "Afar",
+            "names": [ "Adal", "Danakil" ],
+            "sldr": true,
+            "tag": "aa",
+            "windows": "aa-Latn-ET"
+        }))
+        .expect("TagSet value");
+        assert_eq!(
+            ts.all_names().collect::<Vec<_>>(),
+            ["Afar", "Qafar", "Qafar af", "Adal", "Danakil"]
+        );
+    }
+
     #[test]
     fn display_trait() {
         let test: Vec<TagSet> = serde_json::from_str(
@@ -250,4 +418,34 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn specificity() {
+        let ts: TagSet = serde_json::from_value(json!({
+            "full": "aa-Latn-ET",
+            "sldr": true,
+            "tag": "aa",
+            "tags": [ "aa-ET", "aa-Latn" ],
+            "variants": [ "fonipa" ],
+            "windows": "aa-Latn-ET"
+        }))
+        .expect("TagSet value");
+        assert_eq!(ts.canonical_tag(), &ts.full);
+        assert_eq!(
+            ts.iter_by_specificity()
+                .map(Tag::to_string)
+                .collect::<Vec<_>>(),
+            ["aa-Latn-ET", "aa-ET", "aa-Latn", "aa"]
+        );
+        assert_eq!(
+            render_equivalence_set(ts.iter()),
+            "aa-Latn-ET=aa-ET=aa-Latn=aa"
+        );
+        assert_eq!(
+            ts.variant_sets()
+                .map(render_equivalence_set)
+                .collect::<Vec<_>>(),
+            ["aa-Latn-ET-fonipa=aa-ET-fonipa=aa-Latn-fonipa=aa-fonipa"]
+        );
+    }
 }
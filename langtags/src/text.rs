@@ -1,10 +1,12 @@
-use language_tag::Tag;
+use language_tag::{ParseTagError, Tag};
 use std::{
     collections::{hash_map, HashMap, HashSet},
-    error::Error,
-    fmt::Display,
-    io::{self, BufRead},
+    error,
+    fmt::{self, Display},
+    io::{self, BufRead, Write},
+    mem::size_of,
     ops::{Deref, DerefMut, Index},
+    str::FromStr,
 };
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -20,26 +22,77 @@ pub struct LangTags {
     regions: HashSet<String>,
 }
 
-impl LangTags {
-    pub fn from_reader<R: BufRead>(reader: R) -> io::Result<Self> {
-        fn into_io_error<E>(error: E) -> io::Error
-        where
-            E: Into<Box<dyn Error + Send + Sync>>,
-        {
-            io::Error::new(io::ErrorKind::InvalidData, error)
+#[derive(Debug, PartialEq)]
+pub enum ErrorKind {
+    Parse(ParseTagError),
+}
+
+// A langtags.txt line that could not be read. from_reader reports these as
+// InvalidData io::Errors; use Error::from_io_error to get at the details.
+#[derive(Debug, PartialEq)]
+pub struct Error {
+    line: usize,
+    kind: ErrorKind,
+}
+
+impl Error {
+    pub fn from_io_error(err: &io::Error) -> Option<&Error> {
+        err.get_ref()?.downcast_ref()
+    }
+
+    // The 1-based line number of the offending line.
+    pub fn line_number(&self) -> usize {
+        self.line
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ErrorKind::Parse(err) => write!(f, "line {line}: {err}", line = self.line),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Parse(err) => Some(err),
         }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
 
+impl LangTags {
+    pub fn from_reader<R: BufRead>(reader: R) -> io::Result<Self> {
         let parse = |s: &str| s.trim_start_matches(&[' ', '*', '\t'][..]).parse::<Tag>();
         let tagsets = reader
             .lines()
-            .filter_map(|read_line| match read_line {
-                Ok(line) if line.trim().is_empty() => None,
+            .enumerate()
+            .filter_map(|(i, read_line)| match read_line {
+                // Blank lines and # comments.
+                Ok(line) if line.trim().is_empty() || line.trim_start().starts_with('#') => None,
                 Ok(line) => Some(
                     line.split('=')
                         .map(parse)
                         .collect::<Result<HashSet<Tag>, _>>()
                         .map(TagSet)
-                        .map_err(into_io_error),
+                        .map_err(|err| {
+                            Error {
+                                line: i + 1,
+                                kind: ErrorKind::Parse(err),
+                            }
+                            .into()
+                        }),
                 ),
                 Err(err) => Some(Err(err)),
             })
@@ -115,7 +168,7 @@ impl LangTags {
         })
     }
 
-    pub fn iter(&self) -> Iter {
+    pub fn iter(&self) -> Iter<'_> {
         Iter {
             inner: self.tagmap.iter(),
             tagsets: &self.tagsets,
@@ -125,6 +178,82 @@ impl LangTags {
     pub fn tagsets(&self) -> impl Iterator<Item = &TagSet> + '_ {
         self.tagsets.iter()
     }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.tagsets.iter_mut().for_each(|ts| ts.0.shrink_to_fit());
+        self.tagsets.shrink_to_fit();
+        self.tagmap.shrink_to_fit();
+        self.scripts.shrink_to_fit();
+        self.regions.shrink_to_fit();
+    }
+
+    // Rough bytes allocated by the collections held, from their capacities,
+    // and by the tags and strings in them. Allocator overhead is not included.
+    pub fn memory_usage_estimate(&self) -> usize {
+        self.tagsets.capacity() * size_of::<TagSet>()
+            + self
+                .tagsets
+                .iter()
+                .map(|ts| ts.0.capacity() * size_of::<Tag>())
+                .sum::<usize>()
+            + self.tagmap.capacity() * size_of::<(Tag, TagSetRef)>()
+            + (self.scripts.capacity() + self.regions.capacity()) * size_of::<String>()
+            + self
+                .tagsets
+                .iter()
+                .flat_map(|ts| ts.iter())
+                .chain(self.tagmap.keys())
+                .map(Tag::heap_size)
+                .sum::<usize>()
+            + self
+                .scripts
+                .iter()
+                .chain(&self.regions)
+                .map(String::capacity)
+                .sum::<usize>()
+    }
+
+    // The same estimate as memory_usage_estimate, in bytes.
+    pub fn estimate_memory_bytes(&self) -> usize {
+        self.memory_usage_estimate()
+    }
+
+    pub fn to_writer<W: Write>(&self, writer: W) -> io::Result<()> {
+        write_tagsets(
+            writer,
+            self.tagsets.iter().map(|ts| {
+                let mut tags: Vec<_> = ts.iter().collect();
+                tags.sort_unstable();
+                tags
+            }),
+        )
+    }
+}
+
+// Write each set of equivalent tags as a line in langtags.txt format.
+pub fn write_tagsets<'a, W, I, T>(mut writer: W, tagsets: I) -> io::Result<()>
+where
+    W: Write,
+    I: IntoIterator<Item = T>,
+    T: IntoIterator<Item = &'a Tag>,
+{
+    for tagset in tagsets {
+        let line = tagset
+            .into_iter()
+            .map(Tag::to_string)
+            .collect::<Vec<_>>()
+            .join(" = ");
+        writeln!(writer, "{line}")?;
+    }
+    writer.flush()
+}
+
+impl FromStr for LangTags {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LangTags::from_reader(io::Cursor::new(s))
+    }
 }
 
 impl Index<&Tag> for LangTags {
@@ -179,17 +308,41 @@ impl Display for TagSet {
 
 #[cfg(test)]
 mod test {
-    use super::{LangTags, TagSet};
+    use super::{Error, ErrorKind, LangTags, TagSet};
     use language_tag::Tag;
-    use std::{collections::HashMap, io};
+    use std::{collections::HashMap, error::Error as _, io};
 
     #[test]
     fn invalid_tagset() {
-        let test = LangTags::from_reader(&b"#*aa = *aa-ET = aa-Latn = aa-Latn-ET"[..])
-            .err()
-            .expect("io::Error from langtags test case parse.");
+        let test = LangTags::from_reader(&b"%*aa = *aa-ET = aa-Latn = aa-Latn-ET"[..])
+            .expect_err("io::Error from langtags test case parse.");
         assert_eq!(test.kind(), io::ErrorKind::InvalidData);
-        assert_eq!(test.to_string(), "error Tag at: #*aa ");
+        let err = Error::from_io_error(&test).expect("text::Error");
+        assert_eq!(err.line_number(), 1);
+        let ErrorKind::Parse(parse) = err.kind();
+        assert_eq!(parse.input, "%*aa ");
+        assert!(err.source().is_some());
+
+        let test = LangTags::from_reader(&b"aa = aa-ET\n\naa-Arab = aa-Arab-ETH"[..])
+            .expect_err("io::Error from langtags test case parse.");
+        let err = Error::from_io_error(&test).expect("text::Error");
+        assert_eq!(err.line_number(), 3);
+        assert!(Error::from_io_error(&io::Error::other("other")).is_none());
+    }
+
+    #[test]
+    fn comments() {
+        let test = LangTags::from_reader(
+            &br#"
+            # Afar
+            *aa = *aa-ET = aa-Latn = aa-Latn-ET
+            #*aa-Arab = aa-Arab-ET
+            "#[..],
+        )
+        .expect("LangTags test case.");
+        assert_eq!(test.tagsets.len(), 1);
+        assert!(test.get(&Tag::with_lang("aa")).is_some());
+        assert!(test.get(&"aa-Arab".parse().unwrap()).is_none());
     }
 
     #[test]
@@ -247,6 +400,83 @@ mod test {
         );
     }
 
+    #[test]
+    fn from_str() {
+        const SRC: &str = r#"
+            *aa = *aa-ET = aa-Latn = aa-Latn-ET
+            aa-Arab = aa-Arab-ET"#;
+        let test = SRC.parse::<LangTags>().expect("LangTags test case.");
+        assert_eq!(
+            test,
+            LangTags::from_reader(SRC.as_bytes()).expect("LangTags test case.")
+        );
+        assert_eq!(
+            "%*aa = *aa-ET"
+                .parse::<LangTags>()
+                .expect_err("io::Error from langtags test case parse.")
+                .kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn to_writer() {
+        let test = LangTags::from_reader(
+            &br#"
+            *aa = *aa-ET = aa-Latn = aa-Latn-ET
+            aa-Arab = aa-Arab-ET"#[..],
+        )
+        .expect("LangTags test case.");
+        let mut out = Vec::new();
+        test.to_writer(&mut out).expect("written LangTags");
+        assert_eq!(
+            String::from_utf8(out.clone()).unwrap(),
+            "aa = aa-ET = aa-Latn = aa-Latn-ET\naa-Arab = aa-Arab-ET\n"
+        );
+        assert_eq!(
+            LangTags::from_reader(&out[..]).expect("reparsed LangTags"),
+            test
+        );
+    }
+
+    #[test]
+    fn memory_usage_estimate() {
+        let one = LangTags::from_reader(&b"*aa = *aa-ET = aa-Latn = aa-Latn-ET"[..])
+            .expect("LangTags test case.");
+        let two = LangTags::from_reader(
+            &br#"
+            *aa = *aa-ET = aa-Latn = aa-Latn-ET
+            aa-Arab = aa-Arab-ET"#[..],
+        )
+        .expect("LangTags test case.");
+        assert!(one.memory_usage_estimate() > 0);
+        assert!(two.memory_usage_estimate() > one.memory_usage_estimate());
+        assert_eq!(two.estimate_memory_bytes(), two.memory_usage_estimate());
+    }
+
+    #[test]
+    fn shrink_to_fit() {
+        let mut test = LangTags::from_reader(
+            &br#"
+            *aa = *aa-ET = aa-Latn = aa-Latn-ET
+            aa-Arab = aa-Arab-ET"#[..],
+        )
+        .expect("LangTags test case.");
+        test.tagmap.reserve(1000);
+        let before = test.memory_usage_estimate();
+        let expected = LangTags::from_reader(
+            &br#"
+            *aa = *aa-ET = aa-Latn = aa-Latn-ET
+            aa-Arab = aa-Arab-ET"#[..],
+        )
+        .expect("LangTags test case.");
+        test.shrink_to_fit();
+        assert!(test.memory_usage_estimate() < before);
+        assert_eq!(test, expected);
+        let tag = Tag::builder().lang("aa").script("Arab").build();
+        assert_eq!(test.get(&tag), expected.get(&tag));
+    }
+
     #[test]
     fn display_trait() {
         let mut test: Vec<_> = LangTags::from_reader(
@@ -0,0 +1,133 @@
+// Windows locale identifiers (LCIDs) for the most widely used locales, mapped
+// to the locale names Windows reports for them, as listed in Microsoft's
+// [MS-LCID] and CLDR. Kept sorted by LCID for binary search.
+static LCIDS: &[(u32, &str)] = &[
+    (0x0401, "ar-SA"),
+    (0x0402, "bg-BG"),
+    (0x0403, "ca-ES"),
+    (0x0404, "zh-TW"),
+    (0x0405, "cs-CZ"),
+    (0x0406, "da-DK"),
+    (0x0407, "de-DE"),
+    (0x0408, "el-GR"),
+    (0x0409, "en-US"),
+    (0x040B, "fi-FI"),
+    (0x040C, "fr-FR"),
+    (0x040D, "he-IL"),
+    (0x040E, "hu-HU"),
+    (0x040F, "is-IS"),
+    (0x0410, "it-IT"),
+    (0x0411, "ja-JP"),
+    (0x0412, "ko-KR"),
+    (0x0413, "nl-NL"),
+    (0x0414, "nb-NO"),
+    (0x0415, "pl-PL"),
+    (0x0416, "pt-BR"),
+    (0x0418, "ro-RO"),
+    (0x0419, "ru-RU"),
+    (0x041A, "hr-HR"),
+    (0x041B, "sk-SK"),
+    (0x041C, "sq-AL"),
+    (0x041D, "sv-SE"),
+    (0x041E, "th-TH"),
+    (0x041F, "tr-TR"),
+    (0x0420, "ur-PK"),
+    (0x0421, "id-ID"),
+    (0x0422, "uk-UA"),
+    (0x0423, "be-BY"),
+    (0x0424, "sl-SI"),
+    (0x0425, "et-EE"),
+    (0x0426, "lv-LV"),
+    (0x0427, "lt-LT"),
+    (0x0429, "fa-IR"),
+    (0x042A, "vi-VN"),
+    (0x042B, "hy-AM"),
+    (0x042D, "eu-ES"),
+    (0x042F, "mk-MK"),
+    (0x0436, "af-ZA"),
+    (0x0437, "ka-GE"),
+    (0x0438, "fo-FO"),
+    (0x0439, "hi-IN"),
+    (0x043E, "ms-MY"),
+    (0x043F, "kk-KZ"),
+    (0x0440, "ky-KG"),
+    (0x0441, "sw-KE"),
+    (0x0443, "uz-Latn-UZ"),
+    (0x0445, "bn-IN"),
+    (0x0446, "pa-IN"),
+    (0x0447, "gu-IN"),
+    (0x0449, "ta-IN"),
+    (0x044A, "te-IN"),
+    (0x044B, "kn-IN"),
+    (0x044C, "ml-IN"),
+    (0x044E, "mr-IN"),
+    (0x0450, "mn-MN"),
+    (0x0452, "cy-GB"),
+    (0x0453, "km-KH"),
+    (0x0454, "lo-LA"),
+    (0x0455, "my-MM"),
+    (0x0456, "gl-ES"),
+    (0x045B, "si-LK"),
+    (0x045E, "am-ET"),
+    (0x0461, "ne-NP"),
+    (0x0463, "ps-AF"),
+    (0x0464, "fil-PH"),
+    (0x0468, "ha-Latn-NG"),
+    (0x046A, "yo-NG"),
+    (0x0470, "ig-NG"),
+    (0x0801, "ar-IQ"),
+    (0x0804, "zh-CN"),
+    (0x0807, "de-CH"),
+    (0x0809, "en-GB"),
+    (0x080A, "es-MX"),
+    (0x080C, "fr-BE"),
+    (0x0813, "nl-BE"),
+    (0x0816, "pt-PT"),
+    (0x083C, "ga-IE"),
+    (0x0C01, "ar-EG"),
+    (0x0C04, "zh-HK"),
+    (0x0C07, "de-AT"),
+    (0x0C09, "en-AU"),
+    (0x0C0A, "es-ES"),
+    (0x0C0C, "fr-CA"),
+    (0x1004, "zh-SG"),
+    (0x1009, "en-CA"),
+    (0x100C, "fr-CH"),
+    (0x1409, "en-NZ"),
+    (0x1809, "en-IE"),
+    (0x1C09, "en-ZA"),
+    (0x2C0A, "es-AR"),
+    (0x4009, "en-IN"),
+];
+
+// The Windows locale name for lcid, if it is one we know about.
+pub fn windows_tag(lcid: u32) -> Option<&'static str> {
+    LCIDS
+        .binary_search_by_key(&lcid, |&(id, _)| id)
+        .ok()
+        .map(|i| LCIDS[i].1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{windows_tag, LCIDS};
+    use language_tag::Tag;
+
+    #[test]
+    fn table() {
+        assert!(LCIDS.windows(2).all(|w| w[0].0 < w[1].0));
+        assert!(LCIDS.len() >= 50);
+        for &(lcid, name) in LCIDS {
+            assert!(Tag::from_windows_tag(name).is_ok(), "{lcid:#06x}: {name}");
+        }
+    }
+
+    #[test]
+    fn lookup() {
+        assert_eq!(windows_tag(0x0409), Some("en-US"));
+        assert_eq!(windows_tag(0x0468), Some("ha-Latn-NG"));
+        assert_eq!(windows_tag(0x4009), Some("en-IN"));
+        assert_eq!(windows_tag(0x0000), None);
+        assert_eq!(windows_tag(0x040A), None);
+    }
+}
@@ -1,8 +1,13 @@
 use std::{
-    collections::HashSet as Set, fs::File, io::BufReader, iter::once, path::PathBuf, str::FromStr,
+    cmp::Ordering, collections::HashSet as Set, fs::File, io::BufReader, iter::once, path::PathBuf,
+    str::FromStr,
 };
 
-use langtags::{self, json::LangTags};
+use langtags::{
+    self,
+    json::{ErrorKind, LangTags, LookupResult},
+    tagset::TagSet,
+};
 use language_tag::Tag;
 
 // Load and cache the langtags.json database on demand, we use OnceLock to
@@ -65,55 +70,78 @@ fn sanity_check_keyspace() {
 #[test]
 fn conformant_tag() {
     let ltdb = load_langtags_from_reader();
-    assert_eq!(ltdb.conformant(&Tag::with_lang("en")), true);
-    assert_eq!(
-        ltdb.conformant(&Tag::builder().lang("en").region("RU").build()),
-        true
-    );
-    assert_eq!(
-        ltdb.conformant(&Tag::builder().lang("en").script("Thai").build()),
-        true
-    );
-    assert_eq!(
-        ltdb.conformant(
-            &Tag::builder()
-                .lang("en")
-                .script("Thai")
-                .region("RU")
-                .build()
-        ),
-        true
-    );
+    assert!(ltdb.conformant(&Tag::with_lang("en")));
+    assert!(ltdb.conformant(&Tag::builder().lang("en").region("RU").build()));
+    assert!(ltdb.conformant(&Tag::builder().lang("en").script("Thai").build()));
+    assert!(ltdb.conformant(
+        &Tag::builder()
+            .lang("en")
+            .script("Thai")
+            .region("RU")
+            .build()
+    ));
+    assert!(ltdb.conformant(
+        &Tag::builder()
+            .lang("en")
+            .script("Moon")
+            .region("EU")
+            .build()
+    ));
+    assert!(!ltdb.conformant(
+        &Tag::builder()
+            .lang("en")
+            .script("Thai")
+            .region("__")
+            .build()
+    ));
+    assert!(!ltdb.conformant(
+        &Tag::builder()
+            .lang("en")
+            .script("____")
+            .region("RU")
+            .build()
+    ));
+    // Obsolete tagsets are not conformant, suppressed ones are.
+    let aae_grek = Tag::from_str("aae-Grek").unwrap();
+    assert!(ltdb
+        .orthographic_normal_form(&aae_grek)
+        .unwrap()
+        .is_obsolete());
+    assert!(!ltdb.conformant(&aae_grek));
+    let en = Tag::from_str("en-Latn-US").unwrap();
+    assert!(ltdb.orthographic_normal_form(&en).unwrap().is_suppressed());
+    assert!(ltdb.conformant(&en));
+}
+
+#[test]
+fn strict_conformance() {
+    use langtags::json::ConformanceError;
+
+    let ltdb = load_langtags_from_reader();
+    let why = |tag: &str| ltdb.why_not_conformant(&Tag::from_str(tag).unwrap());
+
+    assert!(ltdb.conformant_strict(&Tag::from_str("en-Latn-US").unwrap()));
+    assert!(ltdb.conformant_strict(&Tag::from_str("en-GB-fonipa").unwrap()));
+    assert!(ltdb.conformant_strict(&Tag::from_str("en-basiceng").unwrap()));
+    assert!(ltdb.conformant_strict(&Tag::from_str("thv-Latn-DZ-x-ahaggar").unwrap()));
     assert_eq!(
-        ltdb.conformant(
-            &Tag::builder()
-                .lang("en")
-                .script("Moon")
-                .region("EU")
-                .build()
-        ),
-        true
+        why("ru-Cyrl-fonipa"),
+        [ConformanceError::UnknownVariant("fonipa".into())]
     );
     assert_eq!(
-        ltdb.conformant(
-            &Tag::builder()
-                .lang("en")
-                .script("Thai")
-                .region("__")
-                .build()
-        ),
-        false
+        why("en-Zzzq-ZX-1abc"),
+        [
+            ConformanceError::UnknownScript("Zzzq".into()),
+            ConformanceError::UnknownRegion("ZX".into()),
+            ConformanceError::UnknownVariant("1abc".into()),
+        ]
     );
     assert_eq!(
-        ltdb.conformant(
-            &Tag::builder()
-                .lang("en")
-                .script("____")
-                .region("RU")
-                .build()
-        ),
-        false
+        why("en-x-ahaggar"),
+        [ConformanceError::PrivateUseConflict("x-ahaggar".into())]
     );
+    assert!(ltdb.conformant(&Tag::from_str("en-x-ahaggar").unwrap()));
+    assert!(!ltdb.conformant_strict(&Tag::builder().lang("en").extension("ab-cdef").build()));
 }
 
 #[test]
@@ -189,7 +217,7 @@ fn sanity_check_script() {
             computed_scripts.len(),
             0,
             "Extra scripts in tagset {name} tags list: {computed_scripts:?}",
-            name = ts.full.to_string()
+            name = ts.full
         );
     }
 }
@@ -213,7 +241,7 @@ fn sanity_check_regions() {
             0,
             "Extra regions mentioned in tagset {name}: {:?}",
             computed_regions.difference(&regions),
-            name = ts.full.to_string()
+            name = ts.full
         );
     }
 }
@@ -252,3 +280,487 @@ fn sanity_check_variants() {
         );
     }
 }
+
+#[test]
+fn sldr_counts() {
+    let ltdb = load_langtags_from_reader();
+    assert!(ltdb.tagsets_with_sldr().all(|ts| ts.sldr));
+    assert!(ltdb.tagsets_without_sldr().all(|ts| !ts.sldr));
+    assert_ne!(ltdb.count_sldr(), 0);
+    assert_ne!(ltdb.count_non_sldr(), 0);
+    assert_eq!(ltdb.count_sldr() + ltdb.count_non_sldr(), ltdb.len());
+    assert_eq!(ltdb.len(), ltdb.tagsets().count());
+}
+
+#[test]
+fn is_sldr_enabled_for() {
+    let ltdb = load_langtags_from_reader();
+    let tag = |s| Tag::from_str(s).expect("tag");
+
+    for form in ["aa", "aa-ET", "aa-Latn", "aa-Latn-ET"] {
+        assert!(ltdb.is_sldr_enabled_for(&tag(form)), "{form}");
+    }
+    for form in ["aa-Arab", "aa-Arab-ET"] {
+        assert!(!ltdb.is_sldr_enabled_for(&tag(form)), "{form}");
+    }
+    assert!(!ltdb.is_sldr_enabled_for(&tag("qaa-Zzzz")));
+
+    assert!(ltdb.is_sldr_enabled_for_locale_form(&tag("aa-Latn-DJ")));
+    assert!(!ltdb.is_sldr_enabled_for_locale_form(&tag("aa-Arab-DJ")));
+    assert!(!ltdb.is_sldr_enabled_for_locale_form(&tag("qaa-Zzzz")));
+}
+
+#[test]
+fn language_range_lookup() {
+    let ltdb = load_langtags_from_reader();
+    let available: Vec<_> = ltdb.tagsets_with_sldr().map(|ts| ts.full.clone()).collect();
+    let lookup = |range: &str| {
+        Tag::from_str(range)
+            .expect("tag")
+            .language_range_lookup(&available)
+            .map(Tag::to_string)
+    };
+
+    assert_eq!(lookup("aa-Latn-ET-x-test").as_deref(), Some("aa-Latn-ET"));
+    assert_eq!(lookup("aa-latn-dj-fonipa").as_deref(), Some("aa-Latn-DJ"));
+    assert_eq!(lookup("aa-Arab-ET"), None);
+    assert_eq!(lookup("aa-ET"), None);
+}
+
+#[test]
+fn lookup_with_fallback() {
+    let ltdb = load_langtags_from_reader();
+    let en = Tag::from_str("en-Latn-US").unwrap();
+
+    let LookupResult {
+        tagset,
+        matched_tag,
+        stripped_components,
+    } = ltdb.lookup_with_fallback(&en).expect("exact match");
+    assert_eq!(tagset.full, en);
+    assert_eq!(matched_tag, en);
+    assert_eq!(stripped_components, 0);
+
+    let res = ltdb
+        .lookup_with_fallback(&Tag::from_str("en-Latn-US-bogus-fakery").unwrap())
+        .expect("match after stripping variants");
+    assert_eq!(res.tagset.full, en);
+    assert_eq!(res.matched_tag, en);
+    assert_eq!(res.stripped_components, 2);
+
+    let res = ltdb
+        .lookup_with_fallback(&Tag::from_str("en-Latn-US-a-bable-b-babbel-x-priv").unwrap())
+        .expect("match after stripping extensions");
+    assert_eq!(res.tagset.full, en);
+    assert_eq!(res.matched_tag, en);
+    assert_eq!(res.stripped_components, 3);
+
+    let res = ltdb
+        .lookup_with_fallback(&Tag::from_str("en-Latn-QQ-bogus").unwrap())
+        .expect("match after stripping region");
+    assert_eq!(res.tagset.full, en);
+    assert_eq!(res.matched_tag, Tag::from_str("en-Latn").unwrap());
+    assert_eq!(res.stripped_components, 2);
+
+    assert_eq!(
+        ltdb.lookup_with_fallback(&Tag::from_str("qqq-Latn-US").unwrap()),
+        None
+    );
+}
+
+#[test]
+fn shrink_to_fit() {
+    let file = File::open(
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("langtags.json"),
+    )
+    .expect("open langtags.json");
+    let mut ltdb = LangTags::from_reader(BufReader::new(file)).expect("read langtags.json");
+    ltdb.build_all_tags_index();
+    let shared = load_langtags_from_reader();
+    let before = ltdb.memory_usage_estimate();
+    assert!(before > 0);
+    ltdb.shrink_to_fit();
+    assert!(ltdb.memory_usage_estimate() <= before);
+
+    for tag in [
+        "aa",
+        "en-GB-fonipa",
+        "sr-Latn-RS",
+        "thv-Latn-DZ-x-ahaggar",
+        "qqq",
+    ] {
+        let tag = Tag::from_str(tag).unwrap();
+        assert_eq!(
+            ltdb.orthographic_normal_form(&tag),
+            shared.orthographic_normal_form(&tag)
+        );
+        assert_eq!(ltdb.find_any_form(&tag), shared.find_any_form(&tag));
+    }
+}
+
+#[test]
+fn by_language() {
+    let ltdb = load_langtags_from_reader();
+    let afar: Vec<_> = ltdb.iter_by_language("aa").collect();
+    assert!(afar.len() >= 2);
+    assert!(afar.iter().all(|ts| ts.full.primary_lang() == "aa"));
+    assert!(afar
+        .iter()
+        .any(|ts| ts.full == Tag::from_str("aa-Latn-ET").unwrap()));
+    assert_eq!(ltdb.iter_by_language("qqq").count(), 0);
+
+    let languages: Vec<_> = ltdb.languages().collect();
+    assert!(languages.windows(2).all(|w| w[0] < w[1]));
+    for lang in ["aa", "eka", "frm"] {
+        assert!(languages.binary_search(&lang).is_ok(), "{lang}");
+    }
+}
+
+#[test]
+fn macrolanguages() {
+    let ltdb = load_langtags_from_reader();
+    let tag = |s| Tag::from_str(s).expect("tag");
+
+    let tmh: Vec<_> = ltdb
+        .tagsets_for_macrolang("tmh")
+        .map(|ts| ts.full.to_string())
+        .collect();
+    assert!(tmh.contains(&"thv-Latn-DZ".to_string()), "{tmh:?}");
+    assert!(ltdb
+        .tagsets_for_macrolang("tmh")
+        .all(|ts| ts.macrolang == "tmh" && ts.iso639_3 != "tmh"));
+    assert_eq!(
+        ltdb.tagsets_for_macrolang("TMH").count(),
+        tmh.len(),
+        "case insensitive"
+    );
+
+    // By ISO 639-3 code or language subtag where they differ.
+    let arabic = ltdb.tagsets_for_macrolang("ara").count();
+    assert_ne!(arabic, 0);
+    assert_eq!(ltdb.tagsets_for_macrolang("ar").count(), arabic);
+    assert!(ltdb
+        .tagsets_for_macrolang("ara")
+        .any(|ts| ts.iso639_3 == "arb"));
+    assert_eq!(ltdb.tagsets_for_macrolang("qqq").count(), 0);
+
+    for form in ["thv", "thv-DZ", "thv-Latn-DZ"] {
+        let tmh = ltdb.macrolang_of(&tag(form)).expect(form);
+        assert_eq!(tmh.iso639_3, "tmh");
+        assert_eq!(tmh.tag, tag("tmh"));
+    }
+    assert_eq!(
+        ltdb.macrolang_of(&tag("arb-EG"))
+            .map(|ts| ts.iso639_3.as_str()),
+        Some("ara")
+    );
+    assert_eq!(ltdb.macrolang_of(&tag("tmh")), None);
+    assert_eq!(ltdb.macrolang_of(&tag("qqq")), None);
+}
+
+#[test]
+fn deprecated_tagsets() {
+    let ltdb = load_langtags_from_reader();
+    assert_eq!(
+        ltdb.deprecated_tagsets().count(),
+        ltdb.tagsets().filter(|ts| ts.is_obsolete()).count()
+    );
+    assert!(ltdb.deprecated_tagsets().all(TagSet::is_obsolete));
+
+    let frm = Tag::from_str("frm-Latn-BE").unwrap();
+    assert!(!ltdb.orthographic_normal_form(&frm).unwrap().is_obsolete());
+    assert_eq!(ltdb.has_replacement(&frm), None);
+
+    let replacement = ltdb
+        .has_replacement(&Tag::from_str("ab-Geor").unwrap())
+        .expect("replacement for obsolete tag");
+    assert_eq!(replacement.tag, Tag::with_lang("ab"));
+    assert!(!replacement.is_obsolete());
+}
+
+#[test]
+fn orthographic_chain() {
+    let ltdb = load_langtags_from_reader();
+    let fulls = |tag: &str| {
+        ltdb.orthographic_chain(&Tag::from_str(tag).unwrap())
+            .map(|ts| ts.full.to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(fulls("en-Latn-US-fonipa"), ["en-Latn-US"]);
+    assert_eq!(fulls("en-GB-fonipa"), ["en-Latn-GB", "en-Latn-US"]);
+    assert_eq!(
+        fulls("sr-Latn-RS-fonipa-x-priv"),
+        ["sr-Latn-RS", "sr-Cyrl-RS"]
+    );
+    assert!(fulls("qqq-Latn-US-fonipa").is_empty());
+}
+
+#[test]
+fn find_for_script_region() {
+    let ltdb = load_langtags_from_reader();
+    let aa_arab = Tag::from_str("aa-Arab-ET").unwrap();
+    for region in ["ET", "DJ", "ER"] {
+        assert!(
+            ltdb.find_for_script_region("Arab", region)
+                .any(|ts| ts.full == aa_arab),
+            "aa-Arab-ET not found for region {region}"
+        );
+    }
+    assert!(ltdb
+        .find_for_script_region("Arab", "ER")
+        .all(|ts| ts.script() == Some("Arab")
+            && (ts.region() == Some("ER") || ts.regions.iter().any(|r| r == "ER"))));
+    assert!(!ltdb
+        .find_for_script_region("Latn", "DJ")
+        .any(|ts| ts.full == aa_arab));
+    assert_eq!(ltdb.find_for_script_region("Arab", "__").count(), 0);
+}
+
+#[test]
+fn find_any_form() {
+    let mut ltdb = LangTags::from_reader(BufReader::new(
+        File::open(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("tests")
+                .join("langtags.json"),
+        )
+        .expect("open langtags.json"),
+    ))
+    .expect("read langtags.json");
+    let tags = [
+        "en-Latn-US",
+        "aeb-IL",
+        "en-TW",
+        "frm-Latn-BE",
+        "en-Latn-US-a-bable",
+    ]
+    .map(|t| Tag::from_str(t).unwrap());
+    let unindexed: Vec<_> = tags
+        .iter()
+        .map(|t| ltdb.find_any_form(t).map(|ts| ts.full.clone()))
+        .collect();
+    assert!(unindexed.iter().take(4).all(Option::is_some));
+    assert!(unindexed[4].is_none());
+
+    ltdb.build_all_tags_index();
+    for (tag, expected) in tags.iter().zip(unindexed) {
+        assert_eq!(
+            ltdb.find_any_form(tag).map(|ts| ts.full.clone()),
+            expected,
+            "{tag}"
+        );
+    }
+    assert_eq!(
+        ltdb.find_any_form(&Tag::from_str("qqq-Latn").unwrap()),
+        None
+    );
+}
+
+#[test]
+fn best_name() {
+    let ltdb = load_langtags_from_reader();
+    let aa = Tag::from_str("aa").unwrap();
+    let ts = ltdb.orthographic_normal_form(&aa).unwrap();
+
+    assert_eq!(
+        ltdb.best_name(&aa, &Tag::from_str("en-US").unwrap()),
+        Some(ts.name.as_str())
+    );
+    assert_eq!(
+        ltdb.best_name(&aa, &Tag::from_str("qqq").unwrap()),
+        Some(ts.name.as_str())
+    );
+    assert_eq!(
+        ltdb.best_name(&aa, &Tag::from_str("aa-ET").unwrap()),
+        Some(ts.localname.as_str())
+    );
+    assert_eq!(
+        ltdb.best_name(&aa, &Tag::from_str("fr").unwrap()),
+        Some(ts.localnames[0].as_str())
+    );
+    assert_eq!(ltdb.best_name(&Tag::from_str("qqq").unwrap(), &aa), None);
+}
+
+#[test]
+fn find_by_lcid() {
+    let ltdb = load_langtags_from_reader();
+    let full = |ts: Option<&TagSet>| ts.map(|ts| ts.full.to_string());
+
+    assert_eq!(full(ltdb.find_by_lcid(0x0409)), Some("en-Latn-US".into()));
+    assert_eq!(full(ltdb.find_by_lcid(0x0809)), Some("en-Latn-GB".into()));
+    assert_eq!(full(ltdb.find_by_lcid(0x0468)), Some("ha-Latn-NG".into()));
+    assert_eq!(ltdb.find_by_lcid(0x0000), None);
+    assert_eq!(
+        full(ltdb.find_by_windows_tag(&Tag::from_str("en-Latn-AU").unwrap())),
+        Some("en-Latn-AU".into())
+    );
+}
+
+#[test]
+fn memory_usage_estimate() {
+    let ltdb = load_langtags_from_reader();
+    let estimate = ltdb.memory_usage_estimate();
+
+    let mut tagsets: Vec<_> = ltdb.tagsets().take(10).cloned().collect();
+    let smaller = LangTags::from_str(&serde_json::to_string(&tagsets).unwrap()).unwrap();
+    tagsets.extend(ltdb.tagsets().nth(10).cloned());
+    let larger = LangTags::from_str(&serde_json::to_string(&tagsets).unwrap()).unwrap();
+    assert!(smaller.memory_usage_estimate() > 0);
+    assert!(larger.memory_usage_estimate() > smaller.memory_usage_estimate());
+    assert!(estimate > larger.memory_usage_estimate());
+    assert_eq!(ltdb.estimate_memory_bytes(), estimate);
+    assert!(larger.estimate_memory_bytes() > smaller.estimate_memory_bytes());
+}
+
+#[test]
+fn tagset_counts() {
+    let ltdb = load_langtags_from_reader();
+    let scripts = ltdb.tagset_count_by_script();
+    assert!(scripts["Latn"] > 0);
+    assert_eq!(scripts.values().sum::<usize>(), ltdb.len());
+
+    let regions = ltdb.tagset_count_by_region();
+    assert!(regions["US"] > 0);
+    assert!(regions.values().sum::<usize>() <= ltdb.len());
+}
+
+#[test]
+fn find_by_rod() {
+    let ltdb = load_langtags_from_reader();
+    let found: Vec<_> = ltdb
+        .find_by_rod("6861")
+        .map(|ts| ts.full.to_string())
+        .collect();
+    assert_eq!(found, ["aae-Latn-IT-x-sub84"]);
+    assert_eq!(ltdb.find_by_rod("").count(), 0);
+    assert_eq!(ltdb.find_by_rod("not-a-rod").count(), 0);
+
+    let aa = ltdb
+        .orthographic_normal_form(&"aa".parse().unwrap())
+        .unwrap();
+    assert_eq!(aa.rod_code(), None);
+    assert_eq!(
+        ltdb.find_by_rod("6861").next().unwrap().rod_code(),
+        Some("6861")
+    );
+}
+
+#[test]
+fn from_readers() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("langtags.json");
+    let mut values: Vec<serde_json::Value> =
+        serde_json::from_reader(BufReader::new(File::open(path).unwrap())).unwrap();
+    let second = values.split_off(values.len() / 2);
+    let halves = [values, second].map(|half| serde_json::to_vec(&half).unwrap());
+
+    let merged = LangTags::from_readers(halves.iter().map(Vec::as_slice)).expect("LangTags");
+    assert_eq!(&merged, load_langtags_from_reader());
+
+    let none: [&[u8]; 0] = [];
+    assert_eq!(
+        LangTags::from_readers(none).unwrap_err().kind(),
+        &ErrorKind::NoInput
+    );
+}
+
+#[test]
+fn to_csv_writer() {
+    let ltdb = load_langtags_from_reader();
+    let mut out = Vec::new();
+    ltdb.to_csv_writer(&mut out).expect("CSV");
+
+    let mut csv = csv::Reader::from_reader(out.as_slice());
+    assert_eq!(
+        csv.headers().expect("header row"),
+        vec![
+            "tag",
+            "full",
+            "name",
+            "localname",
+            "iso639_3",
+            "script",
+            "region",
+            "regions",
+            "sldr",
+            "obsolete",
+            "windows"
+        ]
+    );
+    let rows: Vec<_> = csv.records().collect::<Result<_, _>>().expect("rows");
+    assert_eq!(rows.len(), ltdb.len());
+    let afar = rows
+        .iter()
+        .find(|row| &row[1] == "aa-Arab-ET")
+        .expect("aa-Arab-ET row");
+    assert_eq!(
+        afar,
+        vec![
+            "aa-Arab",
+            "aa-Arab-ET",
+            "Afar",
+            "",
+            "aar",
+            "Arab",
+            "ET",
+            "DJ;ER",
+            "false",
+            "false",
+            "aa-Arab"
+        ]
+    );
+}
+
+#[test]
+fn tagsets_changed_since() {
+    let ltdb = load_langtags_from_reader();
+    assert_eq!(ltdb.date(), "2024-04-30");
+    assert_eq!(ltdb.version_date_cmp("2024-04-29"), Ordering::Greater);
+    assert_eq!(ltdb.version_date_cmp("2024-04-30"), Ordering::Equal);
+    assert_eq!(ltdb.version_date_cmp("2024-05-01"), Ordering::Less);
+
+    assert_eq!(ltdb.tagsets_changed_since("2024-04-29").count(), ltdb.len());
+    assert_eq!(ltdb.tagsets_changed_since("2023-02-20").count(), ltdb.len());
+    assert_eq!(ltdb.tagsets_changed_since("2024-04-30").count(), 0);
+    assert_eq!(ltdb.tagsets_changed_since("2025-01-01").count(), 0);
+}
+
+#[test]
+fn validate_tag_file_correspondence() {
+    let ltdb = load_langtags_from_reader();
+    let sldr = tempfile::tempdir().expect("temp dir");
+    let (flat, unflat) = (sldr.path().join("flat"), sldr.path().join("unflat"));
+    let mut sldr_tagsets = ltdb.tagsets_with_sldr();
+    let (both, flat_only) = (sldr_tagsets.next().unwrap(), sldr_tagsets.next().unwrap());
+    let write = |dir: &PathBuf, name: &str| {
+        let dir = dir.join(&name[0..1]);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(name).with_extension("xml"), "<ldml/>").unwrap();
+    };
+    let file_name = |ts: &TagSet| ts.full.to_string().replace('-', "_");
+    for dir in [&flat, &unflat] {
+        write(dir, &file_name(both));
+    }
+    write(&flat, &file_name(flat_only));
+    write(&flat, "zzz_Bogus");
+    write(&unflat, "_bad_name");
+
+    let report = ltdb.validate_tag_file_correspondence(&flat, &unflat);
+
+    assert!(!report.is_ok());
+    assert_eq!(report.missing_files.len(), ltdb.count_sldr() - 1);
+    assert!(!report.missing_files.contains(&both.full));
+    assert!(report.missing_files.contains(&flat_only.full));
+    assert_eq!(
+        report.extra_files,
+        [
+            flat.join("z").join("zzz_Bogus.xml"),
+            unflat.join("_").join("_bad_name.xml")
+        ]
+    );
+    assert!(report.mismatched_revids.is_empty());
+}
@@ -0,0 +1,53 @@
+use std::{env, fs::File, io::BufReader, path::PathBuf};
+
+use langtags::text::LangTags;
+
+const FIXTURE: &str = r#"
+*aa = *aa-ET = aa-Latn = aa-Latn-ET
+aa-Arab = aa-Arab-ET
+*aa-DJ = aa-Latn-DJ
+*en = *en-Latn = en-US = en-Latn-US
+en-GB = en-Latn-GB
+sr = sr-Cyrl = sr-RS = sr-Cyrl-RS
+sr-Latn = sr-Latn-RS"#;
+
+fn roundtrip(langtags: &LangTags) -> (LangTags, String) {
+    let mut out = Vec::new();
+    langtags.to_writer(&mut out).expect("written langtags.txt");
+    let out = String::from_utf8(out).expect("UTF-8 langtags.txt");
+    (out.parse().expect("reparsed langtags.txt"), out)
+}
+
+#[test]
+fn fixture_roundtrip() {
+    let langtags: LangTags = FIXTURE.parse().expect("langtags.txt fixture");
+    let (reparsed, _) = roundtrip(&langtags);
+    assert_eq!(reparsed, langtags);
+}
+
+#[test]
+fn one_line_per_tagset() {
+    let langtags: LangTags = FIXTURE.parse().expect("langtags.txt fixture");
+    let (_, out) = roundtrip(&langtags);
+    assert_eq!(out.lines().count(), langtags.tagsets().count());
+    assert_eq!(out.lines().count(), 7);
+}
+
+// Set LANGTAGS_TXT to check a langtags.txt other than the copy in tests/.
+#[test]
+#[ignore = "requires production data"]
+fn production_roundtrip() {
+    let path = env::var_os("LANGTAGS_TXT").map_or_else(
+        || {
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("tests")
+                .join("langtags.txt")
+        },
+        PathBuf::from,
+    );
+    let file = File::open(&path).expect("open langtags.txt");
+    let langtags = LangTags::from_reader(BufReader::new(file)).expect("read langtags.txt");
+    let (reparsed, out) = roundtrip(&langtags);
+    assert_eq!(out.lines().count(), langtags.tagsets().count());
+    assert_eq!(reparsed, langtags);
+}
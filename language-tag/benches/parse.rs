@@ -0,0 +1,75 @@
+use std::{collections::HashMap, str::FromStr};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use language_tag::Tag;
+
+const TAGS: [(&str, &str); 4] = [
+    ("simple", "en"),
+    ("typical", "en-Latn-US"),
+    (
+        "complex",
+        "sgn-ase-Latn-US-1abc-2def-a-abcdef-ghijkl-u-co-phonebk-x-priv1-priv2",
+    ),
+    (
+        "extensions",
+        "en-Latn-US-1abc-2def-a-abcdef-b-ghijklmn-x-priv",
+    ),
+];
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Tag::from_str");
+    group.throughput(Throughput::Elements(1));
+    for (name, src) in TAGS {
+        group.bench_function(name, |b| b.iter(|| Tag::from_str(black_box(src))));
+    }
+    group.finish();
+}
+
+fn to_string(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Tag::to_string");
+    group.throughput(Throughput::Elements(1));
+    for (name, src) in TAGS {
+        let tag = Tag::from_str(src).unwrap();
+        group.bench_function(name, |b| b.iter(|| black_box(&tag).to_string()));
+    }
+    group.finish();
+}
+
+fn setters(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Tag setters");
+    group.throughput(Throughput::Elements(1));
+    let tag = Tag::from_str(TAGS[2].1).unwrap();
+    group.bench_function("set_script", |b| {
+        b.iter_batched_ref(
+            || tag.clone(),
+            |tag| tag.set_script(black_box("Cyrl")),
+            criterion::BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("set_region", |b| {
+        b.iter_batched_ref(
+            || tag.clone(),
+            |tag| tag.set_region(black_box("419")),
+            criterion::BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+fn lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("HashMap<Tag, _>::get");
+    group.throughput(Throughput::Elements(1));
+    let map: HashMap<Tag, usize> = TAGS
+        .iter()
+        .enumerate()
+        .map(|(i, (_, src))| (Tag::from_str(src).unwrap(), i))
+        .collect();
+    for (name, src) in TAGS {
+        let key = Tag::from_str(src).unwrap();
+        group.bench_function(name, |b| b.iter(|| map.get(black_box(&key))));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse, to_string, setters, lookup);
+criterion_main!(benches);
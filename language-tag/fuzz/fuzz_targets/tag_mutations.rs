@@ -0,0 +1,130 @@
+#![no_main]
+
+// Build a valid tag, then edit it with a random sequence of setters,
+// checking after each edit that the tag still reads back as itself, and
+// that an edit refused for making the tag too long left it alone. Setters
+// check the component offsets themselves in debug builds, which cargo fuzz
+// enables by default.
+//
+//     cargo +nightly fuzz run tag_mutations
+
+use arbitrary::Arbitrary;
+use language_tag::{ParseTagError, Tag};
+use libfuzzer_sys::fuzz_target;
+
+// Subtags are drawn from these, so every tag built is well formed and any
+// failure is down to the edits rather than the input.
+const LANGS: [&str; 4] = ["en", "aa", "thv", "zh"];
+const SCRIPTS: [&str; 3] = ["Latn", "Arab", "Hant"];
+const REGIONS: [&str; 4] = ["US", "ET", "DZ", "419"];
+const VARIANTS: [&str; 4] = ["1606nict", "fonipa", "valencia", "1994"];
+const EXTENSIONS: [&str; 4] = ["a-bable", "a-var1", "b-abcdef", "q-babbel"];
+const PRIVATE: [&str; 3] = ["x-test", "x-ekajuk", "x-a-b"];
+
+fn pick<const N: usize>(table: &[&'static str; N], i: u8) -> &'static str {
+    table[i as usize % N]
+}
+
+fn picks<const N: usize>(table: &[&'static str; N], is: &[u8]) -> Vec<&'static str> {
+    let mut subtags: Vec<_> = is.iter().map(|&i| pick(table, i)).collect();
+    subtags.sort_unstable();
+    subtags.dedup();
+    subtags
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    lang: u8,
+    script: Option<u8>,
+    region: Option<u8>,
+    variants: Vec<u8>,
+    extensions: Vec<u8>,
+    private: Option<u8>,
+    edits: Vec<Edit>,
+}
+
+// Setting a component to None clears it.
+#[derive(Arbitrary, Debug)]
+enum Edit {
+    SetLang(u8),
+    SetScript(Option<u8>),
+    SetRegion(Option<u8>),
+    SetVariants(Vec<u8>),
+    PushVariant(u8),
+    PopVariant,
+    SetExtensions(Vec<u8>),
+    AddExtension(u8),
+    RemoveExtension(u8),
+    SetPrivate(Option<u8>),
+}
+
+impl Edit {
+    fn apply(&self, tag: &mut Tag) -> Result<(), ParseTagError> {
+        match *self {
+            Edit::SetLang(i) => tag.try_set_lang(pick(&LANGS, i)),
+            Edit::SetScript(Some(i)) => tag.try_set_script(pick(&SCRIPTS, i)),
+            Edit::SetScript(None) => {
+                tag.set_script("");
+                Ok(())
+            }
+            Edit::SetRegion(Some(i)) => tag.try_set_region(pick(&REGIONS, i)),
+            Edit::SetRegion(None) => {
+                tag.set_region("");
+                Ok(())
+            }
+            Edit::SetVariants(ref is) => tag.try_set_variants(picks(&VARIANTS, is)),
+            Edit::PushVariant(i) => {
+                let variant = pick(&VARIANTS, i);
+                if tag.variants().any(|v| v == variant) {
+                    return Ok(());
+                }
+                tag.try_push_variant(variant)
+            }
+            Edit::PopVariant => {
+                tag.pop_variant();
+                Ok(())
+            }
+            Edit::SetExtensions(ref is) => tag.try_set_extensions(picks(&EXTENSIONS, is)),
+            Edit::AddExtension(i) => tag.try_add_extension(pick(&EXTENSIONS, i)),
+            Edit::RemoveExtension(i) => {
+                tag.remove_extension(pick(&EXTENSIONS, i));
+                Ok(())
+            }
+            Edit::SetPrivate(i) => tag.try_set_private(i.map_or("", |i| pick(&PRIVATE, i))),
+        }
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let mut builder = Tag::builder()
+        .lang(pick(&LANGS, input.lang))
+        .variants(picks(&VARIANTS, &input.variants))
+        .extensions(picks(&EXTENSIONS, &input.extensions));
+    if let Some(i) = input.script {
+        builder = builder.script(pick(&SCRIPTS, i));
+    }
+    if let Some(i) = input.region {
+        builder = builder.region(pick(&REGIONS, i));
+    }
+    if let Some(i) = input.private {
+        builder = builder.private(pick(&PRIVATE, i));
+    }
+    let Ok(mut tag) = builder.try_build() else {
+        return;
+    };
+
+    for edit in &input.edits {
+        let before = tag.clone();
+        if edit.apply(&mut tag).is_err() {
+            assert_eq!(tag, before, "refused {edit:?}");
+            continue;
+        }
+        let reparsed = tag.as_ref().parse::<Tag>();
+        assert_eq!(reparsed.as_ref(), Ok(&tag), "after {edit:?}");
+        assert_eq!(
+            reparsed.unwrap().to_string(),
+            tag.to_string(),
+            "after {edit:?}"
+        );
+    }
+});
@@ -0,0 +1,71 @@
+use crate::Tag;
+
+// The language ranges of an HTTP Accept-Language header, most preferred
+// first, eg. "en-US,en;q=0.9,fr;q=0.8". Ranges given equal quality keep
+// their order in the header. The * wildcard, tags that do not parse, ranges
+// with a malformed quality value, and those refused with q=0 are dropped.
+pub fn parse_accept_language(header: &str) -> Vec<(Tag, f32)> {
+    let mut ranges: Vec<_> = header
+        .split(',')
+        .filter_map(|range| {
+            let mut params = range.split(';');
+            let tag = params.next()?.trim();
+            let mut quality = 1.0;
+            for param in params {
+                let (name, value) = param.split_once('=')?;
+                if name.trim().eq_ignore_ascii_case("q") {
+                    quality = value.trim().parse().ok()?;
+                }
+            }
+            if tag == "*" || !(quality > 0.0 && quality <= 1.0) {
+                return None;
+            }
+            Some((tag.parse().ok()?, quality))
+        })
+        .collect();
+    ranges.sort_by(|(_, a): &(Tag, f32), (_, b)| b.total_cmp(a));
+    ranges
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_accept_language;
+    use crate::Tag;
+
+    fn tags(header: &str) -> Vec<(String, f32)> {
+        parse_accept_language(header)
+            .into_iter()
+            .map(|(tag, q)| (tag.to_string(), q))
+            .collect()
+    }
+
+    #[test]
+    fn ordered_by_quality() {
+        assert_eq!(
+            tags("fr;q=0.8, en-US,en;q=0.9"),
+            [
+                ("en-US".into(), 1.0),
+                ("en".into(), 0.9),
+                ("fr".into(), 0.8)
+            ]
+        );
+        assert_eq!(
+            tags("de;q=0.5,fr;q=0.5,en"),
+            [("en".into(), 1.0), ("de".into(), 0.5), ("fr".into(), 0.5)]
+        );
+        let (tag, q) = &parse_accept_language("sr-Latn-RS")[0];
+        assert_eq!(
+            (tag, *q),
+            (&Tag::from_parts("sr", "Latn", "RS", [], [], None), 1.0)
+        );
+    }
+
+    #[test]
+    fn dropped_ranges() {
+        assert_eq!(tags("*"), []);
+        assert_eq!(tags(""), []);
+        assert_eq!(tags("en;q=invalid,fr;q=0.5"), [("fr".into(), 0.5)]);
+        assert_eq!(tags("en;q=0,fr;q=2,de;q"), []);
+        assert_eq!(tags("en--US,*;q=0.1,de"), [("de".into(), 1.0)]);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::Tag;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_with::{DeserializeAs, DisplayFromStr, SerializeAs};
+
+/// Serialize a [`Tag`] as its string form, eg. `"en-Latn-US"`. This is how
+/// `Tag` serializes by itself, so it is only needed to spell out the format
+/// alongside [`TagAsObject`], eg. in a `serde_as` container type.
+pub struct TagAsString;
+
+impl SerializeAs<Tag> for TagAsString {
+    fn serialize_as<S: Serializer>(source: &Tag, serializer: S) -> Result<S::Ok, S::Error> {
+        DisplayFromStr::serialize_as(source, serializer)
+    }
+}
+
+impl<'de> DeserializeAs<'de, Tag> for TagAsString {
+    fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<Tag, D::Error> {
+        DisplayFromStr::deserialize_as(deserializer)
+    }
+}
+
+/// Serialize a [`Tag`] as an object with a key per component, omitting those
+/// the tag lacks, eg. `{"lang": "en", "script": "Latn", "region": "US"}`.
+/// Variants are an array of subtags, extensions an array of whole extensions,
+/// eg. `["u-co-phonebk"]`, and private use keeps its `x-` prefix.
+/// Deserializing checks each component against its subtag grammar.
+///
+/// Use it through `serde_as`, or directly with `serde(with)`:
+///
+/// ```
+/// use language_tag::{Tag, TagAsObject};
+/// use serde::{Deserialize, Serialize};
+/// use serde_with::serde_as;
+///
+/// #[serde_as]
+/// #[derive(Deserialize, Serialize)]
+/// struct Locale {
+///     #[serde_as(as = "TagAsObject")]
+///     tag: Tag,
+///     #[serde_as(as = "Vec<TagAsObject>")]
+///     fallbacks: Vec<Tag>,
+///     #[serde(with = "TagAsObject")]
+///     written: Tag,
+/// }
+///
+/// let locale: Locale = serde_json::from_str(
+///     r#"{"tag": {"lang": "en", "region": "US"},
+///         "fallbacks": [{"lang": "en"}],
+///         "written": {"lang": "en", "script": "Latn"}}"#,
+/// )
+/// .unwrap();
+/// assert_eq!(locale.tag, "en-US");
+/// assert_eq!(
+///     serde_json::to_string(&locale).unwrap(),
+///     r#"{"tag":{"lang":"en","region":"US"},"fallbacks":[{"lang":"en"}],"written":{"lang":"en","script":"Latn"}}"#
+/// );
+/// ```
+pub struct TagAsObject;
+
+#[derive(Serialize)]
+struct Components<'a> {
+    #[serde(skip_serializing_if = "str::is_empty")]
+    lang: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    script: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    region: Option<&'a str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    variants: Vec<&'a str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    extensions: Vec<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    private: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OwnedComponents {
+    lang: Option<String>,
+    script: Option<String>,
+    region: Option<String>,
+    #[serde(default)]
+    variants: Vec<String>,
+    #[serde(default)]
+    extensions: Vec<String>,
+    private: Option<String>,
+}
+
+// Each extension whole, from its singleton to the next, eg. u-co-phonebk,
+// where Tag::extensions gives one subtag at a time.
+fn extensions(tag: &Tag) -> Vec<&str> {
+    let all = tag.extensions_str();
+    let mut extensions = Vec::new();
+    let mut start = 0;
+    for (i, _) in all.match_indices('-') {
+        if all.as_bytes().get(i + 2) == Some(&b'-') {
+            extensions.push(&all[start..i]);
+            start = i + 1;
+        }
+    }
+    if !all.is_empty() {
+        extensions.push(&all[start..]);
+    }
+    extensions
+}
+
+impl TagAsObject {
+    pub fn serialize<S: Serializer>(tag: &Tag, serializer: S) -> Result<S::Ok, S::Error> {
+        Components {
+            lang: tag.lang(),
+            script: tag.script(),
+            region: tag.region(),
+            variants: tag.variants().collect(),
+            extensions: extensions(tag),
+            private: tag.private(),
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Tag, D::Error> {
+        let c = OwnedComponents::deserialize(deserializer)?;
+        let Some(lang) = c.lang else {
+            // Only a private use tag, eg. x-foo, has no language.
+            let only_private = c.script.is_none()
+                && c.region.is_none()
+                && c.variants.is_empty()
+                && c.extensions.is_empty();
+            return match c.private {
+                Some(private) if only_private => private.parse().map_err(de::Error::custom),
+                _ => Err(de::Error::missing_field("lang")),
+            };
+        };
+        let variants: Vec<_> = c.variants.iter().map(String::as_str).collect();
+        let extensions: Vec<_> = c.extensions.iter().map(String::as_str).collect();
+        Tag::try_from_components(
+            &lang,
+            c.script.as_deref(),
+            c.region.as_deref(),
+            &variants,
+            &extensions,
+            c.private.as_deref(),
+        )
+        .map_err(de::Error::custom)
+    }
+}
+
+impl SerializeAs<Tag> for TagAsObject {
+    fn serialize_as<S: Serializer>(source: &Tag, serializer: S) -> Result<S::Ok, S::Error> {
+        TagAsObject::serialize(source, serializer)
+    }
+}
+
+impl<'de> DeserializeAs<'de, Tag> for TagAsObject {
+    fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<Tag, D::Error> {
+        TagAsObject::deserialize(deserializer)
+    }
+}
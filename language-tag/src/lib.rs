@@ -1,7 +1,17 @@
+pub mod accept;
+mod formats;
 mod parser;
 mod tag;
 
-pub use self::tag::{ExtensionRef, Tag};
+use std::collections::HashSet;
+
+pub use self::formats::{TagAsObject, TagAsString};
+pub use self::parser::ParseTagError;
+pub use self::tag::{
+    CaseFoldedTag, CaseFoldedTagSet, ComponentError, ExtensionRef, ParseExtensionError,
+    SemanticTag, Subtags, TExtension, Tag, TagCanonical, TagLowercase, TagUppercase,
+    TransformFields, UnicodeKeywords,
+};
 
 #[derive(Default, Debug)]
 pub struct Builder<'a> {
@@ -73,19 +83,59 @@ impl<'a> Builder<'a> {
         self
     }
 
-    pub fn build(mut self) -> Tag {
+    // Replace the variant old with new, or None if old is not present.
+    pub fn replace_variant(mut self, old: &str, new: &'a str) -> Option<Self> {
+        let variant = self.variants.iter_mut().find(|v| **v == old)?;
+        *variant = new;
         self.variants.sort_unstable();
+        Some(self)
+    }
+
+    // Replace the extension old with new, or None if old is not present.
+    pub fn replace_extension(mut self, old: &'a str, new: &'a str) -> Option<Self> {
+        let extension = self.extensions.iter_mut().find(|e| *e == old)?;
+        new.clone_into(extension);
         self.extensions.sort_unstable();
-        let mut tag = Tag::from_parts(
+        Some(self)
+    }
+
+    // Remove repeated variants, keeping the first occurrence of each.
+    pub fn dedup_variants(mut self) -> Self {
+        let mut seen = HashSet::new();
+        self.variants.retain(|&v| seen.insert(v));
+        self
+    }
+
+    // Remove repeated extensions, keeping the first occurrence of each.
+    pub fn dedup_extensions(mut self) -> Self {
+        let mut seen = HashSet::new();
+        self.extensions.retain(|e| seen.insert(e.clone()));
+        self
+    }
+
+    // Panics if the tag would be longer than Tag::MAX_LEN.
+    #[track_caller]
+    pub fn build(self) -> Tag {
+        match self.try_build() {
+            Ok(tag) => tag,
+            Err(err) => panic!("{} is longer than Tag::MAX_LEN", err.input),
+        }
+    }
+
+    // As build, but failing if the tag would be longer than Tag::MAX_LEN.
+    pub fn try_build(mut self) -> Result<Tag, ParseTagError> {
+        self.variants.sort_unstable();
+        self.extensions.sort_unstable();
+        let mut tag = Tag::try_from_parts(
             self.lang,
             Builder::to_option(self.script),
             Builder::to_option(self.region),
             self.variants,
             self.extensions.iter().map(|s| s.as_ref()),
             Builder::to_option(self.private),
-        );
+        )?;
         tag.shrink_to_fit();
-        tag
+        Ok(tag)
     }
 
     #[inline]
@@ -97,3 +147,107 @@ impl<'a> Builder<'a> {
         }
     }
 }
+
+// As Builder, but owning its components, so it can outlive the Tag or strings
+// it was built from, eg. tag.into_builder().region("GB").build().
+#[derive(Clone, Default, Debug)]
+pub struct OwnedBuilder {
+    lang: String,
+    script: String,
+    region: String,
+    variants: Vec<String>,
+    extensions: Vec<String>,
+    private: String,
+}
+
+impl From<Tag> for OwnedBuilder {
+    fn from(value: Tag) -> Self {
+        Builder::from(&value).into_owned()
+    }
+}
+
+impl Builder<'_> {
+    pub fn into_owned(self) -> OwnedBuilder {
+        OwnedBuilder {
+            lang: self.lang.to_owned(),
+            script: self.script.to_owned(),
+            region: self.region.to_owned(),
+            variants: self.variants.into_iter().map(str::to_owned).collect(),
+            extensions: self.extensions,
+            private: self.private.to_owned(),
+        }
+    }
+}
+
+impl OwnedBuilder {
+    #[inline]
+    pub fn lang(mut self, lang: &str) -> Self {
+        lang.clone_into(&mut self.lang);
+        self
+    }
+
+    #[inline]
+    pub fn script(mut self, script: &str) -> Self {
+        script.clone_into(&mut self.script);
+        self
+    }
+
+    #[inline]
+    pub fn region(mut self, region: &str) -> Self {
+        region.clone_into(&mut self.region);
+        self
+    }
+
+    #[inline]
+    pub fn private(mut self, private: &str) -> Self {
+        private.clone_into(&mut self.private);
+        self
+    }
+
+    #[inline]
+    pub fn variant(mut self, variant: &str) -> Self {
+        self.variants.push(variant.to_owned());
+        self
+    }
+
+    #[inline]
+    pub fn extension(mut self, extension: &str) -> Self {
+        self.extensions.push(extension.to_owned());
+        self
+    }
+
+    pub fn variants<C: IntoIterator<Item = impl AsRef<str>>>(mut self, c: C) -> Self {
+        self.variants = c.into_iter().map(|v| v.as_ref().into()).collect();
+        self
+    }
+
+    pub fn extensions<C: IntoIterator<Item = impl AsRef<str>>>(mut self, c: C) -> Self {
+        self.extensions = c.into_iter().map(|e| e.as_ref().into()).collect();
+        self
+    }
+
+    #[track_caller]
+    pub fn build(self) -> Tag {
+        Builder {
+            lang: &self.lang,
+            script: &self.script,
+            region: &self.region,
+            variants: self.variants.iter().map(String::as_str).collect(),
+            extensions: self.extensions,
+            private: &self.private,
+        }
+        .build()
+    }
+
+    pub fn try_build(self) -> Result<Tag, ParseTagError> {
+        Builder {
+            lang: &self.lang,
+            script: &self.script,
+            region: &self.region,
+            variants: self.variants.iter().map(String::as_str).collect(),
+            extensions: self.extensions,
+            private: &self.private,
+        }
+        .try_build()
+    }
+}
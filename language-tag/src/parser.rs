@@ -7,8 +7,8 @@ use nom::{
     branch::alt,
     bytes::complete::{tag, take_while_m_n},
     character::complete::{anychar, char, none_of},
-    combinator::{not, opt, peek, recognize, value, verify},
-    error::{context, ContextError, ParseError},
+    combinator::{all_consuming, not, opt, peek, recognize, value, verify},
+    error::{context, ContextError, ErrorKind, ParseError},
     multi::{many0, many_m_n, separated_list1},
     sequence::{delimited, pair, separated_pair, terminated, tuple},
     IResult,
@@ -16,6 +16,8 @@ use nom::{
 
 pub use nom::{error::Error, Finish};
 
+pub type ParseTagError = Error<String>;
+
 fn dash<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, char, E> {
     char('-')(input)
 }
@@ -51,7 +53,7 @@ fn alphanums<'a, E: ParseError<&'a str>>(
     take_while_m_n(m, n, |c: char| c.is_ascii_alphanumeric())
 }
 
-fn private<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, &'a str, E> {
+pub(crate) fn private<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, &'a str, E> {
     extension_form(char('x'), 1)(input)
 }
 
@@ -89,22 +91,77 @@ macro_rules! fixed_parse {
     };
 }
 
-fn langtag<'a, E>(input: &'a str) -> IResult<&'a str, Tag, E>
-where
-    E: ParseError<&'a str> + ContextError<&'a str>,
-{
-    let letters = |l| take_while_m_n(l, l, |c: char| c.is_ascii_alphabetic());
-    let digits = |l| take_while_m_n(l, l, |c: char| c.is_ascii_digit());
+fn letters<'a, E: ParseError<&'a str>>(
+    l: usize,
+) -> impl Fn(&'a str) -> IResult<&'a str, &'a str, E> {
+    take_while_m_n(l, l, |c: char| c.is_ascii_alphabetic())
+}
+
+fn digits<'a, E: ParseError<&'a str>>(
+    l: usize,
+) -> impl Fn(&'a str) -> IResult<&'a str, &'a str, E> {
+    take_while_m_n(l, l, |c: char| c.is_ascii_digit())
+}
+
+pub(crate) fn language<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, &'a str, E> {
+    let extlang = many_m_n(1, 3, subtag(letters(3)));
+    recognize(pair(alphanums(2, 3), opt(extlang)))(input)
+}
+
+pub(crate) fn script<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, &'a str, E> {
+    letters(4)(input)
+}
+
+pub(crate) fn region<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, &'a str, E> {
+    alt((letters(2), digits(3)))(input)
+}
+
+pub(crate) fn variant<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, &'a str, E> {
     let ident = verify(alphanums(4, 4), |s: &str| {
         s.starts_with(|c: char| c.is_ascii_digit())
     });
+    // Longest first, or the digit led form would take the first four
+    // characters of a variant such as 1606nict.
+    alt((alphanums(5, 8), ident))(input)
+}
+
+pub(crate) fn extension<'a, E: ParseError<&'a str>>(
+    input: &'a str,
+) -> IResult<&'a str, &'a str, E> {
     let singleton = verify(none_of("xX"), |c| c.is_ascii_alphanumeric());
-    let extlang = many_m_n(1, 3, subtag(letters(3)));
-    let language = recognize(pair(alphanums(2, 3), opt(extlang)));
-    let script = subtag(letters(4));
-    let region = subtag(alt((letters(2), digits(3))));
-    let variant = subtag(alt((ident, alphanums(5, 8))));
-    let extension = subtag(extension_form(singleton, 2));
+    extension_form(singleton, 2)(input)
+}
+
+// Check a lone component string is entirely matched by parser.
+pub(crate) fn validate<'a, O, F>(parser: F, input: &'a str) -> Result<(), ParseTagError>
+where
+    F: FnMut(&'a str) -> IResult<&'a str, O, Error<&'a str>>,
+{
+    all_consuming(parser)(input)
+        .finish()
+        .map(|_| ())
+        .map_err(|Error { input, code }| ParseTagError {
+            input: input.to_owned(),
+            code,
+        })
+}
+
+// The error for input that would make a tag longer than Tag::MAX_LEN.
+pub(crate) fn too_large(input: &str) -> ParseTagError {
+    ParseTagError {
+        input: input.to_owned(),
+        code: ErrorKind::TooLarge,
+    }
+}
+
+fn langtag<'a, E>(input: &'a str) -> IResult<&'a str, Tag, E>
+where
+    E: ParseError<&'a str> + ContextError<&'a str>,
+{
+    let script = subtag(script);
+    let region = subtag(region);
+    let variant = subtag(variant);
+    let extension = subtag(extension);
     let terminator = not(peek(verify(anychar, |c| {
         *c == '-' || c.is_ascii_alphanumeric()
     })));
@@ -140,7 +197,7 @@ where
     E: ParseError<&'a str> + ContextError<&'a str>,
 {
     let (input, pu) = context("private use tag", private)(input)?;
-    Ok((input, Tag::privateuse(pu)))
+    Ok((input, Tag::private_use_only(pu)))
 }
 
 fn grandfathered_regular<'a, E>(input: &'a str) -> IResult<&'a str, Tag, E>
@@ -204,9 +261,12 @@ where
 }
 
 impl FromStr for Tag {
-    type Err = Error<String>;
+    type Err = ParseTagError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() > Tag::MAX_LEN {
+            return Err(too_large(s));
+        }
         match languagetag(s).finish() {
             Ok((_, tag)) => Ok(tag),
             Err(Error { input, code }) => Err(Self::Err {
@@ -259,4 +319,32 @@ mod tests {
             assert_eq!(test, *result);
         }
     }
+
+    #[test]
+    fn digit_led_variants() {
+        use crate::Tag;
+
+        for tag in ["frm-1606nict", "en-1994-x-test", "de-Latn-1901-1606nict"] {
+            assert_eq!(tag.parse::<Tag>().map(|t| t.to_string()), Ok(tag.into()));
+        }
+        assert!("en-1a".parse::<Tag>().is_err());
+    }
+
+    #[test]
+    fn too_long() {
+        use crate::{ParseTagError, Tag};
+        use nom::error::ErrorKind;
+
+        let long = format!("en{}", "-abcdefgh".repeat(28));
+        assert_eq!(long.len(), Tag::MAX_LEN - 1);
+        assert!(long.parse::<Tag>().is_ok());
+        let longer = long + "-xy";
+        assert_eq!(
+            longer.parse::<Tag>(),
+            Err(ParseTagError {
+                input: longer.clone(),
+                code: ErrorKind::TooLarge
+            })
+        );
+    }
 }
@@ -1,14 +1,27 @@
-use crate::Builder;
+use crate::{
+    parser::{self, ParseTagError},
+    Builder, OwnedBuilder,
+};
 use core::panic;
 use serde_with::{DeserializeFromStr, SerializeDisplay};
 use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    collections::hash_map::DefaultHasher,
     fmt::{Display, Write},
-    hash::Hash,
+    hash::{Hash, Hasher},
     iter::{once, FusedIterator},
     num::NonZeroUsize,
+    ops::{Add, AddAssign, BitOr, Deref},
     str::SplitTerminator,
+    sync::Arc,
 };
 
+// Component ends are held as u8 offsets into the tag, limiting tags to
+// Tag::MAX_LEN bytes. A tag with every component at its longest is around
+// 116 bytes (an 8 letter language, script, region, 8 variants and a few
+// extensions), so the limit only rejects nonsense.
+
 #[derive(Clone, Debug, Default)]
 struct Offsets {
     lang: u8,
@@ -18,30 +31,38 @@ struct Offsets {
     extensions: u8,
 }
 
+// Move an offset by delta. Edits check the resulting length first, so this
+// can't leave the u8 range.
+#[inline]
+#[track_caller]
+fn offset(end: u8, delta: isize) -> u8 {
+    u8::try_from(end as isize + delta).expect("tag offsets lie within Tag::MAX_LEN")
+}
+
 impl Offsets {
     #[inline]
     fn adjust_lang(&mut self, delta: isize) {
-        self.lang = self.lang.wrapping_add_signed(delta as i8);
+        self.lang = offset(self.lang, delta);
         self.adjust_script(delta);
     }
     #[inline]
     fn adjust_script(&mut self, delta: isize) {
-        self.script = self.script.wrapping_add_signed(delta as i8);
+        self.script = offset(self.script, delta);
         self.adjust_region(delta);
     }
     #[inline]
     fn adjust_region(&mut self, delta: isize) {
-        self.region = self.region.wrapping_add_signed(delta as i8);
+        self.region = offset(self.region, delta);
         self.adjust_variants(delta);
     }
     #[inline]
     fn adjust_variants(&mut self, delta: isize) {
-        self.variants = self.variants.wrapping_add_signed(delta as i8);
+        self.variants = offset(self.variants, delta);
         self.adjust_extensions(delta);
     }
     #[inline]
     fn adjust_extensions(&mut self, delta: isize) {
-        self.extensions = self.extensions.wrapping_add_signed(delta as i8);
+        self.extensions = offset(self.extensions, delta);
     }
 }
 
@@ -83,7 +104,30 @@ macro_rules! component_range {
     }};
 }
 
+// Retired ISO 639 language codes still seen in the wild, and the codes that
+// replaced them, per the IANA subtag registry's Preferred-Value fields.
+static RETIRED_LANGS: [(&str, &str); 15] = [
+    ("drh", "khk"),
+    ("drw", "prs"),
+    ("gav", "dev"),
+    ("in", "id"),
+    ("iw", "he"),
+    ("ji", "yi"),
+    ("jw", "jv"),
+    ("ktr", "dtp"),
+    ("kzj", "dtp"),
+    ("mo", "ro"),
+    ("mst", "mry"),
+    ("tie", "ras"),
+    ("tnf", "prs"),
+    ("ybd", "rki"),
+    ("yma", "lrr"),
+];
+
 impl Tag {
+    // The longest tag the parser accepts.
+    pub const MAX_LEN: usize = u8::MAX as usize;
+
     pub(crate) fn new(
         full: &str,
         lang: usize,
@@ -94,7 +138,7 @@ impl Tag {
         private: impl Into<Option<NonZeroUsize>>,
     ) -> Self {
         if lang == 0 && private.into().is_some() {
-            Tag::privateuse(full)
+            Tag::private_use_only(full)
         } else {
             let mut end = Offsets {
                 lang: lang as u8,
@@ -122,6 +166,8 @@ impl Tag {
         }
     }
 
+    // Panics if the tag would be longer than Tag::MAX_LEN.
+    #[track_caller]
     pub(crate) fn from_parts<'a>(
         lang: &'a str,
         script: impl Into<Option<&'a str>>,
@@ -130,10 +176,27 @@ impl Tag {
         extensions: impl IntoIterator<Item = &'a str, IntoIter = impl Iterator<Item = &'a str> + Clone>,
         private: impl Into<Option<&'a str>>,
     ) -> Self {
+        match Tag::try_from_parts(lang, script, region, variants, extensions, private) {
+            Ok(tag) => tag,
+            Err(err) => panic!("{} is longer than Tag::MAX_LEN", err.input),
+        }
+    }
+
+    pub(crate) fn try_from_parts<'a>(
+        lang: &'a str,
+        script: impl Into<Option<&'a str>>,
+        region: impl Into<Option<&'a str>>,
+        variants: impl IntoIterator<Item = &'a str, IntoIter = impl Iterator<Item = &'a str> + Clone>,
+        extensions: impl IntoIterator<Item = &'a str, IntoIter = impl Iterator<Item = &'a str> + Clone>,
+        private: impl Into<Option<&'a str>>,
+    ) -> Result<Self, ParseTagError> {
         let private = private.into();
         if lang.is_empty() {
             if let Some(private) = private {
-                return Tag::privateuse(private);
+                if private.len() > Tag::MAX_LEN {
+                    return Err(parser::too_large(private));
+                }
+                return Ok(Tag::private_use_only(private));
             }
         }
         let script = script.into();
@@ -160,8 +223,11 @@ impl Tag {
                 full.push('-');
                 full.push_str(v)
             });
+        if full.len() > Tag::MAX_LEN {
+            return Err(parser::too_large(&full));
+        }
 
-        Tag::new(
+        Ok(Tag::new(
             &full,
             lang.len(),
             script.and_then(|r| r.len().try_into().ok()),
@@ -169,11 +235,13 @@ impl Tag {
             variants.map(|v| v.len().try_into().unwrap()),
             extensions.map(|e| e.len().try_into().unwrap()),
             private.and_then(|r| r.len().try_into().ok()),
-        )
+        ))
     }
 
     #[inline]
+    #[track_caller]
     pub fn with_lang(lang: impl AsRef<str>) -> Self {
+        Tag::assert_max_len(lang.as_ref().len());
         let len = lang.as_ref().len() as u8;
         Tag {
             buf: lang.as_ref().to_owned(),
@@ -188,50 +256,146 @@ impl Tag {
     }
 
     #[inline]
-    pub fn privateuse(private: impl AsRef<str>) -> Self {
+    #[track_caller]
+    pub fn private_use_only(private: impl AsRef<str>) -> Self {
+        Tag::assert_max_len(private.as_ref().len());
         Tag {
             buf: private.as_ref().to_owned(),
             end: Default::default(),
         }
     }
 
+    #[deprecated(since = "0.2.0", note = "use private_use_only")]
+    #[inline]
+    pub fn privateuse(private: impl AsRef<str>) -> Self {
+        Tag::private_use_only(private)
+    }
+
     #[inline(always)]
     pub fn builder<'a>() -> Builder<'a> {
         Builder::default()
     }
 
+    #[inline]
+    pub fn into_builder(self) -> OwnedBuilder {
+        self.into()
+    }
+
     #[inline(always)]
     pub fn shrink_to_fit(&mut self) {
         self.buf.shrink_to_fit();
     }
 
+    // Bytes allocated on the heap for this tag.
+    #[inline(always)]
+    pub fn heap_size(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    // Check the component offsets are in order and lie within the buffer,
+    // and the buffer within Tag::MAX_LEN, after any edit. Compiled out of
+    // release builds.
+    #[inline]
+    #[track_caller]
+    fn assert_invariants(&self) {
+        #[cfg(debug_assertions)]
+        {
+            let Offsets {
+                lang,
+                script,
+                region,
+                variants,
+                extensions,
+            } = self.end;
+            assert!(lang <= script, "{self:?}: lang ends after script");
+            assert!(script <= region, "{self:?}: script ends after region");
+            assert!(region <= variants, "{self:?}: region ends after variants");
+            assert!(
+                variants <= extensions,
+                "{self:?}: variants end after extensions"
+            );
+            assert!(
+                extensions as usize <= self.buf.len(),
+                "{self:?}: extensions end past the end of the tag"
+            );
+            assert!(
+                self.buf.len() <= Tag::MAX_LEN,
+                "{self:?}: longer than Tag::MAX_LEN"
+            );
+        }
+    }
+
+    #[inline(always)]
+    #[track_caller]
+    fn assert_max_len(len: usize) {
+        if len > Tag::MAX_LEN {
+            panic!("a {len} byte tag is longer than Tag::MAX_LEN");
+        }
+    }
+
+    // The length of the tag once range is replaced by component, and the
+    // '-' before it if the component is set. Edits check this before
+    // changing anything, so a refused edit leaves the tag as it was.
+    #[inline]
+    fn edited_len(&self, range: std::ops::Range<usize>, component: &str) -> usize {
+        let dash = usize::from(range.start != 0 && !component.is_empty());
+        self.buf.len() - range.len() + dash + component.len()
+    }
+
+    #[inline]
+    fn check_len(
+        &self,
+        range: std::ops::Range<usize>,
+        component: &str,
+    ) -> Result<(), ParseTagError> {
+        if self.edited_len(range, component) > Tag::MAX_LEN {
+            Err(parser::too_large(component))
+        } else {
+            Ok(())
+        }
+    }
+
+    // The single subtag setters panic if the tag would outgrow Tag::MAX_LEN;
+    // try_set_* report it instead.
+    #[track_caller]
     pub fn set_lang(&mut self, lang: &str) {
+        Tag::assert_max_len(self.edited_len(0..self.end.lang as usize, lang));
         let old = self.buf.len() as isize;
         self.buf.replace_range(..self.end.lang as usize, lang);
         self.end.adjust_lang(self.buf.len() as isize - old);
+        self.assert_invariants();
     }
 
+    #[track_caller]
     pub fn set_script(&mut self, script: &str) {
+        Tag::assert_max_len(self.edited_len(_component_range!(self, script), script));
         let old = self.buf.len() as isize;
         let range = component_range!(self, script);
         self.buf.replace_range(range, script);
         self.end.adjust_script(self.buf.len() as isize - old);
+        self.assert_invariants();
     }
 
+    #[track_caller]
     pub fn set_region(&mut self, region: &str) {
+        Tag::assert_max_len(self.edited_len(_component_range!(self, region), region));
         let old = self.buf.len() as isize;
         let range = component_range!(self, region);
         self.buf.replace_range(range, region);
         self.end.adjust_region(self.buf.len() as isize - old);
+        self.assert_invariants();
     }
 
+    #[track_caller]
     pub fn set_variants<'a>(&mut self, variants: impl AsRef<[&'a str]>) {
         let variants = variants.as_ref();
         let variants = variants.join("-");
+        Tag::assert_max_len(self.edited_len(_component_range!(self, variants), &variants));
         let old = self.buf.len() as isize;
         let range = component_range!(self, variants);
         self.buf.replace_range(range, &variants);
         self.end.adjust_variants(self.buf.len() as isize - old);
+        self.assert_invariants();
     }
 
     #[inline(always)]
@@ -242,10 +406,12 @@ impl Tag {
         }
     }
 
+    // Sort extensions and elide repeated singletons, eg. a-abc, a-def becomes
+    // a-abc-def.
     #[track_caller]
-    pub fn set_extensions<'a>(&mut self, extensions: impl AsRef<[&'a str]>) {
-        let mut extensions = extensions.as_ref().to_vec();
-        let extensions = if extensions.is_empty() {
+    fn join_extensions(extensions: &[&str]) -> String {
+        let mut extensions = extensions.to_vec();
+        if extensions.is_empty() {
             Default::default()
         } else {
             extensions.sort_unstable();
@@ -260,25 +426,74 @@ impl Tag {
                 }
             }
             extensions.join("-")
-        };
+        }
+    }
 
+    #[track_caller]
+    pub fn set_extensions<'a>(&mut self, extensions: impl AsRef<[&'a str]>) {
+        let extensions = Tag::join_extensions(extensions.as_ref());
+        Tag::assert_max_len(self.edited_len(_component_range!(self, extensions), &extensions));
         let old = self.buf.len() as isize;
         let range = component_range!(self, extensions);
         self.buf.replace_range(range, &extensions);
         self.end.adjust_extensions(self.buf.len() as isize - old);
+        self.assert_invariants();
     }
 
+    #[track_caller]
     pub fn set_private(&mut self, private: &str) {
+        Tag::assert_max_len(self.edited_len(_component_range!(self, private), private));
         let range = component_range!(self, private);
         self.buf.replace_range(range, private);
+        self.assert_invariants();
+    }
+
+    #[inline]
+    pub fn clear_variants(&mut self) {
+        self.set_variants([]);
+    }
+
+    #[inline]
+    pub fn clear_extensions(&mut self) {
+        self.set_extensions([]);
+    }
+
+    #[inline]
+    pub fn clear_private(&mut self) {
+        self.set_private("");
     }
 
     #[track_caller]
     pub fn push_variant(&mut self, variant: &str) {
+        let pos = self.end.variants as usize;
+        self.insert_variant(pos, variant);
+    }
+
+    // As push_variant, but inserted before the first variant that sorts after
+    // it, keeping variants in the order Builder puts them in.
+    #[track_caller]
+    pub fn push_variant_sorted(&mut self, variant: &str) {
+        self.insert_variant(self.sorted_variant_pos(variant), variant);
+    }
+
+    #[inline]
+    fn sorted_variant_pos(&self, variant: &str) -> usize {
+        self.end.region as usize
+            + self
+                .variants()
+                .take_while(|&v| v <= variant)
+                .map(|v| v.len() + 1)
+                .sum::<usize>()
+    }
+
+    #[track_caller]
+    fn insert_variant(&mut self, pos: usize, variant: &str) {
+        Tag::assert_max_len(self.edited_len(pos..pos, variant));
         let old = self.buf.len() as isize;
-        self.buf.insert(self.end.variants as usize, '-');
-        self.buf.insert_str(self.end.variants as usize + 1, variant);
+        self.buf.insert(pos, '-');
+        self.buf.insert_str(pos + 1, variant);
         self.end.adjust_variants(self.buf.len() as isize - old);
+        self.assert_invariants();
     }
 
     pub fn pop_variant(&mut self) -> Option<String> {
@@ -288,9 +503,369 @@ impl Tag {
         range.start = range.end - variant.len() - 1;
         self.buf.replace_range(range, "");
         self.end.adjust_variants(self.buf.len() as isize - old);
+        self.assert_invariants();
         Some(variant)
     }
 
+    // Windows locale names are plain BCP 47, so this is just the parser; it
+    // exists to make the intent clear at call sites.
+    pub fn from_windows_tag(windows_tag: &str) -> Result<Tag, ParseTagError> {
+        windows_tag.parse()
+    }
+
+    // Parse the sort of almost-tags real systems send: underscore separated,
+    // oddly cased or using a retired language code, eg. iw_il becomes he-IL.
+    pub fn parse_lenient(s: &str) -> Result<Tag, ParseTagError> {
+        let s = s.replace('_', "-");
+        let (lang, rest) = s.split_at(s.find('-').unwrap_or(s.len()));
+        let lang = RETIRED_LANGS
+            .iter()
+            .find(|(retired, _)| retired.eq_ignore_ascii_case(lang))
+            .map_or(lang, |&(_, current)| current);
+        let tag: Tag = format!("{lang}{rest}").parse()?;
+        Ok(tag.display_canonical().to_string().parse().unwrap_or(tag))
+    }
+
+    // Build a tag from separately stored components, checking each against
+    // its subtag grammar first. Private use includes its x- prefix.
+    pub fn try_from_components(
+        lang: &str,
+        script: Option<&str>,
+        region: Option<&str>,
+        variants: &[&str],
+        extensions: &[&str],
+        private: Option<&str>,
+    ) -> Result<Tag, ComponentError> {
+        use parser::validate;
+
+        validate(parser::language, lang).map_err(|_| ComponentError::InvalidLang)?;
+        if let Some(script) = script {
+            validate(parser::script, script)
+                .map_err(|_| ComponentError::InvalidScript(script.into()))?;
+        }
+        if let Some(region) = region {
+            validate(parser::region, region)
+                .map_err(|_| ComponentError::InvalidRegion(region.into()))?;
+        }
+        for &variant in variants {
+            validate(parser::variant, variant)
+                .map_err(|_| ComponentError::InvalidVariant(variant.into()))?;
+        }
+        for &extension in extensions {
+            validate(parser::extension, extension)
+                .map_err(|_| ComponentError::InvalidExtension(extension.into()))?;
+        }
+        if let Some(private) = private {
+            validate(parser::private, private)
+                .map_err(|_| ComponentError::InvalidPrivate(private.into()))?;
+        }
+        Tag::builder()
+            .lang(lang)
+            .script(script.unwrap_or_default())
+            .region(region.unwrap_or_default())
+            .variants(variants)
+            .extensions(extensions)
+            .private(private.unwrap_or_default())
+            .try_build()
+            .map_err(|_| ComponentError::TooLong)
+    }
+
+    // Validating counterparts of the setters above, for components taken
+    // from untrusted input. Each rejects anything its subtag grammar would.
+    pub fn try_set_lang(&mut self, lang: &str) -> Result<(), ParseTagError> {
+        parser::validate(parser::language, lang)?;
+        self.check_len(0..self.end.lang as usize, lang)?;
+        self.set_lang(lang);
+        Ok(())
+    }
+
+    pub fn try_set_script(&mut self, script: &str) -> Result<(), ParseTagError> {
+        parser::validate(parser::script, script)?;
+        self.check_len(_component_range!(self, script), script)?;
+        self.set_script(script);
+        Ok(())
+    }
+
+    pub fn try_set_region(&mut self, region: &str) -> Result<(), ParseTagError> {
+        parser::validate(parser::region, region)?;
+        self.check_len(_component_range!(self, region), region)?;
+        self.set_region(region);
+        Ok(())
+    }
+
+    pub fn try_set_variants<'a>(
+        &mut self,
+        variants: impl AsRef<[&'a str]>,
+    ) -> Result<(), ParseTagError> {
+        let variants = variants.as_ref();
+        for variant in variants {
+            parser::validate(parser::variant, variant)?;
+        }
+        self.check_len(_component_range!(self, variants), &variants.join("-"))?;
+        self.set_variants(variants);
+        Ok(())
+    }
+
+    pub fn try_set_extensions<'a>(
+        &mut self,
+        extensions: impl AsRef<[&'a str]>,
+    ) -> Result<(), ParseTagError> {
+        let extensions = extensions.as_ref();
+        for extension in extensions {
+            parser::validate(parser::extension, extension)?;
+        }
+        let joined = Tag::join_extensions(extensions);
+        self.check_len(_component_range!(self, extensions), &joined)?;
+        self.set_extensions(extensions);
+        Ok(())
+    }
+
+    // An empty private clears it, as with set_private.
+    pub fn try_set_private(&mut self, private: &str) -> Result<(), ParseTagError> {
+        if !private.is_empty() {
+            parser::validate(parser::private, private)?;
+        }
+        self.check_len(_component_range!(self, private), private)?;
+        self.set_private(private);
+        Ok(())
+    }
+
+    pub fn try_push_variant(&mut self, variant: &str) -> Result<(), ParseTagError> {
+        parser::validate(parser::variant, variant)?;
+        let pos = self.end.variants as usize;
+        self.check_len(pos..pos, variant)?;
+        self.insert_variant(pos, variant);
+        Ok(())
+    }
+
+    pub fn try_push_variant_sorted(&mut self, variant: &str) -> Result<(), ParseTagError> {
+        parser::validate(parser::variant, variant)?;
+        let pos = self.sorted_variant_pos(variant);
+        self.check_len(pos..pos, variant)?;
+        self.insert_variant(pos, variant);
+        Ok(())
+    }
+
+    pub fn try_add_extension(&mut self, extension: &str) -> Result<(), ParseTagError> {
+        parser::validate(parser::extension, extension)?;
+        if let Err((pos, elided)) = self.find_extension(extension) {
+            self.check_len(pos..pos, elided)?;
+        }
+        self.add_extension(extension);
+        Ok(())
+    }
+
+    // The subtags of the extension introduced by singleton, without it, eg.
+    // ca-islamic for u in en-u-ca-islamic.
+    fn extension_subtags(&self, singleton: &str) -> Option<&str> {
+        let extensions = &self.buf[_component_range!(self, extensions)];
+        let mut start = None;
+        let mut offset = 0;
+        for subtag in extensions.split('-') {
+            let next = offset + subtag.len() + 1;
+            if subtag.len() == 1 {
+                if let Some(start) = start {
+                    return Some(&extensions[start..offset - 1]);
+                }
+                if subtag.eq_ignore_ascii_case(singleton) {
+                    start = Some(next);
+                }
+            }
+            offset = next;
+        }
+        start.and_then(|s| extensions.get(s..))
+    }
+
+    // The keywords of the u extension as (key, value) pairs, eg. u-ca-islamic
+    // gives (ca, islamic). A key without a value is paired with "".
+    pub fn unicode_extension_pairs(&self) -> UnicodeKeywords<'_> {
+        UnicodeKeywords::new(self.extension_subtags("u").unwrap_or_default())
+    }
+
+    // The t extension of a transformed content tag, eg. ja-t0-und-fonipa for
+    // en-t-ja-t0-und-fonipa, split into its source and fields on demand.
+    pub fn t_extension(&self) -> Option<TExtension<'_>> {
+        self.extension_subtags("t").map(TExtension)
+    }
+
+    // The locale content was transformed from, per the t extension, eg. ja-Jpan
+    // for en-Latn-t-ja-jpan. None when there is no t extension or it only
+    // has fields.
+    pub fn source_tag(&self) -> Option<Tag> {
+        self.t_extension()?.source_tag()
+    }
+
+    // This tag's script followed by the source tag's, as written, eg. Latn
+    // then jpan for en-Latn-t-ja-jpan.
+    pub fn all_scripts(&self) -> impl Iterator<Item = &str> {
+        let source_script = self.t_extension().and_then(|t| t.source_script());
+        self.script().into_iter().chain(source_script)
+    }
+
+    // Look up a u extension keyword's value by its two character key.
+    pub fn unicode_extension_value(&self, key: &str) -> Option<&str> {
+        let valid_key = key.len() == 2
+            && key
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit());
+        if !valid_key {
+            return None;
+        }
+        self.unicode_extension_pairs()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v)
+    }
+
+    // Copies of this tag with one component removed, for chaining.
+    pub fn without_private(&self) -> Tag {
+        let mut tag = self.clone();
+        tag.clear_private();
+        tag
+    }
+
+    pub fn without_extensions(&self) -> Tag {
+        let mut tag = self.clone();
+        tag.clear_extensions();
+        tag
+    }
+
+    pub fn without_variants(&self) -> Tag {
+        let mut tag = self.clone();
+        tag.clear_variants();
+        tag
+    }
+
+    pub fn without_region(&self) -> Tag {
+        let mut tag = self.clone();
+        tag.set_region("");
+        tag
+    }
+
+    pub fn without_script(&self) -> Tag {
+        let mut tag = self.clone();
+        tag.set_script("");
+        tag
+    }
+
+    // Drop every component after the region, script or language, keeping
+    // those up to and including it. A private use only tag, having no
+    // language, is left as it is.
+    pub fn truncate_to_region(&mut self) {
+        if self.lang().is_empty() {
+            return;
+        }
+        self.end.variants = self.end.region;
+        self.end.extensions = self.end.region;
+        self.buf.truncate(self.end.extensions as usize);
+        self.assert_invariants();
+    }
+
+    pub fn truncate_to_script(&mut self) {
+        if self.lang().is_empty() {
+            return;
+        }
+        self.end.region = self.end.script;
+        self.truncate_to_region();
+    }
+
+    pub fn truncate_to_lang(&mut self) {
+        if self.lang().is_empty() {
+            return;
+        }
+        self.end.script = self.end.lang;
+        self.truncate_to_script();
+    }
+
+    // This tag followed by each distinct shorter form truncate_to_region,
+    // truncate_to_script and truncate_to_lang give, for locale fallback, eg.
+    // en-Latn-US-fonipa, en-Latn-US, en-Latn, en. A private use only tag has
+    // no shorter forms.
+    pub fn truncated_forms(&self) -> impl Iterator<Item = Tag> {
+        let truncations: [fn(&mut Tag); 3] = [
+            Tag::truncate_to_region,
+            Tag::truncate_to_script,
+            Tag::truncate_to_lang,
+        ];
+        let mut forms = vec![self.clone()];
+        for truncate in truncations {
+            let mut tag = forms.last().unwrap().clone();
+            truncate(&mut tag);
+            if tag.buf.len() < forms.last().unwrap().buf.len() {
+                forms.push(tag);
+            }
+        }
+        forms.into_iter()
+    }
+
+    // The length of this tag once merged with other.
+    fn merged_len(&self, other: &Tag) -> usize {
+        if self.is_private_use() {
+            return self.buf.len();
+        }
+        let fill = |has: bool, theirs: &str| {
+            if has || theirs.is_empty() {
+                0
+            } else {
+                theirs.len() + 1
+            }
+        };
+        self.buf.len()
+            + if self.lang().is_empty() {
+                other.lang().len()
+            } else {
+                0
+            }
+            + fill(self.script().is_some(), other.script().unwrap_or_default())
+            + fill(self.region().is_some(), other.region().unwrap_or_default())
+            + fill(self.has_variants(), other.variants_str())
+            + fill(self.has_extensions(), other.extensions_str())
+            + fill(self.private().is_some(), other.private_str())
+    }
+
+    // Fill in any component this tag lacks from other, leaving those it
+    // already has alone, eg. en merged with de-Latn-US gives en-Latn-US. A
+    // private use only tag has no language to hang the rest on, so it is
+    // left unchanged.
+    #[track_caller]
+    pub fn merge_with(&mut self, other: &Tag) {
+        if self.is_private_use() {
+            return;
+        }
+        Tag::assert_max_len(self.merged_len(other));
+        if self.lang().is_empty() {
+            self.set_lang(other.lang());
+        }
+        if self.script().is_none() {
+            self.set_script(other.script().unwrap_or_default());
+        }
+        if self.region().is_none() {
+            self.set_region(other.region().unwrap_or_default());
+        }
+        if !self.has_variants() {
+            self.set_variants(other.variants().collect::<Vec<_>>());
+        }
+        if !self.has_extensions() {
+            let extensions = other
+                .extensions()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>();
+            self.set_extensions(extensions.iter().map(String::as_str).collect::<Vec<_>>());
+        }
+        if self.private().is_none() {
+            self.set_private(other.private().unwrap_or_default());
+        }
+    }
+
+    // As merge_with, but failing, leaving the tag alone, if the result would
+    // outgrow Tag::MAX_LEN.
+    pub fn try_merge_with(&mut self, other: &Tag) -> Result<(), ParseTagError> {
+        if self.merged_len(other) > Tag::MAX_LEN {
+            return Err(parser::too_large(other.as_ref()));
+        }
+        self.merge_with(other);
+        Ok(())
+    }
+
     fn find_extension<'c, 'e: 'c>(
         &'c self,
         extension: &'e str,
@@ -347,12 +922,15 @@ impl Tag {
         self.find_extension(extension).is_ok()
     }
 
+    #[track_caller]
     pub fn add_extension(&mut self, extension: &str) {
         if let Err((pos, extension)) = self.find_extension(extension) {
+            Tag::assert_max_len(self.edited_len(pos..pos, extension));
             let old = self.buf.len() as isize;
             self.buf.insert(pos, '-');
             self.buf.insert_str(pos + 1, extension);
             self.end.adjust_extensions(self.buf.len() as isize - old);
+            self.assert_invariants();
         }
     }
 
@@ -362,6 +940,7 @@ impl Tag {
             self.buf
                 .replace_range(start - 1..start + extension.len(), "");
             self.end.adjust_extensions(self.buf.len() as isize - old);
+            self.assert_invariants();
             true
         } else {
             false
@@ -373,6 +952,35 @@ impl Tag {
         &self.buf[..self.end.lang as usize]
     }
 
+    // The primary language subtag, without any extended language subtags.
+    #[inline]
+    pub fn primary_lang(&self) -> &str {
+        let lang = self.lang();
+        lang.split_once('-').map_or(lang, |(primary, _)| primary)
+    }
+
+    #[inline]
+    pub fn extlangs(&self) -> Subtags<'_> {
+        let lang = self.lang();
+        Subtags::new(lang.split_once('-').map_or("", |(_, extlangs)| extlangs))
+    }
+
+    #[inline]
+    pub fn has_extlang(&self) -> bool {
+        self.lang().contains('-')
+    }
+
+    // Collapse extended language subtags into the primary language, as BCP 47
+    // canonical form requires, eg. zh-gan becomes gan. Every registered
+    // extlang is also a primary language subtag, so the last one present is
+    // kept and the rest dropped.
+    pub fn normalize_extlang(&mut self) {
+        if let Some(extlang) = self.extlangs().next_back() {
+            let extlang = extlang.to_owned();
+            self.set_lang(&extlang);
+        }
+    }
+
     #[inline]
     pub fn script(&self) -> Option<&str> {
         let s = &self.buf[self.end.lang as usize..self.end.script as usize];
@@ -394,7 +1002,7 @@ impl Tag {
     }
 
     #[inline]
-    pub fn variants(&self) -> Variants {
+    pub fn variants(&self) -> Variants<'_> {
         let mut range = self.end.region as usize..self.end.variants as usize;
         if !range.is_empty() {
             range.start += 1;
@@ -403,7 +1011,7 @@ impl Tag {
     }
 
     #[inline]
-    pub fn extensions(&self) -> Extentions {
+    pub fn extensions(&self) -> Extentions<'_> {
         let mut range = self.end.variants as usize..self.end.extensions as usize;
         if !range.is_empty() {
             range.start += 1;
@@ -411,16 +1019,127 @@ impl Tag {
         Extentions::new(&self.buf[range.clone()])
     }
 
+    // The private use part, including its x- prefix. A private use only tag
+    // is all private use, with no '-' separating it from the rest.
     #[inline]
     pub fn private(&self) -> Option<&str> {
-        let s = &self.buf[self.end.extensions as usize..];
+        let start = self.end.extensions as usize;
+        let s = &self.buf[start..];
         if s.is_empty() {
             None
+        } else if start == 0 {
+            Some(s)
         } else {
             Some(&s[1..])
         }
     }
 
+    // The raw variants, extensions and private use parts of the tag, without
+    // their leading '-', borrowed straight from the tag; "" when absent.
+    #[inline]
+    pub fn variants_str(&self) -> &str {
+        self.component_str(_component_range!(self, variants))
+    }
+
+    #[inline]
+    pub fn extensions_str(&self) -> &str {
+        self.component_str(_component_range!(self, extensions))
+    }
+
+    #[inline]
+    pub fn private_str(&self) -> &str {
+        self.component_str(_component_range!(self, private))
+    }
+
+    // The tag split before its script, eg. en-Latn-US gives (en, Latn-US),
+    // and before its region, giving (en-Latn, US). The joining '-' is in
+    // neither half, and a missing component leaves the split where it would
+    // be.
+    #[inline]
+    pub fn split_at_script(&self) -> (&str, &str) {
+        self.split_at(self.end.lang as usize)
+    }
+
+    #[inline]
+    pub fn split_at_region(&self) -> (&str, &str) {
+        self.split_at(self.end.script as usize)
+    }
+
+    #[inline(always)]
+    fn split_at(&self, mid: usize) -> (&str, &str) {
+        let (head, tail) = self.buf.split_at(mid);
+        (head, tail.strip_prefix('-').unwrap_or(tail))
+    }
+
+    #[inline(always)]
+    fn component_str(&self, range: std::ops::Range<usize>) -> &str {
+        let s = &self.buf[range];
+        // A private use only tag has no leading '-' to strip.
+        s.strip_prefix('-').unwrap_or(s)
+    }
+
+    #[inline]
+    pub fn count_variants(&self) -> usize {
+        self.buf[_component_range!(self, variants)]
+            .bytes()
+            .filter(|&b| b == b'-')
+            .count()
+    }
+
+    // The number of subtags, counting the language with any extended
+    // language subtags as one, and extensions as extensions() yields them.
+    // The x introducing private use subtags is not counted.
+    pub fn component_count(&self) -> usize {
+        usize::from(!self.lang().is_empty())
+            + usize::from(self.script().is_some())
+            + usize::from(self.region().is_some())
+            + self.count_variants()
+            + self.count_extensions()
+            + self.private_str().split('-').skip(1).count()
+    }
+
+    #[inline]
+    pub fn depth(&self) -> u8 {
+        self.component_count() as u8
+    }
+
+    // Counts extension values, as yielded by extensions(), not the singleton
+    // namespace subtags that introduce them.
+    #[inline]
+    pub fn count_extensions(&self) -> usize {
+        self.buf[_component_range!(self, extensions)]
+            .split('-')
+            .filter(|s| s.len() > 1)
+            .count()
+    }
+
+    #[inline]
+    pub fn variant_count(&self) -> usize {
+        self.count_variants()
+    }
+
+    // Counts extension namespaces, ie. the singleton subtags introducing each
+    // run of extension values, rather than the values themselves.
+    #[inline]
+    pub fn extension_count(&self) -> usize {
+        self.buf[_component_range!(self, extensions)]
+            .split('-')
+            .filter(|s| s.len() == 1)
+            .count()
+    }
+
+    #[inline]
+    pub fn namespace_count(&self) -> usize {
+        self.extension_count()
+    }
+
+    // Counts the subtags following the x- singleton.
+    #[inline]
+    pub fn count_private_subtags(&self) -> usize {
+        let s = &self.buf[_component_range!(self, private)];
+        s.bytes().filter(|&b| b == b'-').count() - usize::from(s.starts_with('-'))
+    }
+
     #[inline(always)]
     pub fn has_variants(&self) -> bool {
         self.end.variants != self.end.region
@@ -432,9 +1151,141 @@ impl Tag {
     }
 
     #[inline]
-    pub fn is_privateuse(&self) -> bool {
+    pub fn is_private_use(&self) -> bool {
         self.end.extensions == 0 && !self.buf.is_empty()
     }
+
+    #[deprecated(since = "0.2.0", note = "use is_private_use")]
+    #[inline]
+    pub fn is_privateuse(&self) -> bool {
+        self.is_private_use()
+    }
+
+    // The private use subtags of a private use only tag, eg. x-priv.
+    #[inline]
+    pub fn into_private_use(self) -> Option<String> {
+        self.is_private_use().then_some(self.buf)
+    }
+
+    #[inline(always)]
+    pub fn display_lowercase(&self) -> TagLowercase<'_> {
+        TagLowercase(self)
+    }
+
+    #[inline(always)]
+    pub fn display_uppercase(&self) -> TagUppercase<'_> {
+        TagUppercase(self)
+    }
+
+    #[inline(always)]
+    pub fn display_canonical(&self) -> TagCanonical<'_> {
+        TagCanonical(self)
+    }
+
+    // Unlike ==, which is only case insensitive, this also ignores the order
+    // variants and extensions were added in, eg. by push_variant.
+    pub fn equals_canonical(&self, other: &Tag) -> bool {
+        self.canonical_key() == other.canonical_key()
+    }
+
+    // RFC 4647 §3.3.2 extended filtering of this tag by a language range, eg.
+    // en-*-US. A * matches any run of subtags, and otherwise the range's
+    // subtags must appear in order, skipping over tag subtags but never past
+    // a singleton. So unlike §3.3.1 basic filtering, a plain range need not
+    // be a prefix of the tag: en-US matches en-Latn-US.
+    pub fn matches_range(&self, range: &str) -> bool {
+        let mut range = range.split('-');
+        let mut subtags = self.buf.split('-').peekable();
+        match (range.next(), subtags.next()) {
+            (Some("*"), _) => {}
+            (Some(r), Some(s)) if r.eq_ignore_ascii_case(s) => {}
+            _ => return false,
+        }
+        for r in range.filter(|&r| r != "*") {
+            loop {
+                match subtags.next() {
+                    Some(s) if r.eq_ignore_ascii_case(s) => break,
+                    Some(s) if s.len() > 1 => continue,
+                    _ => return false,
+                }
+            }
+        }
+        true
+    }
+
+    // RFC 4647 §3.4 lookup of the best of candidates for this tag as a
+    // language range. Subtags are stripped from the end one at a time, along
+    // with any singleton left dangling, until some candidate matches exactly
+    // (ignoring case) or only the primary language is left to try.
+    pub fn language_range_lookup<'a>(&self, candidates: &'a [Tag]) -> Option<&'a Tag> {
+        let mut range = self.buf.as_str();
+        loop {
+            if let Some(found) = candidates
+                .iter()
+                .find(|c| c.buf.eq_ignore_ascii_case(range))
+            {
+                return Some(found);
+            }
+            range = &range[..range.rfind('-')?];
+            if range.rfind('-').is_some_and(|i| range.len() - i == 2) {
+                range = &range[..range.len() - 2];
+            }
+        }
+    }
+
+    // A hash consistent with equals_canonical.
+    pub fn semantic_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.canonical_key().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn canonical_key(&self) -> String {
+        let mut tag = self.clone();
+        tag.buf.make_ascii_lowercase();
+        let mut variants: Vec<_> = self.variants().map(str::to_ascii_lowercase).collect();
+        variants.sort_unstable();
+        tag.set_variants(variants.iter().map(String::as_str).collect::<Vec<_>>());
+        let extensions: Vec<_> = self
+            .extensions()
+            .map(|e| e.to_string().to_ascii_lowercase())
+            .collect();
+        tag.set_extensions(extensions.iter().map(String::as_str).collect::<Vec<_>>());
+        tag.buf
+    }
+}
+
+// Tags are stored as their string form and reparsed on the way back in, so a
+// corrupt cache cannot produce a Tag with inconsistent offsets.
+#[cfg(feature = "bincode")]
+impl bincode::Encode for Tag {
+    fn encode<E: bincode::enc::Encoder>(
+        &self,
+        encoder: &mut E,
+    ) -> Result<(), bincode::error::EncodeError> {
+        self.buf.encode(encoder)
+    }
+}
+
+#[cfg(feature = "bincode")]
+impl bincode::Decode for Tag {
+    fn decode<D: bincode::de::Decoder>(
+        decoder: &mut D,
+    ) -> Result<Self, bincode::error::DecodeError> {
+        String::decode(decoder)?
+            .parse()
+            .map_err(|err: ParseTagError| bincode::error::DecodeError::OtherString(err.to_string()))
+    }
+}
+
+#[cfg(feature = "bincode")]
+bincode::impl_borrow_decode!(Tag);
+
+impl AsRef<str> for Tag {
+    #[inline(always)]
+    fn as_ref(&self) -> &str {
+        &self.buf
+    }
 }
 
 impl Display for Tag {
@@ -444,6 +1295,99 @@ impl Display for Tag {
     }
 }
 
+// Case converting display adapters
+fn write_mapped(
+    f: &mut std::fmt::Formatter,
+    s: &str,
+    mut map: impl FnMut(char) -> char,
+) -> std::fmt::Result {
+    s.chars().try_for_each(|c| f.write_char(map(c)))
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TagLowercase<'c>(&'c Tag);
+
+impl Display for TagLowercase<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write_mapped(f, &self.0.buf, |c| c.to_ascii_lowercase())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TagUppercase<'c>(&'c Tag);
+
+impl Display for TagUppercase<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write_mapped(f, &self.0.buf, |c| c.to_ascii_uppercase())
+    }
+}
+
+// Formats a tag using the BCP 47 case conventions: lowercase language,
+// titlecase script, uppercase region and lowercase everything else.
+#[derive(Clone, Copy, Debug)]
+pub struct TagCanonical<'c>(&'c Tag);
+
+impl Display for TagCanonical<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let tag = self.0;
+        let lower = |c: char| c.to_ascii_lowercase();
+        write_mapped(f, tag.lang(), lower)?;
+        let mut first = true;
+        write_mapped(f, &tag.buf[_component_range!(tag, script)], |c| {
+            if c == '-' {
+                c
+            } else if first {
+                first = false;
+                c.to_ascii_uppercase()
+            } else {
+                c.to_ascii_lowercase()
+            }
+        })?;
+        write_mapped(f, &tag.buf[_component_range!(tag, region)], |c| {
+            c.to_ascii_uppercase()
+        })?;
+        write_mapped(f, &tag.buf[tag.end.region as usize..], lower)
+    }
+}
+
+// The merging operators panic if the result would outgrow Tag::MAX_LEN, as
+// integer arithmetic does on overflow; try_merge_with reports it instead.
+impl AddAssign<&Tag> for Tag {
+    #[inline]
+    fn add_assign(&mut self, rhs: &Tag) {
+        self.merge_with(rhs);
+    }
+}
+
+impl Add<&Tag> for Tag {
+    type Output = Tag;
+
+    #[inline]
+    fn add(mut self, rhs: &Tag) -> Self::Output {
+        self.merge_with(rhs);
+        self
+    }
+}
+
+impl Add<Tag> for Tag {
+    type Output = Tag;
+
+    #[inline(always)]
+    fn add(self, rhs: Tag) -> Self::Output {
+        self + &rhs
+    }
+}
+
+impl BitOr<&Tag> for Tag {
+    type Output = Tag;
+
+    #[inline]
+    fn bitor(mut self, rhs: &Tag) -> Self::Output {
+        self.merge_with(rhs);
+        self
+    }
+}
+
 impl PartialEq for Tag {
     #[inline(always)]
     fn eq(&self, other: &Self) -> bool {
@@ -453,6 +1397,74 @@ impl PartialEq for Tag {
 
 impl Eq for Tag {}
 
+// String comparisons are case insensitive, as between tags.
+impl PartialEq<str> for Tag {
+    #[inline]
+    fn eq(&self, other: &str) -> bool {
+        self.buf.eq_ignore_ascii_case(other)
+    }
+}
+
+impl PartialEq<&str> for Tag {
+    #[inline]
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<String> for Tag {
+    #[inline]
+    fn eq(&self, other: &String) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<Tag> for &str {
+    #[inline]
+    fn eq(&self, other: &Tag) -> bool {
+        other == *self
+    }
+}
+
+impl PartialEq<Tag> for String {
+    #[inline]
+    fn eq(&self, other: &Tag) -> bool {
+        other == self.as_str()
+    }
+}
+
+impl From<Tag> for String {
+    #[inline]
+    fn from(tag: Tag) -> Self {
+        tag.to_string()
+    }
+}
+
+impl From<Tag> for Arc<str> {
+    #[inline]
+    fn from(tag: Tag) -> Self {
+        tag.as_ref().into()
+    }
+}
+
+impl TryFrom<String> for Tag {
+    type Error = ParseTagError;
+
+    #[inline]
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl TryFrom<Arc<str>> for Tag {
+    type Error = ParseTagError;
+
+    #[inline]
+    fn try_from(s: Arc<str>) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
 impl Hash for Tag {
     #[inline]
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
@@ -460,22 +1472,165 @@ impl Hash for Tag {
     }
 }
 
+// A Tag which compares and hashes by equals_canonical, for use as a map key
+// where tags built up in different orders must still find each other.
+#[derive(Clone, Debug)]
+pub struct SemanticTag(pub Tag);
+
+impl PartialEq for SemanticTag {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0.equals_canonical(&other.0)
+    }
+}
+
+impl Eq for SemanticTag {}
+
+impl Hash for SemanticTag {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.canonical_key().hash(state);
+    }
+}
+
+impl From<Tag> for SemanticTag {
+    #[inline]
+    fn from(tag: Tag) -> Self {
+        SemanticTag(tag)
+    }
+}
+
+impl Deref for SemanticTag {
+    type Target = Tag;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+// A Tag stored in lowercase, so it can implement Borrow<str> and be looked
+// up in maps and sets by &str. Tag itself cannot: its == and Hash ignore case,
+// which str's do not. The price is that lookup keys must be lowercase too,
+// and the original casing of the tag is lost; display_canonical restores the
+// BCP 47 conventional form.
+#[derive(Clone, Debug)]
+pub struct CaseFoldedTag(Tag);
+
+pub type CaseFoldedTagSet = std::collections::HashSet<CaseFoldedTag>;
+
+impl From<Tag> for CaseFoldedTag {
+    fn from(mut tag: Tag) -> Self {
+        tag.buf.make_ascii_lowercase();
+        CaseFoldedTag(tag)
+    }
+}
+
+impl CaseFoldedTag {
+    #[inline]
+    pub fn into_inner(self) -> Tag {
+        self.0
+    }
+}
+
+// Hash and equality must agree with the str returned by borrow.
+impl PartialEq for CaseFoldedTag {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0.buf == other.0.buf
+    }
+}
+
+impl Eq for CaseFoldedTag {}
+
+impl Hash for CaseFoldedTag {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.buf.hash(state);
+    }
+}
+
+impl Borrow<str> for CaseFoldedTag {
+    #[inline]
+    fn borrow(&self) -> &str {
+        &self.0.buf
+    }
+}
+
+impl Deref for CaseFoldedTag {
+    type Target = Tag;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+// ASCII case insensitive comparisons, without lowercasing copies.
+fn cmp_ignore_case(a: &str, b: &str) -> Ordering {
+    a.bytes()
+        .map(|c| c.to_ascii_lowercase())
+        .cmp(b.bytes().map(|c| c.to_ascii_lowercase()))
+}
+
+fn cmp_opt_ignore_case(a: Option<&str>, b: Option<&str>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => cmp_ignore_case(a, b),
+        _ => a.is_some().cmp(&b.is_some()),
+    }
+}
+
+fn cmp_seq_ignore_case<'a>(
+    mut a: impl Iterator<Item = &'a str>,
+    mut b: impl Iterator<Item = &'a str>,
+) -> Ordering {
+    loop {
+        match (a.next(), b.next()) {
+            (Some(x), Some(y)) => match cmp_ignore_case(x, y) {
+                Ordering::Equal => continue,
+                ord => return ord,
+            },
+            (x, y) => return x.is_some().cmp(&y.is_some()),
+        }
+    }
+}
+
+// Component by component, then by the whole tag, so that two tags are
+// only Equal when they are ==, even if they parse into the same
+// components, eg. en-a-bcd-efg and en-a-bcd-a-efg.
 impl Ord for Tag {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        let mut this = self.clone();
-        let mut other = other.clone();
-        this.buf.make_ascii_lowercase();
-        other.buf.make_ascii_lowercase();
-        this.lang()
-            .cmp(other.lang())
-            .then_with(|| this.script().cmp(&other.script()))
-            .then_with(|| this.region().cmp(&other.region()))
+    fn cmp(&self, other: &Self) -> Ordering {
+        cmp_ignore_case(self.lang(), other.lang())
+            .then_with(|| cmp_opt_ignore_case(self.script(), other.script()))
+            .then_with(|| cmp_opt_ignore_case(self.region(), other.region()))
+            .then_with(|| cmp_seq_ignore_case(self.variants(), other.variants()))
+            .then_with(|| {
+                let mut these = self.extensions();
+                let mut those = other.extensions();
+                loop {
+                    match (these.next(), those.next()) {
+                        (Some(x), Some(y)) => {
+                            let ord = x
+                                .namespace
+                                .to_ascii_lowercase()
+                                .cmp(&y.namespace.to_ascii_lowercase())
+                                .then_with(|| cmp_ignore_case(x.name, y.name));
+                            if ord.is_ne() {
+                                return ord;
+                            }
+                        }
+                        (x, y) => return x.is_some().cmp(&y.is_some()),
+                    }
+                }
+            })
+            .then_with(|| cmp_opt_ignore_case(self.private(), other.private()))
+            .then_with(|| cmp_ignore_case(&self.buf, &other.buf))
     }
 }
 
 impl PartialOrd for Tag {
     #[inline]
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
@@ -509,23 +1664,83 @@ impl DoubleEndedIterator for Variants<'_> {
     }
 }
 
+// Iterator over any run of simple dash separated subtags.
+pub type Subtags<'c> = Variants<'c>;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ExtensionRef<'c> {
     name: &'c str,
     namespace: char,
 }
 
+impl<'c> ExtensionRef<'c> {
+    // An extension from its singleton namespace and a single name subtag,
+    // eg. ('u', "islamic").
+    pub fn from_parts(namespace: char, name: &'c str) -> Result<Self, ParseExtensionError> {
+        if !namespace.is_ascii_alphanumeric() || namespace.eq_ignore_ascii_case(&'x') {
+            return Err(ParseExtensionError::InvalidNamespace);
+        }
+        if !(2..=8).contains(&name.len()) {
+            return Err(ParseExtensionError::NameToLong);
+        }
+        if !name.bytes().all(|b| b.is_ascii_alphanumeric()) {
+            return Err(ParseExtensionError::InvalidName);
+        }
+        Ok(ExtensionRef { name, namespace })
+    }
+
+    #[inline]
+    pub fn namespace(&self) -> char {
+        self.namespace
+    }
+
+    #[inline]
+    pub fn name(&self) -> &'c str {
+        self.name
+    }
+}
+
 impl PartialEq<&str> for ExtensionRef<'_> {
     fn eq(&self, other: &&str) -> bool {
         [self.namespace as u8, b'-'].eq(&other.as_bytes()[..2]) && self.name.eq(&other[2..])
     }
 }
 
+// The first component try_from_components found not to fit its grammar, or
+// that the components together are longer than Tag::MAX_LEN.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ComponentError {
+    InvalidLang,
+    InvalidScript(String),
+    InvalidRegion(String),
+    InvalidVariant(String),
+    InvalidExtension(String),
+    InvalidPrivate(String),
+    TooLong,
+}
+
+impl std::error::Error for ComponentError {}
+
+impl Display for ComponentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ComponentError::InvalidLang => f.write_str("invalid language subtag"),
+            ComponentError::InvalidScript(s) => write!(f, "invalid script subtag: {s}"),
+            ComponentError::InvalidRegion(r) => write!(f, "invalid region subtag: {r}"),
+            ComponentError::InvalidVariant(v) => write!(f, "invalid variant subtag: {v}"),
+            ComponentError::InvalidExtension(e) => write!(f, "invalid extension: {e}"),
+            ComponentError::InvalidPrivate(p) => write!(f, "invalid private use: {p}"),
+            ComponentError::TooLong => write!(f, "longer than {} bytes", Tag::MAX_LEN),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ParseExtensionError {
     InvalidNamespace,
     MissingNamespace,
     NameToLong,
+    InvalidName,
 }
 
 impl std::error::Error for ParseExtensionError {}
@@ -538,6 +1753,7 @@ impl Display for ParseExtensionError {
             ParseExtensionError::NameToLong => {
                 "name value must be between 2 and 8 ascii characters"
             }
+            ParseExtensionError::InvalidName => "name value must be ascii alphanumeric",
         }
         .fmt(f)
     }
@@ -546,19 +1762,9 @@ impl Display for ParseExtensionError {
 impl<'c> TryFrom<&'c str> for ExtensionRef<'c> {
     type Error = ParseExtensionError;
     fn try_from(s: &'c str) -> Result<Self, Self::Error> {
-        let ns = &s.as_bytes()[..2];
-        match ns {
-            [n, b'-'] if n.is_ascii() => {
-                if s.len() - 2 > 8 || s.len() - 2 < 2 {
-                    Err(ParseExtensionError::NameToLong)
-                } else {
-                    Ok(ExtensionRef {
-                        namespace: (*n).into(),
-                        name: &s[2..],
-                    })
-                }
-            }
-            [_, b'-'] => Err(ParseExtensionError::InvalidNamespace),
+        match s.as_bytes().get(..2) {
+            Some(&[n, b'-']) if n.is_ascii() => ExtensionRef::from_parts(n.into(), &s[2..]),
+            Some([_, b'-']) => Err(ParseExtensionError::InvalidNamespace),
             _ => Err(ParseExtensionError::MissingNamespace),
         }
     }
@@ -606,6 +1812,128 @@ impl<'c> Iterator for Extentions<'c> {
 
 impl FusedIterator for Extentions<'_> {}
 
+// Unicode locale extension keywords iterator
+#[derive(Clone, Debug)]
+pub struct UnicodeKeywords<'c>(&'c str);
+
+impl<'c> UnicodeKeywords<'c> {
+    #[inline]
+    fn new<'a: 'c>(subtags: &'a str) -> Self {
+        UnicodeKeywords(subtags)
+    }
+}
+
+impl<'c> Iterator for UnicodeKeywords<'c> {
+    type Item = (&'c str, &'c str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Skip any attributes preceding the first key.
+        let mut subtags = self.0.split('-');
+        let skipped = subtags
+            .by_ref()
+            .take_while(|s| s.len() != 2)
+            .map(|s| s.len() + 1)
+            .sum::<usize>();
+        let rest = self.0.get(skipped..).unwrap_or_default();
+        let key = rest.get(..2).filter(|k| k.len() == 2 && !k.contains('-'))?;
+        let value = rest.get(3..).unwrap_or_default();
+        let len = value
+            .split('-')
+            .take_while(|s| s.len() > 2)
+            .map(|s| s.len() + 1)
+            .sum::<usize>();
+        let value = &value[..len.saturating_sub(1)];
+        self.0 = rest.get(3 + len..).unwrap_or_default();
+        Some((key, value))
+    }
+}
+
+impl FusedIterator for UnicodeKeywords<'_> {}
+
+// A tfield key, a letter then a digit, eg. t0 or m0.
+fn is_transform_key(subtag: &str) -> bool {
+    let b = subtag.as_bytes();
+    b.len() == 2 && b[0].is_ascii_alphabetic() && b[1].is_ascii_digit()
+}
+
+// The subtags of a t extension, without the t: an optional source locale
+// followed by any transform fields, eg. ja-Jpan-m0-alaloc.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TExtension<'c>(&'c str);
+
+impl<'c> TExtension<'c> {
+    // Byte offset of the first field, or the length if there are none.
+    fn fields_start(&self) -> usize {
+        let mut offset = 0;
+        for subtag in self.0.split('-') {
+            if is_transform_key(subtag) {
+                return offset;
+            }
+            offset += subtag.len() + 1;
+        }
+        self.0.len()
+    }
+
+    // The source locale, from the start up to the first field.
+    fn source_str(&self) -> Option<&'c str> {
+        Some(self.0[..self.fields_start()].trim_end_matches('-')).filter(|s| !s.is_empty())
+    }
+
+    // The locale content was transformed from, eg. ja-Jpan for ja-jpan-m0-alaloc.
+    pub fn source_tag(&self) -> Option<Tag> {
+        self.source_str()?.parse().ok()
+    }
+
+    // The source locale's script subtag, found in place after its language
+    // and any extlangs, eg. jpan for ja-jpan-m0-alaloc.
+    fn source_script(&self) -> Option<&'c str> {
+        let is_alpha = |s: &str, len| s.len() == len && s.bytes().all(|b| b.is_ascii_alphabetic());
+        self.source_str()?
+            .split('-')
+            .skip(1)
+            .find(|&s| !is_alpha(s, 3))
+            .filter(|&s| is_alpha(s, 4))
+    }
+
+    // The fields as (key, value) pairs, eg. (t0, und-fonipa) then (m0, ungegn)
+    // for ja-t0-und-fonipa-m0-ungegn. A value runs to the next key, so may
+    // span several subtags.
+    pub fn transform_fields(&self) -> TransformFields<'c> {
+        TransformFields(&self.0[self.fields_start()..])
+    }
+}
+
+impl AsRef<str> for TExtension<'_> {
+    fn as_ref(&self) -> &str {
+        self.0
+    }
+}
+
+// Transform fields iterator
+#[derive(Clone, Debug)]
+pub struct TransformFields<'c>(&'c str);
+
+impl<'c> Iterator for TransformFields<'c> {
+    type Item = (&'c str, &'c str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let (key, rest) = self.0.split_once('-').unwrap_or((self.0, ""));
+        let len = rest
+            .split('-')
+            .take_while(|s| !is_transform_key(s))
+            .map(|s| s.len() + 1)
+            .sum::<usize>();
+        let value = &rest[..len.saturating_sub(1)];
+        self.0 = rest.get(len..).unwrap_or_default();
+        Some((key, value))
+    }
+}
+
+impl FusedIterator for TransformFields<'_> {}
+
 // impl<'c> DoubleEndedIterator for Extentions<'c> {
 //     #[inline]
 //     fn next_back(&mut self) -> Option<Self::Item> {
@@ -725,7 +2053,7 @@ mod tests {
             }
         );
         assert_eq!(
-            Tag::privateuse("x-priv"),
+            Tag::private_use_only("x-priv"),
             Tag {
                 buf: "x-priv".into(),
                 end: Offsets {
@@ -751,4 +2079,14 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "script ends after region")]
+    fn invariants() {
+        let mut tag: Tag = "en-Latn-US".parse().unwrap();
+        tag.set_region("GB");
+        tag.end.region = 2;
+        tag.set_private("x-test");
+    }
 }
@@ -1,6 +1,9 @@
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr, sync::Arc};
 
-use language_tag::{ExtensionRef, Tag};
+use language_tag::{
+    CaseFoldedTag, CaseFoldedTagSet, ComponentError, ExtensionRef, ParseExtensionError,
+    SemanticTag, Tag, TagAsObject, TagAsString,
+};
 
 #[test]
 fn builder() {
@@ -17,6 +20,36 @@ fn builder() {
     );
 }
 
+#[test]
+fn builder_replace_and_dedup() {
+    let builder = Tag::builder()
+        .lang("en")
+        .variants(["2abc", "1abc"])
+        .extensions(["a-bable", "q-babbel"]);
+    assert!(Tag::builder().replace_variant("1abc", "3abc").is_none());
+    assert!(Tag::builder()
+        .replace_extension("a-bable", "b-bable")
+        .is_none());
+    assert_eq!(
+        builder
+            .replace_variant("2abc", "0abc")
+            .and_then(|b| b.replace_extension("q-babbel", "b-babbel"))
+            .expect("replaced subtags")
+            .build(),
+        Tag::from_str("en-0abc-1abc-a-bable-b-babbel").unwrap()
+    );
+
+    let tag = Tag::builder()
+        .lang("en")
+        .variants(["2abc", "1abc", "2abc"])
+        .extensions(["q-babbel", "a-bable", "q-babbel"])
+        .dedup_variants()
+        .dedup_extensions()
+        .build();
+    assert_eq!(tag.variants().collect::<Vec<_>>(), ["1abc", "2abc"]);
+    assert_eq!(tag, Tag::from_str("en-1abc-2abc-a-bable-q-babbel").unwrap());
+}
+
 #[test]
 fn parser() {
     use crate::Tag;
@@ -73,7 +106,7 @@ fn parser() {
         ),
         (
             "x-priv1-priv2-xpriv3",
-            Ok(Tag::privateuse("x-priv1-priv2-xpriv3")),
+            Ok(Tag::private_use_only("x-priv1-priv2-xpriv3")),
         ),
         (
             "en-gan-yue-Latn",
@@ -99,8 +132,7 @@ fn from_str() {
 
     assert_eq!(
         Tag::from_str("en-Latn-USA")
-            .err()
-            .expect("Err value not found")
+            .expect_err("Err value not found")
             .to_string(),
         "error Tag at: en-Latn-USA"
     );
@@ -122,6 +154,27 @@ fn display() {
     );
 }
 
+#[test]
+fn display_case() {
+    let tag = Tag::from_str("EN-latn-US").unwrap();
+    assert_eq!(tag.display_lowercase().to_string(), "en-latn-us");
+    assert_eq!(tag.display_uppercase().to_string(), "EN-LATN-US");
+    assert_eq!(tag.display_canonical().to_string(), "en-Latn-US");
+    assert_eq!(tag.to_string(), "EN-latn-US");
+
+    let tag = Tag::from_str("EN-GAN-latn-us-1ABC-A-BCDEF-x-PRIV").unwrap();
+    assert_eq!(
+        tag.display_canonical().to_string(),
+        "en-gan-Latn-US-1abc-a-bcdef-x-priv"
+    );
+    assert_eq!(
+        Tag::private_use_only("X-Priv")
+            .display_canonical()
+            .to_string(),
+        "x-priv"
+    );
+}
+
 #[test]
 fn sorting() {
     let aa = Tag::with_lang("aa");
@@ -137,6 +190,69 @@ fn sorting() {
     test.sort();
 
     assert_eq!(test, standard);
+
+    let standard = [
+        "en-Latn-US",
+        "en-Latn-US-1abc",
+        "en-Latn-US-1abc-x-priv",
+        "en-Latn-US-1abc-2def",
+        "en-Latn-US-2def",
+        "en-Latn-US-2def-a-abcdef",
+        "en-Latn-US-2def-a-abcdef-b-abcdef",
+        "en-Latn-US-2def-b-abcdef",
+        "en-Latn-US-2def-b-abcdef-x-priv",
+        "en-Latn-US-2def-b-abcdef-x-priv2",
+    ]
+    .map(|t| Tag::from_str(t).unwrap());
+    let mut test = standard.clone();
+    test.reverse();
+    test.sort();
+    assert_eq!(test, standard);
+
+    use std::cmp::Ordering;
+    let a = Tag::from_str("en-Latn-US-1abc").unwrap();
+    let b = Tag::from_str("en-Latn-US-2def").unwrap();
+    assert_eq!(a.cmp(&b), Ordering::Less);
+    assert_eq!(
+        a.cmp(&Tag::from_str("EN-latn-us-1ABC").unwrap()),
+        Ordering::Equal
+    );
+    assert_eq!(
+        Tag::private_use_only("x-priv1").cmp(&Tag::private_use_only("x-priv2")),
+        Ordering::Less
+    );
+    assert_eq!(Tag::private_use_only("x-priv1").cmp(&a), Ordering::Less);
+}
+
+#[test]
+fn ordering_consistent_with_eq() {
+    use std::{cmp::Ordering, collections::BTreeSet};
+
+    let tags = [
+        "en",
+        "EN",
+        "en-Latn",
+        "en-US",
+        "en-Latn-US-1abc",
+        "en-latn-us-1ABC",
+        "en-a-bcd-efg",
+        "en-a-bcd-a-efg",
+        "en-A-BCD-EFG",
+        "en-x-priv",
+        "x-priv",
+        "x-PRIV",
+    ]
+    .map(|t| Tag::from_str(t).unwrap());
+    for a in &tags {
+        for b in &tags {
+            assert_eq!(a.cmp(b) == Ordering::Equal, a == b, "{a} cmp {b}");
+            assert_eq!(a.cmp(b), b.cmp(a).reverse(), "{a} cmp {b}");
+        }
+    }
+    let set: BTreeSet<_> = tags.iter().collect();
+    assert!(set.contains(&Tag::from_str("en-a-bcd-efg").unwrap()));
+    assert!(set.contains(&Tag::from_str("en-a-bcd-a-efg").unwrap()));
+    assert_eq!(set.len(), 8);
 }
 
 #[test]
@@ -153,6 +269,10 @@ fn getters() {
         ["a-abcdef", "b-ghijklmn", "c-tester"]
     );
 
+    let tag = Tag::from_str("x-priv1-priv2").unwrap();
+    assert_eq!(tag.lang(), "");
+    assert_eq!(tag.private(), Some("x-priv1-priv2"));
+
     let tag = Tag::default();
     assert_eq!(tag.lang(), "");
     assert_eq!(tag.script(), None);
@@ -165,6 +285,488 @@ fn getters() {
     );
 }
 
+#[test]
+fn counts() {
+    let tag = Tag::with_lang("en");
+    assert_eq!(tag.count_variants(), 0);
+    assert_eq!(tag.count_extensions(), 0);
+    assert_eq!(tag.count_private_subtags(), 0);
+
+    let tag = Tag::from_str("en-Latn-US-1abc-a-abcdef-x-priv").unwrap();
+    assert_eq!(tag.count_variants(), 1);
+    assert_eq!(tag.count_extensions(), 1);
+    assert_eq!(tag.count_private_subtags(), 1);
+
+    let tag =
+        Tag::from_str("en-Latn-US-1abc-2def-3ghi-a-abcdef-ghijkl-c-tester-x-priv1-priv2").unwrap();
+    assert_eq!(tag.count_variants(), 3);
+    assert_eq!(tag.count_extensions(), 3);
+    assert_eq!(tag.count_extensions(), tag.extensions().count());
+    assert_eq!(tag.count_private_subtags(), 2);
+
+    let tag = Tag::private_use_only("x-priv1-priv2-priv3");
+    assert_eq!(tag.extension_count(), 0);
+    assert_eq!(tag.count_variants(), 0);
+    assert_eq!(tag.count_extensions(), 0);
+    assert_eq!(tag.count_private_subtags(), 3);
+}
+
+#[test]
+fn namespace_counts() {
+    let tag = Tag::from_str("en-Latn-US-1abc-x-priv").unwrap();
+    assert_eq!(tag.extension_count(), 0);
+    assert_eq!(tag.namespace_count(), 0);
+    assert_eq!(tag.variant_count(), 1);
+
+    let tag = Tag::from_str("en-a-abcdef-ghijkl").unwrap();
+    assert_eq!(tag.extension_count(), 1);
+    assert_eq!(tag.namespace_count(), 1);
+    assert_eq!(tag.count_extensions(), 2);
+
+    let mut tag =
+        Tag::from_str("en-Latn-US-1abc-2def-a-abcdef-b-ghijklmn-opqrstuv-c-tester-x-priv").unwrap();
+    assert_eq!(tag.extension_count(), 3);
+    assert_eq!(tag.namespace_count(), 3);
+    assert_eq!(tag.variant_count(), 2);
+    tag.add_extension("b-abcdef");
+    assert_eq!(tag.extension_count(), 3);
+}
+
+#[test]
+fn parse_lenient() {
+    let lenient = |s| Tag::parse_lenient(s).map(|t| t.to_string());
+    for (retired, current) in [
+        ("iw", "he"),
+        ("ji", "yi"),
+        ("in", "id"),
+        ("jw", "jv"),
+        ("mo", "ro"),
+        ("drh", "khk"),
+        ("drw", "prs"),
+        ("gav", "dev"),
+        ("ktr", "dtp"),
+        ("kzj", "dtp"),
+        ("mst", "mry"),
+        ("tie", "ras"),
+        ("tnf", "prs"),
+        ("ybd", "rki"),
+        ("yma", "lrr"),
+    ] {
+        assert_eq!(lenient(retired).as_deref(), Ok(current), "{retired}");
+    }
+    assert_eq!(lenient("en_US").as_deref(), Ok("en-US"));
+    assert_eq!(lenient("en-latn").as_deref(), Ok("en-Latn"));
+    assert_eq!(lenient("EN_latn_us").as_deref(), Ok("en-Latn-US"));
+    assert_eq!(lenient("IW_il").as_deref(), Ok("he-IL"));
+    assert_eq!(lenient("ind").as_deref(), Ok("ind"));
+    assert_eq!(
+        lenient("sr_Latn_RS_x_Priv").as_deref(),
+        Ok("sr-Latn-RS-x-priv")
+    );
+    assert!(Tag::parse_lenient("en__US").is_err());
+}
+
+#[test]
+fn component_strs() {
+    let tag = Tag::from_str("en-Latn-US-1abc-2def-a-abcdef-b-ghijklmn-x-priv-ate").unwrap();
+    assert_eq!(tag.variants_str(), "1abc-2def");
+    assert_eq!(tag.extensions_str(), "a-abcdef-b-ghijklmn");
+    assert_eq!(tag.private_str(), "x-priv-ate");
+    for part in [tag.variants_str(), tag.extensions_str(), tag.private_str()] {
+        let whole: &str = tag.as_ref();
+        assert!(whole.as_bytes().as_ptr_range().contains(&part.as_ptr()));
+        assert!(whole.contains(part));
+    }
+
+    let tag = Tag::from_str("en-US").unwrap();
+    assert_eq!(tag.variants_str(), "");
+    assert_eq!(tag.extensions_str(), "");
+    assert_eq!(tag.private_str(), "");
+    assert_eq!(Tag::private_use_only("x-priv").private_str(), "x-priv");
+}
+
+#[test]
+fn split_at_components() {
+    let cases = [
+        ("en", ("en", ""), ("en", "")),
+        ("en-Latn", ("en", "Latn"), ("en-Latn", "")),
+        ("en-US", ("en", "US"), ("en", "US")),
+        ("en-Latn-US", ("en", "Latn-US"), ("en-Latn", "US")),
+        ("en-1abc", ("en", "1abc"), ("en", "1abc")),
+        ("en-Latn-1abc", ("en", "Latn-1abc"), ("en-Latn", "1abc")),
+        ("en-US-x-priv", ("en", "US-x-priv"), ("en", "US-x-priv")),
+        (
+            "zh-yue-Hant-HK-a-abcdef",
+            ("zh-yue", "Hant-HK-a-abcdef"),
+            ("zh-yue-Hant", "HK-a-abcdef"),
+        ),
+    ];
+    for (tag, at_script, at_region) in cases {
+        let tag = Tag::from_str(tag).unwrap();
+        assert_eq!(tag.split_at_script(), at_script, "{tag}");
+        assert_eq!(tag.split_at_region(), at_region, "{tag}");
+    }
+
+    let private = Tag::private_use_only("x-priv");
+    assert_eq!(private.split_at_script(), ("", "x-priv"));
+    assert_eq!(private.split_at_region(), ("", "x-priv"));
+}
+
+#[test]
+fn semantic_equality() {
+    let parsed = Tag::from_str("en-Latn-US-1abc-2def-a-abcdef-b-ghijklmn").unwrap();
+    let mut built = Tag::from_str("EN-latn-us").unwrap();
+    built.push_variant("2DEF");
+    built.push_variant("1abc");
+    built.add_extension("b-ghijklmn");
+    built.add_extension("a-abcdef");
+
+    assert_ne!(parsed, built);
+    assert!(parsed.equals_canonical(&built));
+    assert!(built.equals_canonical(&parsed));
+    assert_eq!(parsed.semantic_hash(), built.semantic_hash());
+    assert!(!parsed.equals_canonical(&Tag::from_str("en-Latn-US-1abc").unwrap()));
+    assert_ne!(
+        parsed.semantic_hash(),
+        Tag::from_str("en-Latn-GB").unwrap().semantic_hash()
+    );
+
+    let mut map = HashMap::new();
+    map.insert(SemanticTag::from(parsed), 1);
+    assert_eq!(map.get(&SemanticTag(built)), Some(&1));
+    assert_eq!(map.get(&SemanticTag(Tag::from_str("en-US").unwrap())), None);
+}
+
+#[test]
+fn into_builder() {
+    fn parse(s: &str) -> Tag {
+        s.parse().unwrap()
+    }
+
+    assert_eq!(
+        "en-Latn-US"
+            .parse::<Tag>()
+            .unwrap()
+            .into_builder()
+            .region("GB")
+            .build(),
+        parse("en-Latn-GB")
+    );
+    let builder = parse("en-Latn-US-1abc-a-abcdef-x-priv").into_builder();
+    assert_eq!(
+        builder.clone().build(),
+        parse("en-Latn-US-1abc-a-abcdef-x-priv")
+    );
+    let region = String::from("GB");
+    let tag = builder
+        .region(&region)
+        .variant("2def")
+        .extension("b-ghijklmn")
+        .private("")
+        .build();
+    drop(region);
+    assert_eq!(tag, parse("en-Latn-GB-1abc-2def-a-abcdef-b-ghijklmn"));
+    assert_eq!(
+        Tag::builder()
+            .lang("sr")
+            .into_owned()
+            .script("Cyrl")
+            .build(),
+        parse("sr-Cyrl")
+    );
+}
+
+#[test]
+fn case_folded() {
+    let mut set = CaseFoldedTagSet::new();
+    assert!(set.insert(Tag::from_str("en-Latn-US").unwrap().into()));
+    assert!(!set.insert(Tag::from_str("EN-latn-us").unwrap().into()));
+    assert!(set.contains("en-latn-us"));
+    assert!(!set.contains("en-Latn-US"));
+
+    let mut map = HashMap::new();
+    map.insert(CaseFoldedTag::from(Tag::from_str("sr-Cyrl").unwrap()), 1);
+    assert_eq!(map.get("sr-cyrl"), Some(&1));
+    let tag = map.into_keys().next().unwrap();
+    assert_eq!(tag.display_canonical().to_string(), "sr-Cyrl");
+    assert_eq!(tag.into_inner(), Tag::from_str("sr-Cyrl").unwrap());
+}
+
+#[test]
+fn try_from_components() {
+    assert_eq!(
+        Tag::try_from_components(
+            "en",
+            Some("Latn"),
+            Some("US"),
+            &["2def", "1abc"],
+            &["a-abcdef"],
+            Some("x-priv")
+        ),
+        Ok(Tag::from_str("en-Latn-US-1abc-2def-a-abcdef-x-priv").unwrap())
+    );
+    assert_eq!(
+        Tag::try_from_components("sr", None, Some("419"), &[], &[], None),
+        Ok(Tag::from_str("sr-419").unwrap())
+    );
+
+    let component = |lang, script, region, variant, extension, private| {
+        Tag::try_from_components(lang, script, region, variant, extension, private)
+    };
+    assert_eq!(
+        component("english", None, None, &[], &[], None),
+        Err(ComponentError::InvalidLang)
+    );
+    assert_eq!(
+        component("en", Some("Lat"), Some("USA"), &[], &[], None),
+        Err(ComponentError::InvalidScript("Lat".into()))
+    );
+    assert_eq!(
+        component("en", None, Some("USA"), &[], &[], None),
+        Err(ComponentError::InvalidRegion("USA".into()))
+    );
+    assert_eq!(
+        component("en", None, None, &["1abc", "abc"], &[], None),
+        Err(ComponentError::InvalidVariant("abc".into()))
+    );
+    assert_eq!(
+        component("en", None, None, &[], &["x-abcdef"], None),
+        Err(ComponentError::InvalidExtension("x-abcdef".into()))
+    );
+    assert_eq!(
+        component("en", None, None, &[], &[], Some("priv")),
+        Err(ComponentError::InvalidPrivate("priv".into()))
+    );
+}
+
+#[test]
+fn extlangs() {
+    let tag = Tag::from_str("en-gan-yue-Latn").unwrap();
+    assert_eq!(tag.lang(), "en-gan-yue");
+    assert_eq!(tag.primary_lang(), "en");
+    assert_eq!(tag.extlangs().collect::<Vec<_>>(), ["gan", "yue"]);
+    assert!(tag.has_extlang());
+    assert_eq!(tag.script(), Some("Latn"));
+
+    let tag = Tag::from_str("en-Latn-US").unwrap();
+    assert_eq!(tag.primary_lang(), "en");
+    assert_eq!(tag.extlangs().count(), 0);
+    assert!(!tag.has_extlang());
+
+    let tag = Tag::private_use_only("x-priv");
+    assert_eq!(tag.primary_lang(), "");
+    assert_eq!(tag.extlangs().count(), 0);
+}
+
+#[test]
+fn extension_ref() {
+    let ext = ExtensionRef::from_parts('u', "islamic").unwrap();
+    assert_eq!((ext.namespace(), ext.name()), ('u', "islamic"));
+    assert_eq!(ext.to_string(), "u-islamic");
+    assert_eq!(ExtensionRef::try_from("u-islamic"), Ok(ext));
+    let tag = Tag::from_str("en-a-abcdef").unwrap();
+    assert_eq!(
+        tag.extensions().next(),
+        ExtensionRef::from_parts('a', "abcdef").ok()
+    );
+
+    for (namespace, name, err) in [
+        ('x', "abcdef", ParseExtensionError::InvalidNamespace),
+        ('-', "abcdef", ParseExtensionError::InvalidNamespace),
+        ('é', "abcdef", ParseExtensionError::InvalidNamespace),
+        ('a', "b", ParseExtensionError::NameToLong),
+        ('a', "abcdefghi", ParseExtensionError::NameToLong),
+        ('a', "ab_d", ParseExtensionError::InvalidName),
+    ] {
+        assert_eq!(ExtensionRef::from_parts(namespace, name), Err(err));
+    }
+    assert_eq!(
+        ExtensionRef::try_from("a"),
+        Err(ParseExtensionError::MissingNamespace)
+    );
+    assert_eq!(
+        ExtensionRef::try_from("ab-cdef"),
+        Err(ParseExtensionError::MissingNamespace)
+    );
+}
+
+#[test]
+fn push_variant_sorted() {
+    let mut tag = Tag::from_str("en-Latn-2def-a-abcdef").unwrap();
+    tag.push_variant_sorted("1abc");
+    assert_eq!(tag.to_string(), "en-Latn-1abc-2def-a-abcdef");
+    tag.push_variant_sorted("4jkl");
+    assert_eq!(tag.to_string(), "en-Latn-1abc-2def-4jkl-a-abcdef");
+    tag.push_variant_sorted("3ghi");
+    assert_eq!(tag.to_string(), "en-Latn-1abc-2def-3ghi-4jkl-a-abcdef");
+    assert_eq!(tag.pop_variant().as_deref(), Some("4jkl"));
+
+    let mut tag = Tag::with_lang("en");
+    tag.push_variant_sorted("2def");
+    tag.push_variant_sorted("1abc");
+    assert_eq!(
+        tag,
+        Tag::builder().lang("en").variants(["2def", "1abc"]).build()
+    );
+}
+
+#[test]
+fn component_count() {
+    for (tag, count) in [
+        ("en", 1),
+        ("en-US", 2),
+        ("en-Latn-US", 3),
+        ("zh-yue-Hant-HK", 3),
+        ("en-Latn-US-1abc-2def", 5),
+        ("en-a-abcdef-ghijkl-b-ghijklmn", 4),
+        ("en-Latn-US-1abc-2def-a-abcdef-b-ghijklmn-x-priv", 8),
+        ("x-priv1-priv2", 2),
+    ] {
+        let tag = Tag::from_str(tag).unwrap();
+        assert_eq!(tag.component_count(), count, "{tag}");
+        assert_eq!(tag.depth() as usize, count, "{tag}");
+    }
+}
+
+#[test]
+fn private_use() {
+    let tag = Tag::from_str("x-priv1-priv2").unwrap();
+    assert!(tag.is_private_use());
+    assert_eq!(tag, Tag::private_use_only("x-priv1-priv2"));
+    assert_eq!(tag.into_private_use(), Some("x-priv1-priv2".into()));
+
+    let tag = Tag::from_str("en-x-priv").unwrap();
+    assert!(!tag.is_private_use());
+    assert_eq!(tag.into_private_use(), None);
+}
+
+#[test]
+fn unicode_extension() {
+    let tag = Tag::from_str("en-u-ca-islamic-co-trad").unwrap();
+    assert_eq!(
+        tag.unicode_extension_pairs().collect::<Vec<_>>(),
+        [("ca", "islamic"), ("co", "trad")]
+    );
+    assert_eq!(tag.unicode_extension_value("co"), Some("trad"));
+    assert_eq!(tag.unicode_extension_value("nu"), None);
+    assert_eq!(tag.unicode_extension_value("CO"), None);
+    assert_eq!(tag.unicode_extension_value("c"), None);
+
+    let tag = Tag::from_str("ar-a-bable-u-attr-ca-islamic-umalqura-kn-nu-arab-x-priv").unwrap();
+    assert_eq!(
+        tag.unicode_extension_pairs().collect::<Vec<_>>(),
+        [("ca", "islamic-umalqura"), ("kn", ""), ("nu", "arab")]
+    );
+    assert_eq!(tag.unicode_extension_value("ca"), Some("islamic-umalqura"));
+
+    let tag = Tag::from_str("en-a-bable-b-babbel").unwrap();
+    assert_eq!(tag.unicode_extension_pairs().count(), 0);
+    assert_eq!(Tag::with_lang("en").unicode_extension_pairs().count(), 0);
+}
+
+#[test]
+fn validating_setters() {
+    let mut tag = Tag::with_lang("en");
+    assert!(tag.try_set_region("USA").is_err());
+    assert_eq!(tag, Tag::with_lang("en"));
+    tag.try_set_region("US").expect("valid region");
+    assert_eq!(tag.region(), Some("US"));
+    tag.try_set_region("001").expect("valid region");
+    assert_eq!(tag.region(), Some("001"));
+    assert!(tag.try_set_region("01").is_err());
+    assert!(tag.try_set_region("").is_err());
+
+    assert!(tag.try_set_script("Lat").is_err());
+    assert!(tag.try_set_script("Lat1").is_err());
+    tag.try_set_script("Latn").expect("valid script");
+
+    assert!(tag.try_set_lang("e").is_err());
+    assert!(tag.try_set_lang("en-g").is_err());
+    tag.try_set_lang("en-gan").expect("valid lang with extlang");
+
+    assert!(tag.try_push_variant("abc").is_err());
+    assert!(tag.try_push_variant("abcdefghi").is_err());
+    tag.try_push_variant("1abc").expect("valid variant");
+
+    assert!(tag.try_add_extension("x-abcdef").is_err());
+    assert!(tag.try_add_extension("a-b").is_err());
+    tag.try_add_extension("a-bable").expect("valid extension");
+
+    assert_eq!(tag, Tag::from_str("en-gan-Latn-001-1abc-a-bable").unwrap());
+    let err = tag.try_set_region("USA").unwrap_err();
+    assert_eq!(err.input, "A");
+}
+
+#[test]
+fn without() {
+    const SRC: &str = "en-Latn-US-1abc-a-bable-x-priv";
+    let tag = Tag::from_str(SRC).unwrap();
+    assert_eq!(
+        tag.without_private(),
+        Tag::from_str("en-Latn-US-1abc-a-bable").unwrap()
+    );
+    assert_eq!(
+        tag.without_extensions(),
+        Tag::from_str("en-Latn-US-1abc-x-priv").unwrap()
+    );
+    assert_eq!(
+        tag.without_variants(),
+        Tag::from_str("en-Latn-US-a-bable-x-priv").unwrap()
+    );
+    assert_eq!(
+        tag.without_region(),
+        Tag::from_str("en-Latn-1abc-a-bable-x-priv").unwrap()
+    );
+    assert_eq!(
+        tag.without_script(),
+        Tag::from_str("en-US-1abc-a-bable-x-priv").unwrap()
+    );
+    assert_eq!(
+        tag.without_private()
+            .without_extensions()
+            .without_variants()
+            .without_region()
+            .without_script(),
+        Tag::with_lang("en")
+    );
+    assert_eq!(tag.to_string(), SRC);
+}
+
+#[test]
+fn merge() {
+    let en = Tag::with_lang("en");
+    let de = Tag::from_str("de-Latn-US").unwrap();
+    let expected = Tag::from_str("en-Latn-US").unwrap();
+
+    let mut tag = en.clone();
+    tag.merge_with(&de);
+    assert_eq!(tag, expected);
+    assert_eq!(en.clone() + &de, expected);
+    assert_eq!(en.clone() + de.clone(), expected);
+    assert_eq!(en.clone() | &de, expected);
+    let mut tag = en.clone();
+    tag += &de;
+    assert_eq!(tag, expected);
+
+    let full = Tag::from_str("de-Cyrl-1abc-a-bable-x-priv").unwrap();
+    assert_eq!(
+        Tag::from_str("en-GB-2abc").unwrap() + &full,
+        Tag::from_str("en-Cyrl-GB-2abc-a-bable-x-priv").unwrap()
+    );
+    assert_eq!(expected.clone() + &Tag::default(), expected);
+
+    // A private use only tag has nothing to merge into.
+    let private = Tag::from_str("x-foo").unwrap();
+    assert_eq!(private.clone() + &de, private);
+    assert_eq!(private.clone() | &full, private);
+    let mut tag = private.clone();
+    tag += &de;
+    assert_eq!(tag, private);
+    assert_eq!(tag.to_string(), "x-foo");
+    assert_eq!(tag.try_merge_with(&full), Ok(()));
+    assert_eq!(tag, private);
+}
+
 #[test]
 fn setters() {
     // Test each in isolation
@@ -276,3 +878,419 @@ fn setters() {
     tag.set_script("");
     assert_eq!(tag, Tag::with_lang("en"));
 }
+
+#[test]
+fn length_limit() {
+    use nom::error::ErrorKind;
+
+    // 28 variants of 8 letters make a 251 byte variants component, moving
+    // the later offsets by more than an i8 can hold.
+    let names: Vec<String> = (0..28).map(|i| format!("v{i:0>7}")).collect();
+    let variants: Vec<&str> = names.iter().map(String::as_str).collect();
+    let mut tag: Tag = "en-x-priv".parse().unwrap();
+    assert_eq!(
+        tag.try_set_variants(&variants).map_err(|e| e.code),
+        Err(ErrorKind::TooLarge)
+    );
+    assert_eq!(tag.as_ref(), "en-x-priv");
+
+    tag.clear_private();
+    tag.try_set_variants(&variants).unwrap();
+    assert_eq!(tag.as_ref().len(), Tag::MAX_LEN - 1);
+    assert_eq!(tag.variants().count(), 28);
+    assert!(tag.try_push_variant("fonipa").is_err());
+    assert!(tag.try_push_variant_sorted("fonipa").is_err());
+    assert!(tag.try_add_extension("a-bable").is_err());
+    assert!(tag.try_set_extensions(["a-bable"]).is_err());
+    assert!(tag.try_set_private("x-a").is_err());
+    assert!(tag.try_set_region("419").is_err());
+    assert!(tag.try_merge_with(&"und-Latn".parse().unwrap()).is_err());
+    assert_eq!(tag.as_ref().len(), Tag::MAX_LEN - 1);
+
+    tag.try_set_variants(&variants[1..]).unwrap();
+    tag.try_set_private("x-priv").unwrap();
+    assert_eq!(tag.variants().next(), Some("v0000001"));
+    assert_eq!(tag.private(), Some("x-priv"));
+
+    assert!(Tag::builder()
+        .lang("en")
+        .variants(&variants)
+        .try_build()
+        .is_ok());
+    assert!(Tag::builder()
+        .lang("en-aaa")
+        .variants(&variants)
+        .try_build()
+        .is_err());
+    assert_eq!(
+        Tag::try_from_components("en-aaa", None, None, &variants, &[], None),
+        Err(ComponentError::TooLong)
+    );
+}
+
+#[test]
+#[should_panic(expected = "longer than Tag::MAX_LEN")]
+fn length_limit_unchecked_setter() {
+    let names: Vec<String> = (0..28).map(|i| format!("v{i:0>7}")).collect();
+    let variants: Vec<&str> = names.iter().map(String::as_str).collect();
+    let mut tag = Tag::builder().lang("en").variants(variants).build();
+    tag.set_script("Latn");
+}
+
+#[test]
+#[should_panic(expected = "longer than Tag::MAX_LEN")]
+fn length_limit_unchecked_builder() {
+    let names: Vec<String> = (0..29).map(|i| format!("v{i:0>7}")).collect();
+    let variants: Vec<&str> = names.iter().map(String::as_str).collect();
+    Tag::builder().lang("en").variants(variants).build();
+}
+
+#[test]
+fn try_setters_validate() {
+    let mut tag = Tag::with_lang("en");
+    assert!(tag.try_set_variants(["fonipa", "abc"]).is_err());
+    assert!(tag.try_set_extensions(["a-b"]).is_err());
+    assert!(tag.try_set_private("priv").is_err());
+    assert_eq!(tag, Tag::with_lang("en"));
+
+    tag.try_set_variants(["fonipa"]).unwrap();
+    tag.try_set_extensions(["a-bable"]).unwrap();
+    tag.try_set_private("x-priv").unwrap();
+    assert_eq!(tag.as_ref(), "en-fonipa-a-bable-x-priv");
+    tag.try_set_private("").unwrap();
+    assert_eq!(tag.private(), None);
+}
+
+#[test]
+fn string_conversions() {
+    let tag = "en-US".parse::<Tag>().unwrap();
+    assert!(tag == "en-us");
+    assert!("EN-us" == tag);
+    let owned = String::from("en-us");
+    assert!(tag == owned);
+    assert!(owned == tag);
+    assert!(tag != "en");
+    assert!(tag == *"en-US");
+
+    assert_eq!(String::from(tag.clone()), "en-US");
+    assert_eq!(&*Arc::<str>::from(tag.clone()), "en-US");
+    assert_eq!(Tag::try_from(String::from("en-US")), Ok(tag.clone()));
+    assert_eq!(Tag::try_from(Arc::<str>::from("en-US")), Ok(tag));
+    assert!(Tag::try_from(String::from("en--US")).is_err());
+}
+
+#[test]
+fn matches_range() {
+    let matches = |range, tag: &str| Tag::from_str(tag).expect(tag).matches_range(range);
+
+    assert!(matches("en-*", "en-Latn-US"));
+    assert!(matches("*", "any-tag"));
+    assert!(matches("*", "x-priv"));
+    assert!(!matches("en-Latn", "de-Latn"));
+    // Plain ranges match on subtag boundaries.
+    assert!(matches("en", "en"));
+    assert!(matches("EN-us", "en-US-fonipa"));
+    assert!(!matches("en-US", "en-USA"));
+    assert!(!matches("en-US", "en"));
+    // Wildcards and skipped subtags.
+    assert!(matches("en-*-US", "en-Latn-US"));
+    assert!(matches("en-*-US", "en-US"));
+    assert!(matches("en-US", "en-Latn-US"));
+    assert!(matches("*-US", "de-Latn-US"));
+    assert!(matches("de-*-DE", "de-Latn-DE-1996"));
+    assert!(!matches("en-US", "en-a-bbbbbb-US"));
+    assert!(!matches("en-US-Latn", "en-Latn-US"));
+    assert!(!matches("", "en"));
+}
+
+#[test]
+fn language_range_lookup() {
+    let tags = |ts: &[&str]| -> Vec<Tag> { ts.iter().map(|t| t.parse().unwrap()).collect() };
+    let lookup = |range: &str, candidates: &[Tag]| {
+        Tag::from_str(range)
+            .unwrap()
+            .language_range_lookup(candidates)
+            .map(Tag::to_string)
+    };
+
+    let available = tags(&["en", "en-GB", "zh-Hant", "de-CH-1996", "sr-Latn", "x-priv"]);
+    assert_eq!(lookup("en-gb", &available).as_deref(), Some("en-GB"));
+    assert_eq!(lookup("en-US", &available).as_deref(), Some("en"));
+    assert_eq!(
+        lookup("zh-Hant-CN-x-private1", &available).as_deref(),
+        Some("zh-Hant")
+    );
+    assert_eq!(
+        lookup("de-CH-1996-u-co-phonebk", &available).as_deref(),
+        Some("de-CH-1996")
+    );
+    assert_eq!(
+        lookup("sr-Latn-RS-a-xyz", &available).as_deref(),
+        Some("sr-Latn")
+    );
+    assert_eq!(lookup("x-priv", &available).as_deref(), Some("x-priv"));
+    assert_eq!(lookup("zh-CN", &available), None);
+    assert_eq!(lookup("fr", &available), None);
+    assert_eq!(lookup("en", &[]), None);
+
+    // The first matching candidate wins.
+    let available = tags(&["en-gb", "en-GB"]);
+    let found = Tag::from_str("en-GB-oed")
+        .unwrap()
+        .language_range_lookup(&available);
+    assert!(found.is_some_and(|t| std::ptr::eq(t, &available[0])));
+}
+
+#[test]
+fn truncation() {
+    let tag = Tag::from_str("en-Latn-US-fonipa-a-abcdef-x-priv").unwrap();
+    let truncated = |truncate: fn(&mut Tag)| {
+        let mut tag = tag.clone();
+        truncate(&mut tag);
+        tag
+    };
+    assert_eq!(truncated(Tag::truncate_to_region), "en-Latn-US");
+    assert_eq!(truncated(Tag::truncate_to_script), "en-Latn");
+    assert_eq!(truncated(Tag::truncate_to_lang), "en");
+    assert_eq!(truncated(Tag::truncate_to_lang).script(), None);
+
+    // A private use only tag has no language to truncate to, and stays valid.
+    let private = Tag::from_str("x-priv").unwrap();
+    let truncations: [fn(&mut Tag); 3] = [
+        Tag::truncate_to_region,
+        Tag::truncate_to_script,
+        Tag::truncate_to_lang,
+    ];
+    for truncate in truncations {
+        let mut tag = private.clone();
+        truncate(&mut tag);
+        assert_eq!(tag, private);
+        assert_eq!(Tag::from_str(tag.as_ref()), Ok(private.clone()));
+    }
+
+    let forms = |tag: &str| {
+        Tag::from_str(tag)
+            .unwrap()
+            .truncated_forms()
+            .map(|t| t.to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        forms("en-Latn-US-fonipa-a-abcdef-x-priv"),
+        [
+            "en-Latn-US-fonipa-a-abcdef-x-priv",
+            "en-Latn-US",
+            "en-Latn",
+            "en"
+        ]
+    );
+    assert_eq!(forms("en-US"), ["en-US", "en"]);
+    assert_eq!(forms("zh-yue-Hant"), ["zh-yue-Hant", "zh-yue"]);
+    assert_eq!(forms("en"), ["en"]);
+    assert_eq!(forms("x-priv"), ["x-priv"]);
+
+    for form in tag.truncated_forms() {
+        assert_eq!(Tag::from_str(form.as_ref()), Ok(form.clone()));
+        assert_eq!(
+            Tag::from_str(form.as_ref()).unwrap().region(),
+            form.region()
+        );
+    }
+}
+
+#[test]
+fn normalize_extlang() {
+    let normalized = |tag: &str| {
+        let mut tag = Tag::from_str(tag).unwrap();
+        tag.normalize_extlang();
+        assert!(!tag.has_extlang());
+        tag.to_string()
+    };
+    assert_eq!(normalized("zh-gan"), "gan");
+    assert_eq!(normalized("zh-yue-Hant-HK"), "yue-Hant-HK");
+    assert_eq!(normalized("sgn-ase-US-x-priv"), "ase-US-x-priv");
+    assert_eq!(normalized("en-Latn-US"), "en-Latn-US");
+    assert_eq!(normalized("x-priv"), "x-priv");
+
+    let mut tag = Tag::from_str("zh-cmn-Hans-CN").unwrap();
+    tag.normalize_extlang();
+    assert_eq!(tag.lang(), "cmn");
+    assert_eq!(tag.script(), Some("Hans"));
+    assert_eq!(tag.region(), Some("CN"));
+}
+
+#[test]
+fn transformed_content() {
+    let tag = Tag::from_str("en-Latn-t-ja-Jpan").unwrap();
+    assert_eq!(tag.source_tag(), Some(Tag::from_str("ja-Jpan").unwrap()));
+    assert_eq!(tag.all_scripts().collect::<Vec<_>>(), ["Latn", "Jpan"]);
+
+    let tag = Tag::from_str("ja-t-en-us-m0-alaloc-u-ca-japanese").unwrap();
+    assert_eq!(tag.source_tag(), Some(Tag::from_str("en-US").unwrap()));
+    assert_eq!(tag.all_scripts().count(), 0);
+    assert_eq!(tag.unicode_extension_value("ca"), Some("japanese"));
+
+    let tag = Tag::from_str("und-Cyrl-t-m0-ungegn").unwrap();
+    assert_eq!(tag.source_tag(), None);
+    assert_eq!(tag.all_scripts().collect::<Vec<_>>(), ["Cyrl"]);
+
+    let tag = Tag::from_str("en-Latn-US").unwrap();
+    assert_eq!(tag.source_tag(), None);
+    assert_eq!(tag.all_scripts().collect::<Vec<_>>(), ["Latn"]);
+
+    let tag = Tag::from_str("en-t-zh-yue-Hant").unwrap();
+    assert_eq!(tag.all_scripts().collect::<Vec<_>>(), ["Hant"]);
+
+    // Scripts come as written, like script(), rather than case folded.
+    let tag = Tag::from_str("en-latn-t-ja-jpan").unwrap();
+    assert_eq!(tag.all_scripts().collect::<Vec<_>>(), ["latn", "jpan"]);
+
+    let tag = Tag::from_str("en-t-ja-jp").unwrap();
+    assert_eq!(tag.all_scripts().count(), 0);
+}
+
+#[test]
+fn t_extension() {
+    let t_extension = |src: &str| {
+        let tag = Tag::from_str(src).expect("tag");
+        tag.t_extension().map(|t| {
+            (
+                t.as_ref().to_owned(),
+                t.source_tag().map(|s| s.to_string()),
+                t.transform_fields()
+                    .map(|(k, v)| (k.to_owned(), v.to_owned()))
+                    .collect::<Vec<_>>(),
+            )
+        })
+    };
+    let fields = |fs: &[(&str, &str)]| -> Vec<(String, String)> {
+        fs.iter()
+            .map(|&(k, v)| (k.to_owned(), v.to_owned()))
+            .collect()
+    };
+
+    // Source only.
+    assert_eq!(
+        t_extension("en-t-ja"),
+        Some(("ja".into(), Some("ja".into()), vec![]))
+    );
+    assert_eq!(
+        t_extension("en-Latn-t-ja-Jpan-JP-hepburn"),
+        Some((
+            "ja-Jpan-JP-hepburn".into(),
+            Some("ja-Jpan-JP-hepburn".into()),
+            vec![]
+        ))
+    );
+    // Source and fields.
+    assert_eq!(
+        t_extension("en-t-ja-t0-und-fonipa"),
+        Some((
+            "ja-t0-und-fonipa".into(),
+            Some("ja".into()),
+            fields(&[("t0", "und-fonipa")])
+        ))
+    );
+    assert_eq!(
+        t_extension("en-t-ja-Jpan-t0-und-fonipa"),
+        Some((
+            "ja-Jpan-t0-und-fonipa".into(),
+            Some("ja-Jpan".into()),
+            fields(&[("t0", "und-fonipa")])
+        ))
+    );
+    assert_eq!(
+        t_extension("ru-Latn-t-ru-m0-ungegn-2007-h0-hybrid"),
+        Some((
+            "ru-m0-ungegn-2007-h0-hybrid".into(),
+            Some("ru".into()),
+            fields(&[("m0", "ungegn-2007"), ("h0", "hybrid")])
+        ))
+    );
+    // Fields only.
+    assert_eq!(
+        t_extension("und-Cyrl-t-m0-ungegn"),
+        Some(("m0-ungegn".into(), None, fields(&[("m0", "ungegn")])))
+    );
+    assert_eq!(
+        t_extension("und-t-d0-fwidth-s0-accents"),
+        Some((
+            "d0-fwidth-s0-accents".into(),
+            None,
+            fields(&[("d0", "fwidth"), ("s0", "accents")])
+        ))
+    );
+    // Alongside other extensions and private use, in any case.
+    assert_eq!(
+        t_extension("ja-a-abcdef-T-EN-US-M0-ALALOC-u-ca-japanese-x-priv"),
+        Some((
+            "EN-US-M0-ALALOC".into(),
+            Some("EN-US".into()),
+            fields(&[("M0", "ALALOC")])
+        ))
+    );
+    // No t extension.
+    assert_eq!(t_extension("en-Latn-US"), None);
+    assert_eq!(t_extension("en-u-ca-gregory-x-t-ja"), None);
+    assert_eq!(t_extension("x-t-ja"), None);
+}
+
+#[test]
+fn serde_formats() {
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+    use serde_with::serde_as;
+
+    #[serde_as]
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct AsObject(#[serde_as(as = "TagAsObject")] Tag);
+
+    #[serde_as]
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct AsString(#[serde_as(as = "TagAsString")] Tag);
+
+    let tags = [
+        "en",
+        "en-Latn-US",
+        "sgn-ase-US",
+        "en-Latn-US-1abc-2def-a-abcdef-b-ghijklmn-x-priv",
+        "de-t-en-u-ca-gregory-co-phonebk",
+        "x-priv",
+    ];
+    for src in tags {
+        let tag = Tag::from_str(src).expect("tag");
+        let object = serde_json::to_value(AsObject(tag.clone())).expect("object");
+        let string = serde_json::to_value(AsString(tag.clone())).expect("string");
+        assert_eq!(string, json!(src));
+        assert_eq!(string, serde_json::to_value(&tag).expect("plain"));
+
+        let from_object = serde_json::from_value::<AsObject>(object.clone())
+            .expect(src)
+            .0;
+        let from_string = serde_json::from_value::<AsString>(string).expect(src).0;
+        assert_eq!(from_object, tag, "{object}");
+        assert_eq!(from_object, from_string, "{object}");
+        assert_eq!(from_object.to_string(), src);
+    }
+
+    assert_eq!(
+        serde_json::to_value(AsObject("en-Latn-US".parse().unwrap())).unwrap(),
+        json!({"lang": "en", "script": "Latn", "region": "US"})
+    );
+    assert_eq!(
+        serde_json::to_value(AsObject("de-1996-u-co-phonebk-x-priv".parse().unwrap())).unwrap(),
+        json!({"lang": "de", "variants": ["1996"], "extensions": ["u-co-phonebk"], "private": "x-priv"})
+    );
+    assert_eq!(
+        serde_json::to_value(AsObject("x-priv".parse().unwrap())).unwrap(),
+        json!({"private": "x-priv"})
+    );
+
+    let object = |v| serde_json::from_value::<AsObject>(v).map(|o| o.0);
+    assert!(object(json!({"lang": "en", "script": "US"})).is_err());
+    assert!(object(json!({"lang": "en", "region": "Latn"})).is_err());
+    assert!(object(json!({"lang": "en", "dialect": "Cockney"})).is_err());
+    assert!(object(json!({"script": "Latn"})).is_err());
+    assert!(object(json!({"region": "US", "private": "x-priv"})).is_err());
+    assert!(object(json!("en-US")).is_err());
+}
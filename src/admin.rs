@@ -0,0 +1,117 @@
+use crate::config::{self, SharedProfiles};
+use axum::{
+    body::Bytes,
+    extract::{Path, Request, State},
+    http::{header::WWW_AUTHENTICATE, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{delete, post},
+    Router,
+};
+use axum_extra::headers::{authorization::Bearer, Authorization, HeaderMapExt};
+use serde_json::Value;
+use std::sync::{Arc, PoisonError};
+use subtle::ConstantTimeEq;
+use tokio::task;
+
+/*
+POST /admin/profiles {<name>: <profile>, ..}    => add profiles, as defined in the config file
+DELETE /admin/profiles/<name>                   => remove a profile, other than the default
+Both need an Authorization: Bearer <token> header matching --admin-token, or
+the LDML_ADMIN_TOKEN environment variable.
+*/
+
+// The admin API, for managing profiles while running. Only mounted when an
+// admin token is configured.
+pub fn routes(profiles: SharedProfiles, token: String) -> Router {
+    Router::new()
+        .route("/admin/profiles", post(add_profiles))
+        .route("/admin/profiles/:name", delete(remove_profile))
+        .with_state(profiles)
+        .layer(middleware::from_fn_with_state(
+            Arc::<str>::from(token),
+            authorise,
+        ))
+}
+
+async fn authorise(State(token): State<Arc<str>>, req: Request, next: Next) -> Response {
+    let bearer = req.headers().typed_get::<Authorization<Bearer>>();
+    // Compared in constant time, so response timings give nothing away about
+    // how much of a guessed token was right.
+    if bearer.is_some_and(|auth| auth.token().as_bytes().ct_eq(token.as_bytes()).into()) {
+        next.run(req).await
+    } else {
+        (StatusCode::UNAUTHORIZED, [(WWW_AUTHENTICATE, "Bearer")]).into_response()
+    }
+}
+
+fn bad_request(err: impl ToString) -> Response {
+    (StatusCode::BAD_REQUEST, err.to_string()).into_response()
+}
+
+fn conflict(name: &str) -> Response {
+    (
+        StatusCode::CONFLICT,
+        format!("profile \"{name}\" already exists"),
+    )
+        .into_response()
+}
+
+async fn add_profiles(
+    State(shared): State<SharedProfiles>,
+    body: Bytes,
+) -> Result<StatusCode, Response> {
+    let body: Value = serde_json::from_slice(&body).map_err(bad_request)?;
+    let defined = body
+        .as_object()
+        .ok_or_else(|| bad_request("expected a map of profiles"))?
+        .iter()
+        .map(|(name, v)| Ok((name.clone(), config::profiles::from_value(v)?)))
+        .collect::<Result<Vec<_>, config::Error>>()
+        .map_err(bad_request)?;
+
+    // Loading langtags.json is slow, so new profiles are built up in a copy
+    // of the profiles and only inserted once they are all ready.
+    let mut profiles = shared
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone();
+    if let Some((name, _)) = defined.iter().find(|(name, _)| profiles.contains_key(name)) {
+        return Err(conflict(name));
+    }
+    let names: Vec<_> = defined.iter().map(|(name, _)| name.clone()).collect();
+    let profiles = task::spawn_blocking(move || {
+        for (name, cfg) in defined {
+            config::profiles::add(&mut profiles, &name, cfg)?;
+        }
+        Ok::<_, config::Error>(profiles)
+    })
+    .await
+    .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response())?
+    .map_err(bad_request)?;
+
+    let mut shared = shared.write().unwrap_or_else(PoisonError::into_inner);
+    if let Some(name) = names.iter().find(|name| shared.contains_key(*name)) {
+        return Err(conflict(name));
+    }
+    for name in names {
+        tracing::info!("added profile \"{name}\"");
+        shared.insert(name.clone(), profiles[&name].clone());
+    }
+    Ok(StatusCode::CREATED)
+}
+
+async fn remove_profile(
+    State(shared): State<SharedProfiles>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, Response> {
+    let mut profiles = shared.write().unwrap_or_else(PoisonError::into_inner);
+    match config::profiles::remove(&mut profiles, &name) {
+        Ok(Some(_)) => {
+            tracing::info!("removed profile \"{name}\"");
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Ok(None) => Err((StatusCode::NOT_FOUND, format!("no profile \"{name}\"")).into_response()),
+        Err(err) => Err((StatusCode::CONFLICT, err.to_string()).into_response()),
+    }
+}
@@ -0,0 +1,78 @@
+use axum::{
+    extract::{ConnectInfo, Request},
+    middleware::Next,
+    response::Response,
+};
+use std::{
+    fmt::{self, Display},
+    net::{IpAddr, SocketAddr},
+};
+
+// The address of the client making a request, looked up once per request.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ClientAddr(pub IpAddr);
+
+impl Display for ClientAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+// Prefer the address a reverse proxy reports for the original client over
+// the peer address of the connection, which is the proxy's.
+fn get_client_addr(req: &Request) -> Option<ClientAddr> {
+    let headers = req.headers();
+    headers
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next())
+        .or_else(|| headers.get("x-real-ip").and_then(|v| v.to_str().ok()))
+        .and_then(|v| v.trim().parse().ok())
+        .or_else(|| {
+            req.extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip())
+        })
+        .map(ClientAddr)
+}
+
+pub async fn layer(mut req: Request, next: Next) -> Response {
+    if let Some(addr) = get_client_addr(&req) {
+        req.extensions_mut().insert(addr);
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::{get_client_addr, ClientAddr};
+    use axum::{body::Body, extract::ConnectInfo, http::Request};
+    use std::net::SocketAddr;
+
+    #[test]
+    fn client_addr() {
+        let req = Request::builder()
+            .header("x-forwarded-for", "192.0.2.1, 10.0.0.1")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(
+            get_client_addr(&req),
+            Some(ClientAddr([192, 0, 2, 1].into()))
+        );
+
+        let req = Request::builder()
+            .header("x-real-ip", "2001:db8::1")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(get_client_addr(&req).unwrap().to_string(), "2001:db8::1");
+
+        let mut req = Request::builder().body(Body::empty()).unwrap();
+        assert_eq!(get_client_addr(&req), None);
+        req.extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 8000))));
+        assert_eq!(
+            get_client_addr(&req),
+            Some(ClientAddr([127, 0, 0, 1].into()))
+        );
+    }
+}
@@ -1,129 +1,850 @@
+use crate::{ldml::Schema, toggle::Toggle};
+use arc_swap::ArcSwap;
+use axum::body::Bytes;
+use axum_extra::headers::ETag;
 use langtags::json::LangTags;
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use language_tag::Tag;
+use serde_with::DeserializeFromStr;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs, io,
+    net::SocketAddr,
+    ops::Deref,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, OnceLock, RwLock},
+};
 
-#[derive(Debug, PartialEq)]
+// The langtags database can be swapped out from under a shared Config, see
+// reload_langtags. Readers take a snapshot with langtags.load().
+#[derive(Debug)]
 pub struct Config {
     pub sendfile_method: Option<String>,
-    pub langtags: LangTags,
+    pub langtags: Arc<ArcSwap<LoadedLangTags>>,
     pub langtags_dir: PathBuf,
     pub sldr_dir: PathBuf,
+    pub max_upload_bytes: usize,
+    // An XML schema LDML documents must conform to before being served,
+    // compiled when the profile is loaded. Uploads are checked every time.
+    // Stored files, whether customised or streamed straight from disk, are
+    // checked when first served and again only once they change.
+    pub(crate) schema: Option<Arc<Schema>>,
+    pub etag_method: EtagMethod,
+}
+
+impl PartialEq for Config {
+    fn eq(&self, other: &Self) -> bool {
+        self.sendfile_method == other.sendfile_method
+            && **self.langtags.load() == **other.langtags.load()
+            && self.langtags_dir == other.langtags_dir
+            && self.sldr_dir == other.sldr_dir
+            && self.max_upload_bytes == other.max_upload_bytes
+            && self.schema_path() == other.schema_path()
+            && self.etag_method == other.etag_method
+    }
+}
+
+// A langtags database as loaded, with what is derived from it on demand.
+// Reloading swaps in a fresh one, so derived data never outlives its source.
+#[derive(Debug, Default)]
+pub struct LoadedLangTags {
+    langtags: LangTags,
+    // langtags.csv and its ETag, generated on first request.
+    pub(crate) csv: OnceLock<(Bytes, Option<ETag>)>,
+}
+
+impl From<LangTags> for LoadedLangTags {
+    fn from(langtags: LangTags) -> Self {
+        LoadedLangTags {
+            langtags,
+            csv: OnceLock::new(),
+        }
+    }
+}
+
+impl Deref for LoadedLangTags {
+    type Target = LangTags;
+
+    #[inline]
+    fn deref(&self) -> &LangTags {
+        &self.langtags
+    }
+}
+
+impl PartialEq for LoadedLangTags {
+    fn eq(&self, other: &Self) -> bool {
+        self.langtags == other.langtags
+    }
+}
+
+// Largest LDML document accepted for upload unless a profile says otherwise.
+pub const DEFAULT_MAX_UPLOAD_BYTES: usize = 10 << 20;
+
+// How ETags are made for files served as they are on disk: from their
+// modification time and size, or from a hash of their content where mtimes
+// cannot be trusted, eg. on FAT32 or NFS. Content hashes are remembered
+// until a file's mtime or size changes, so without --watch to forget them an
+// edit leaving both alone keeps its old ETag until the server restarts.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum EtagMethod {
+    #[default]
+    Metadata,
+    ContentHash,
+}
+
+// Server wide settings, given as top level keys of the config file beside
+// the profiles rather than inside any one of them. Each is read whatever
+// features were built, so main can warn about those it cannot act on.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Settings {
+    // Also serve the gRPC interface at this address.
+    pub grpc_listen: Option<SocketAddr>,
+    // Export traces over OTLP/gRPC to this endpoint.
+    pub otel_endpoint: Option<String>,
+}
+
+// The top level keys taken by Settings, which cannot name a profile.
+const SETTINGS_KEYS: [&str; 2] = ["grpc_listen", "otel_endpoint"];
+
+// Which of the SLDR's trees to serve LDML from: flat files include the
+// values inherited from parent locales, unflat ones only their own.
+#[derive(Clone, Copy, Debug, Default, DeserializeFromStr, Eq, PartialEq)]
+pub enum FlattenMode {
+    #[default]
+    Flat,
+    Unflat,
+}
+
+// Accepts flat and unflat, or any Toggle value for the flatten parameter's
+// older boolean form.
+impl FromStr for FlattenMode {
+    type Err = core::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            _ if s.eq_ignore_ascii_case("flat") => FlattenMode::Flat,
+            _ if s.eq_ignore_ascii_case("unflat") => FlattenMode::Unflat,
+            _ if s.parse::<Toggle>()?.is_on() => FlattenMode::Flat,
+            _ => FlattenMode::Unflat,
+        })
+    }
 }
 
 impl Config {
-    pub fn sldr_path(&self, flat: bool) -> PathBuf {
-        self.sldr_dir.join(if flat { "flat" } else { "unflat" })
+    pub fn sldr_path(&self, mode: FlattenMode) -> PathBuf {
+        self.sldr_dir.join(match mode {
+            FlattenMode::Flat => "flat",
+            FlattenMode::Unflat => "unflat",
+        })
     }
+
+    // Count the LDML files present anywhere under the SLDR directory.
+    pub fn sldr_file_count(&self) -> io::Result<usize> {
+        walkdir::WalkDir::new(&self.sldr_dir)
+            .into_iter()
+            .try_fold(0, |count, entry| {
+                let entry = entry?;
+                let is_xml = entry.file_type().is_file()
+                    && entry.path().extension().is_some_and(|ext| ext == "xml");
+                Ok(count + usize::from(is_xml))
+            })
+    }
+
+    pub fn langtags_path(&self) -> PathBuf {
+        self.langtags_dir.join("langtags.json")
+    }
+
+    pub fn schema_path(&self) -> Option<&Path> {
+        self.schema.as_deref().map(Schema::path)
+    }
+
+    // Check the directories and settings this config refers to are usable:
+    // the SLDR directory can be listed, langtags.json is a readable file, and
+    // the sendfile header and upload limit make sense.
+    pub fn validate(&self) -> Result<(), Error> {
+        fs::read_dir(&self.sldr_dir)
+            .map_err(|e| Error::with_io_error("sldr", &self.sldr_dir, e))?;
+        let langtags = self.langtags_path();
+        let is_file = fs::File::open(&langtags)
+            .and_then(|f| f.metadata())
+            .map_err(|e| Error::with_io_error("langtags", &langtags, e))?
+            .is_file();
+        if !is_file {
+            let err = io::Error::new(io::ErrorKind::InvalidInput, "not a file");
+            return Err(Error::with_io_error("langtags", &langtags, err));
+        }
+        if let Some(method) = &self.sendfile_method {
+            if axum::http::HeaderName::from_bytes(method.as_bytes()).is_err() {
+                return Err(Error::invalid_field("sendfile_method", "a header name"));
+            }
+        }
+        if self.max_upload_bytes == 0 {
+            return Err(Error::invalid_field("max_upload_bytes", "a size in bytes"));
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    // Re-read the langtags database from disk and swap it in. Requests already
+    // holding the old database finish with it. Nothing is swapped if the
+    // config is no longer valid or the new database fails to load.
+    pub fn reload_langtags(&self) -> Result<(), Error> {
+        self.validate()?;
+        let langtags = load_langtags(&self.langtags_path())?;
+        self.langtags.store(Arc::new(langtags.into()));
+        Ok(())
+    }
+}
+
+// The LDML file for ws in sldr_dir, see LangTags::resolve_sldr_path.
+#[tracing::instrument(ret, skip(langtags))]
+pub fn find_ldml_file(ws: &Tag, sldr_dir: &Path, langtags: &LangTags) -> Option<PathBuf> {
+    langtags.resolve_sldr_path(ws, sldr_dir)
 }
 
 pub type Profiles = HashMap<String, Arc<Config>>;
+pub type SharedProfiles = Arc<RwLock<Profiles>>;
+
+fn read_value<R: io::Read>(mut reader: R, format: Format) -> Result<serde_json::Value, Error> {
+    Ok(match format {
+        Format::Json => serde_json::from_reader(reader)?,
+        Format::Toml => {
+            let mut src = String::new();
+            reader.read_to_string(&mut src)?;
+            toml::from_str(&src)?
+        }
+    })
+}
+
+fn load_langtags(path: &Path) -> Result<LangTags, Error> {
+    let reader = io::BufReader::new(
+        std::fs::File::open(path).map_err(|e| Error::with_io_error("langtags", path, e))?,
+    );
+    let mut langtags = LangTags::from_reader(reader)
+        .map_err(|e| Error::with_io_error("langtags", path, e.into()))?;
+    langtags.build_all_tags_index();
+    Ok(langtags)
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Format {
+    #[default]
+    Json,
+    Toml,
+}
+
+impl Format {
+    pub fn from_extension(path: &Path) -> Format {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Format::Toml,
+            _ => Format::Json,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    IO(io::Error),
+    Json(serde_json::Error),
+    Toml(Box<toml::de::Error>),
+    MissingField {
+        field: String,
+    },
+    InvalidField {
+        field: String,
+        expected: &'static str,
+    },
+    Malformed(&'static str),
+    // The default profile, under its own name or the default alias, cannot
+    // be removed while running.
+    DefaultProfile,
+}
+
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    field: Option<String>,
+    path: Option<PathBuf>,
+}
+
+impl Error {
+    pub fn with_io_error(field: &str, path: impl AsRef<Path>, error: io::Error) -> Self {
+        Error {
+            kind: ErrorKind::IO(error),
+            field: Some(field.to_owned()),
+            path: Some(path.as_ref().to_owned()),
+        }
+    }
+
+    pub fn missing_field(field: &str) -> Self {
+        ErrorKind::MissingField {
+            field: field.to_owned(),
+        }
+        .into()
+    }
+
+    pub fn invalid_field(field: &str, expected: &'static str) -> Self {
+        ErrorKind::InvalidField {
+            field: field.to_owned(),
+            expected,
+        }
+        .into()
+    }
+
+    #[inline]
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    #[inline]
+    pub fn field(&self) -> Option<&str> {
+        self.field.as_deref()
+    }
+
+    pub fn raw_os_error(&self) -> Option<i32> {
+        match &self.kind {
+            ErrorKind::IO(err) => err.raw_os_error(),
+            _ => None,
+        }
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        let field = match &kind {
+            ErrorKind::MissingField { field } | ErrorKind::InvalidField { field, .. } => {
+                Some(field.clone())
+            }
+            _ => None,
+        };
+        Error {
+            kind,
+            field,
+            path: None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        ErrorKind::IO(error).into()
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        ErrorKind::Json(error).into()
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(error: toml::de::Error) -> Self {
+        ErrorKind::Toml(error.into()).into()
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::IO(err) => Some(err),
+            ErrorKind::Json(err) => Some(err),
+            ErrorKind::Toml(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(field) = &self.field {
+            write!(f, "Error in config field \"{field}\": ")?;
+        }
+        if let Some(path) = &self.path {
+            write!(f, "{path}: ", path = path.to_string_lossy())?;
+        }
+        match &self.kind {
+            ErrorKind::IO(err) => err.fmt(f),
+            ErrorKind::Json(err) => err.fmt(f),
+            ErrorKind::Toml(err) => err.fmt(f),
+            ErrorKind::MissingField { .. } => f.write_str("missing required field"),
+            ErrorKind::InvalidField { expected, .. } => write!(f, "expected {expected}"),
+            ErrorKind::Malformed(expected) => write!(f, "malformed config: expected {expected}"),
+            ErrorKind::DefaultProfile => f.write_str("the default profile cannot be removed"),
+        }
+    }
+}
 
 pub mod profiles {
-    use super::{Config, LangTags, Profiles};
+    use super::{
+        find_ldml_file, load_langtags, read_value, Config, Error, ErrorKind, EtagMethod,
+        FlattenMode, Format, Profiles, Schema, DEFAULT_MAX_UPLOAD_BYTES, SETTINGS_KEYS,
+    };
     use serde_json::Value;
     use std::{
-        fs::File,
-        io::{self, BufReader, Read},
+        fs::{self, File},
+        io::{self, Read},
         path::{Path, PathBuf},
+        sync::Arc,
     };
 
-    pub fn from<P, S>(path: P, default: S) -> io::Result<Profiles>
+    pub fn from<P, S>(path: P, default: S) -> Result<Profiles, Error>
     where
         P: AsRef<Path>,
         S: AsRef<str>,
     {
-        let mut profiles = from_reader(File::open(path)?)?;
+        let format = Format::from_extension(path.as_ref());
+        let mut profiles = from_reader_with_format(File::open(path)?, format)?;
         let default = default.as_ref();
         if !default.is_empty() {
-            profiles.insert("".into(), profiles[default].clone());
+            let cfg = profiles
+                .get(default)
+                .ok_or(ErrorKind::Malformed("the default profile to be defined"))?
+                .clone();
+            profiles.insert("".into(), cfg);
         }
         Ok(profiles)
     }
 
-    fn into_parse_error(msg: &str) -> io::Error {
-        io::Error::new(io::ErrorKind::InvalidData, format!("parse failed: {msg}"))
+    // Each distinct config once, under its own name rather than the default
+    // alias, taking the greatest name where several share a config.
+    pub fn unique(profiles: &Profiles) -> impl Iterator<Item = (&str, &Arc<Config>)> {
+        let mut names: Vec<_> = profiles.keys().collect();
+        names.sort_unstable_by(|a, b| b.cmp(a));
+        let mut seen: Vec<(&str, &Arc<Config>)> = Vec::new();
+        for name in names {
+            let cfg = &profiles[name];
+            if !seen.iter().any(|(_, c)| Arc::ptr_eq(c, cfg)) {
+                seen.push((name, cfg));
+            }
+        }
+        seen.into_iter()
     }
 
-    pub fn from_reader<R: Read>(reader: R) -> io::Result<Profiles> {
-        let cfg: Value = serde_json::from_reader(reader)?;
+    // Check each profile's flattened SLDR directory can be read, without
+    // looking at the files in it.
+    pub fn validate_sldr_dirs(profiles: &Profiles) -> Result<(), Error> {
+        for cfg in profiles.values() {
+            let path = cfg.sldr_path(FlattenMode::Flat);
+            fs::read_dir(&path).map_err(|e| Error::with_io_error("sldr", &path, e))?;
+        }
+        Ok(())
+    }
 
+    // Check every tagset marked as having SLDR data can be served from each
+    // profile's flattened SLDR, returning each tag that cannot along with the
+    // path its file was expected at. This touches the filesystem once per
+    // tag, so is only run on request.
+    pub fn validate_sldr_integrity(profiles: &Profiles) -> Vec<(String, PathBuf)> {
+        let mut missing = Vec::new();
+        for (_, cfg) in unique(profiles) {
+            let sldr_dir = cfg.sldr_path(FlattenMode::Flat);
+            let langtags = cfg.langtags.load();
+            for ts in langtags.tagsets_with_sldr() {
+                if find_ldml_file(&ts.full, &sldr_dir, &langtags).is_none() {
+                    let mut expected = sldr_dir.join(&ts.lang()[0..1]);
+                    expected.push(ts.tag.to_string().replace('-', "_"));
+                    missing.push((ts.tag.to_string(), expected.with_extension("xml")));
+                }
+            }
+        }
+        missing
+    }
+
+    fn path_field(tbl: &serde_json::Map<String, Value>, field: &str) -> Result<PathBuf, Error> {
+        tbl.get(field)
+            .ok_or_else(|| Error::missing_field(field))?
+            .as_str()
+            .map(PathBuf::from)
+            .ok_or_else(|| Error::invalid_field(field, "a path string"))
+    }
+
+    pub fn from_reader<R: Read>(reader: R) -> Result<Profiles, Error> {
+        from_reader_with_format(reader, Format::Json)
+    }
+
+    // A profile's config from its entry in the config file, with an empty
+    // langtags database for the caller to load.
+    pub fn from_value(v: &Value) -> Result<Config, Error> {
+        let tbl = v
+            .as_object()
+            .ok_or(ErrorKind::Malformed("a config object for each profile"))?;
+        let sendfile_method = tbl
+            .get("sendfile_method")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let sldr_dir = path_field(tbl, "sldr")?;
+        let langtags_dir = path_field(tbl, "langtags")?;
+        let max_upload_bytes = tbl
+            .get("max_upload_bytes")
+            .map(|v| {
+                v.as_u64()
+                    .and_then(|n| usize::try_from(n).ok())
+                    .ok_or_else(|| Error::invalid_field("max_upload_bytes", "a size in bytes"))
+            })
+            .transpose()?
+            .unwrap_or(DEFAULT_MAX_UPLOAD_BYTES);
+        let schema = tbl
+            .get("schema_path")
+            .map(|_| {
+                let path = path_field(tbl, "schema_path")?;
+                Schema::new(&path).map(Arc::new).map_err(|errs| {
+                    let err = io::Error::new(io::ErrorKind::InvalidData, errs.join("; "));
+                    Error::with_io_error("schema_path", &path, err)
+                })
+            })
+            .transpose()?;
+        let etag_method = tbl
+            .get("etag_method")
+            .map(|v| match v.as_str() {
+                Some("metadata") => Ok(EtagMethod::Metadata),
+                Some("content_hash") => Ok(EtagMethod::ContentHash),
+                _ => Err(Error::invalid_field(
+                    "etag_method",
+                    "\"metadata\" or \"content_hash\"",
+                )),
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(Config {
+            sendfile_method,
+            langtags: Default::default(),
+            langtags_dir,
+            sldr_dir,
+            max_upload_bytes,
+            schema,
+            etag_method,
+        })
+    }
+
+    // Add a profile while running. The config must validate and its
+    // langtags database is loaded afresh. An existing profile is never
+    // replaced.
+    pub fn add(profiles: &mut Profiles, name: &str, cfg: Config) -> Result<(), Error> {
+        if name.is_empty() || profiles.contains_key(name) {
+            return Err(ErrorKind::Malformed("a new profile name").into());
+        }
+        cfg.reload_langtags()?;
+        profiles.insert(name.to_owned(), cfg.into());
+        Ok(())
+    }
+
+    // Remove a profile while running, returning it if it existed. The
+    // default profile, under either its own name or the default alias,
+    // cannot be removed.
+    pub fn remove(profiles: &mut Profiles, name: &str) -> Result<Option<Arc<Config>>, Error> {
+        let is_default = match (profiles.get(name), profiles.get("")) {
+            _ if name.is_empty() => true,
+            (Some(cfg), Some(default)) => Arc::ptr_eq(cfg, default),
+            _ => false,
+        };
+        if is_default {
+            return Err(ErrorKind::DefaultProfile.into());
+        }
+        Ok(profiles.remove(name))
+    }
+
+    pub fn from_reader_with_format<R: Read>(reader: R, format: Format) -> Result<Profiles, Error> {
+        let cfg = read_value(reader, format)?;
         let profiles = cfg
             .as_object()
-            .ok_or_else(|| into_parse_error("profile map"))?;
+            .ok_or(ErrorKind::Malformed("a map of profiles"))?;
         let mut configs = Profiles::with_capacity(profiles.len());
-        // Read defined profiles
+        // Read defined profiles, skipping the server wide settings
         for (name, v) in profiles.iter() {
-            let mut sendfile_method = Default::default();
-            let mut langtags_dir = Default::default();
-            let mut sldr_dir = Default::default();
-
-            v.as_object()
-                .ok_or_else(|| into_parse_error("config object"))
-                .and_then(|tbl| {
-                    sendfile_method = tbl
-                        .get("sendfile_method")
-                        .and_then(Value::as_str)
-                        .map(str::to_string);
-                    sldr_dir = tbl["sldr"]
-                        .as_str()
-                        .map(PathBuf::from)
-                        .ok_or_else(|| into_parse_error("sldr path"))?;
-                    langtags_dir = tbl["langtags"]
-                        .as_str()
-                        .map(PathBuf::from)
-                        .ok_or_else(|| into_parse_error("sldr path"))?;
-                    Ok(())
-                })?;
-
-            let langtags_path = langtags_dir.join("langtags.json");
-            let reader = BufReader::new(File::open(&langtags_path).map_err(|e| {
-                tracing::error!(
-                    "Error: {file}: {message}",
-                    file = langtags_path.to_string_lossy(),
-                    message = e.to_string()
-                );
-                into_parse_error("langtags path")
-            })?);
-            let langtags = LangTags::from_reader(reader)?;
-
-            configs.insert(
-                name.to_owned(),
-                Config {
-                    sendfile_method,
-                    langtags,
-                    langtags_dir,
-                    sldr_dir,
-                }
-                .into(),
-            );
+            if SETTINGS_KEYS.contains(&name.as_str()) {
+                continue;
+            }
+            let cfg = from_value(v)?;
+            cfg.langtags
+                .store(Arc::new(load_langtags(&cfg.langtags_path())?.into()));
+            configs.insert(name.to_owned(), cfg.into());
         }
 
         Ok(configs)
     }
 }
 
+pub mod settings {
+    use super::{read_value, Error, ErrorKind, Format, Settings};
+    use std::{fs::File, io::Read, path::Path};
+
+    pub fn from<P: AsRef<Path>>(path: P) -> Result<Settings, Error> {
+        let format = Format::from_extension(path.as_ref());
+        from_reader_with_format(File::open(path)?, format)
+    }
+
+    pub fn from_reader_with_format<R: Read>(reader: R, format: Format) -> Result<Settings, Error> {
+        let cfg = read_value(reader, format)?;
+        let tbl = cfg
+            .as_object()
+            .ok_or(ErrorKind::Malformed("a map of profiles"))?;
+        let grpc_listen = tbl
+            .get("grpc_listen")
+            .map(|v| {
+                v.as_str()
+                    .and_then(|s| s.parse().ok())
+                    .ok_or_else(|| Error::invalid_field("grpc_listen", "a socket address"))
+            })
+            .transpose()?;
+        let otel_endpoint = tbl
+            .get("otel_endpoint")
+            .map(|v| {
+                v.as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| Error::invalid_field("otel_endpoint", "a URL string"))
+            })
+            .transpose()?;
+        Ok(Settings {
+            grpc_listen,
+            otel_endpoint,
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::{profiles, Arc, Config, LangTags, Profiles};
+    use super::{
+        profiles, settings, Arc, ArcSwap, Config, ErrorKind, EtagMethod, FlattenMode, Format,
+        LangTags, LoadedLangTags, Profiles, Schema, Settings, DEFAULT_MAX_UPLOAD_BYTES,
+    };
     use serde_json::json;
+    use std::io;
+
+    #[test]
+    fn flatten_mode() {
+        for flat in ["flat", "FLAT", "true", "1", "yes", "on"] {
+            assert_eq!(flat.parse(), Ok(FlattenMode::Flat), "{flat}");
+        }
+        for unflat in ["unflat", "Unflat", "false", "0", "no", "off", ""] {
+            assert_eq!(unflat.parse(), Ok(FlattenMode::Unflat), "{unflat}");
+        }
+        assert_eq!(FlattenMode::default(), FlattenMode::Flat);
+    }
 
     #[test]
     fn missing_config() {
         let res = profiles::from("test/missing-config.json", "");
+        assert!(matches!(
+            res.expect_err("io::Error: Not found.").kind(),
+            ErrorKind::IO(err) if err.kind() == io::ErrorKind::NotFound
+        ));
+    }
+
+    #[test]
+    fn unknown_default_profile() {
+        let file = tempfile::Builder::new()
+            .suffix(".json")
+            .tempfile()
+            .expect("temp file");
+        std::fs::write(
+            file.path(),
+            json!({"production": {"langtags": "tests/short", "sldr": "tests"}}).to_string(),
+        )
+        .unwrap();
+        let res = profiles::from(file.path(), "staging");
+        assert!(matches!(
+            res.expect_err("no staging profile").kind(),
+            ErrorKind::Malformed(_)
+        ));
+    }
+
+    #[test]
+    fn add_remove() {
+        let mut profiles = profiles::from_reader(
+            json!({"production": {"langtags": "tests/short", "sldr": "tests"}})
+                .to_string()
+                .as_bytes(),
+        )
+        .expect("profiles");
+        profiles.insert("".into(), profiles["production"].clone());
+        let beta = || {
+            profiles::from_value(&json!({"langtags": "tests/short", "sldr": "tests"}))
+                .expect("beta config")
+        };
+
+        profiles::add(&mut profiles, "beta", beta()).expect("added beta");
+        assert!(!profiles["beta"].langtags.load().is_empty());
         assert_eq!(
-            res.err().expect("io::Error: Not found.").kind(),
-            std::io::ErrorKind::NotFound
+            profiles::unique(&profiles)
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>(),
+            ["production", "beta"]
         );
+        assert!(profiles::add(&mut profiles, "beta", beta()).is_err());
+        assert!(profiles::add(&mut profiles, "", beta()).is_err());
+        let broken = Config {
+            sldr_dir: "tests/missing-sldr/".into(),
+            ..beta()
+        };
+        assert_eq!(
+            profiles::add(&mut profiles, "broken", broken)
+                .expect_err("invalid config")
+                .field(),
+            Some("sldr")
+        );
+
+        for name in ["", "production"] {
+            assert!(matches!(
+                profiles::remove(&mut profiles, name)
+                    .expect_err("default profile")
+                    .kind(),
+                ErrorKind::DefaultProfile
+            ));
+        }
+        assert!(profiles::remove(&mut profiles, "beta")
+            .expect("removed beta")
+            .is_some());
+        assert!(profiles::remove(&mut profiles, "beta")
+            .expect("no beta")
+            .is_none());
+        assert_eq!(profiles.len(), 2);
+    }
+
+    #[test]
+    fn validate() {
+        let valid = || Config {
+            sendfile_method: Some("X-Accel-Redirect".into()),
+            langtags: Default::default(),
+            langtags_dir: "tests/short/".into(),
+            sldr_dir: "tests/".into(),
+            max_upload_bytes: DEFAULT_MAX_UPLOAD_BYTES,
+            schema: None,
+            etag_method: EtagMethod::Metadata,
+        };
+        assert!(valid().is_valid());
+        let field = |cfg: Config| {
+            cfg.validate()
+                .expect_err("invalid config")
+                .field()
+                .map(str::to_owned)
+        };
+
+        let cfg = Config {
+            sldr_dir: "tests/missing-sldr/".into(),
+            ..valid()
+        };
+        assert_eq!(field(cfg), Some("sldr".into()));
+        let cfg = Config {
+            sldr_dir: "tests/en_US.xml".into(),
+            ..valid()
+        };
+        assert_eq!(field(cfg), Some("sldr".into()));
+
+        let cfg = Config {
+            langtags_dir: "tests/".into(),
+            ..valid()
+        };
+        assert_eq!(field(cfg), Some("langtags".into()));
+        let cfg = Config {
+            langtags_dir: "tests/short/langtags.json".into(),
+            ..valid()
+        };
+        assert_eq!(field(cfg), Some("langtags".into()));
+
+        let cfg = Config {
+            sendfile_method: Some("".into()),
+            ..valid()
+        };
+        assert_eq!(field(cfg), Some("sendfile_method".into()));
+        let cfg = Config {
+            sendfile_method: Some("X Accel".into()),
+            ..valid()
+        };
+        assert_eq!(field(cfg), Some("sendfile_method".into()));
+        let cfg = Config {
+            sendfile_method: None,
+            ..valid()
+        };
+        assert!(cfg.is_valid());
+
+        let cfg = Config {
+            max_upload_bytes: 0,
+            ..valid()
+        };
+        assert_eq!(field(cfg), Some("max_upload_bytes".into()));
     }
 
     #[test]
     fn unreadable_config() {
         let res = profiles::from_reader(&br"hang on this isn't JSON!"[..])
-            .err()
-            .expect("io::Error: Invlalid data.");
-        assert_eq!(res.kind(), std::io::ErrorKind::InvalidData);
+            .expect_err("io::Error: Invlalid data.");
+        assert!(matches!(res.kind(), ErrorKind::Json(_)));
         assert_eq!(res.to_string(), "expected value at line 1 column 1");
     }
 
+    #[test]
+    fn missing_field() {
+        let res = profiles::from_reader(
+            json!({"production": {"langtags": "tests/short/"}})
+                .to_string()
+                .as_bytes(),
+        )
+        .expect_err("config::Error: Missing field.");
+        assert!(matches!(
+            res.kind(),
+            ErrorKind::MissingField { field } if field == "sldr"
+        ));
+        assert_eq!(res.field(), Some("sldr"));
+        assert_eq!(
+            res.to_string(),
+            "Error in config field \"sldr\": missing required field"
+        );
+    }
+
+    #[test]
+    fn invalid_field() {
+        let res = profiles::from_reader(
+            json!({"production": {"langtags": 42, "sldr": "/data/sldr/"}})
+                .to_string()
+                .as_bytes(),
+        )
+        .expect_err("config::Error: Invalid field.");
+        assert_eq!(
+            res.to_string(),
+            "Error in config field \"langtags\": expected a path string"
+        );
+
+        let res = profiles::from_reader(
+            json!({"production": {"langtags": "tests/short/", "sldr": "/data/sldr/", "max_upload_bytes": "10MB"}})
+                .to_string()
+                .as_bytes(),
+        )
+        .expect_err("config::Error: Invalid field.");
+        assert_eq!(
+            res.to_string(),
+            "Error in config field \"max_upload_bytes\": expected a size in bytes"
+        );
+
+        let res = profiles::from_reader(
+            json!({"production": {"langtags": "tests/short/", "sldr": "/data/sldr/", "etag_method": "mtime"}})
+                .to_string()
+                .as_bytes(),
+        )
+        .expect_err("config::Error: Invalid field.");
+        assert_eq!(
+            res.to_string(),
+            "Error in config field \"etag_method\": expected \"metadata\" or \"content_hash\""
+        );
+
+        let res = profiles::from_reader(
+            json!({"production": {"langtags": "tests/short/", "sldr": "/data/sldr/", "schema_path": "tests/missing.xsd"}})
+                .to_string()
+                .as_bytes(),
+        )
+        .expect_err("config::Error: Unloadable schema.");
+        assert!(matches!(
+            res.kind(),
+            ErrorKind::IO(err) if err.kind() == io::ErrorKind::InvalidData
+        ));
+        assert_eq!(res.field(), Some("schema_path"));
+    }
+
     #[test]
     fn missing_langtags() {
         let res = profiles::from_reader(
@@ -143,9 +864,110 @@ mod test {
             .to_string()
             .as_bytes(),
         )
-        .err()
-        .expect("io:Error: Not found during profiles::from_reader.");
-        assert_eq!(res.kind(), std::io::ErrorKind::InvalidData);
+        .expect_err("io:Error: Not found during profiles::from_reader.");
+        assert!(matches!(
+            res.kind(),
+            ErrorKind::IO(err) if err.kind() == io::ErrorKind::NotFound
+        ));
+        assert_eq!(res.field(), Some("langtags"));
+        let message = res.to_string();
+        assert!(
+            message.starts_with("Error in config field \"langtags\": /")
+                && message.contains("data/langtags.json: "),
+            "unexpected message: {message}"
+        );
+    }
+
+    #[test]
+    fn format_from_extension() {
+        use std::path::Path;
+        assert_eq!(
+            Format::from_extension(Path::new("ldml-api.toml")),
+            Format::Toml
+        );
+        assert_eq!(
+            Format::from_extension(Path::new("ldml-api.TOML")),
+            Format::Toml
+        );
+        assert_eq!(
+            Format::from_extension(Path::new("ldml-api.json")),
+            Format::Json
+        );
+        assert_eq!(Format::from_extension(Path::new("ldml-api")), Format::Json);
+    }
+
+    #[test]
+    fn toml_config() {
+        let src = r#"
+            # Profiles may be documented inline.
+            [staging]
+            langtags = "tests/short/"
+            sldr = "/staging/data/sldr/"
+
+            [production]
+            sendfile_method = "X-Accel-Redirect"
+            langtags = "tests/short/"
+            sldr = "/data/sldr/"
+        "#;
+        let toml = profiles::from_reader_with_format(src.as_bytes(), Format::Toml)
+            .expect("Profiles value.");
+        let json = profiles::from_reader(
+            json!({
+                "staging": {
+                    "langtags": "tests/short/",
+                    "sldr": "/staging/data/sldr/"
+                },
+                "production": {
+                    "sendfile_method": "X-Accel-Redirect",
+                    "langtags": "tests/short/",
+                    "sldr": "/data/sldr/"
+                }
+            })
+            .to_string()
+            .as_bytes(),
+        )
+        .expect("Profiles value.");
+        assert_eq!(toml, json);
+
+        let res = profiles::from_reader_with_format(&b"[staging\n"[..], Format::Toml)
+            .expect_err("TOML parse error");
+        assert!(matches!(res.kind(), ErrorKind::Toml(_)));
+
+        let res = profiles::from_reader_with_format(
+            &b"[staging]\nlangtags = \"tests/short/\"\n"[..],
+            Format::Toml,
+        )
+        .expect_err("missing sldr field");
+        assert!(matches!(res.kind(), ErrorKind::MissingField { field } if field == "sldr"));
+    }
+
+    #[test]
+    fn settings() {
+        let src = r#"
+            grpc_listen = "127.0.0.1:50051"
+            otel_endpoint = "http://localhost:4317"
+
+            [production]
+            langtags = "tests/short/"
+            sldr = "/data/sldr/"
+        "#;
+        let res = settings::from_reader_with_format(src.as_bytes(), Format::Toml)
+            .expect("Settings value.");
+        assert_eq!(res.grpc_listen, Some(([127, 0, 0, 1], 50051).into()));
+        assert_eq!(res.otel_endpoint.as_deref(), Some("http://localhost:4317"));
+        let res = profiles::from_reader_with_format(src.as_bytes(), Format::Toml)
+            .expect("Profiles value.");
+        assert_eq!(res.keys().collect::<Vec<_>>(), ["production"]);
+
+        let res =
+            settings::from_reader_with_format(&b"{}"[..], Format::Json).expect("Settings value.");
+        assert_eq!(res, Settings::default());
+        let res = settings::from_reader_with_format(
+            json!({"grpc_listen": "localhost"}).to_string().as_bytes(),
+            Format::Json,
+        )
+        .expect_err("not a socket address");
+        assert_eq!(res.field(), Some("grpc_listen"));
     }
 
     #[test]
@@ -160,7 +982,10 @@ mod test {
                     "production": {
                         "sendfile_method": "X-Accel-Redirect",
                         "langtags": "tests/short/",
-                        "sldr": "/data/sldr/"
+                        "sldr": "/data/sldr/",
+                        "max_upload_bytes": 1048576,
+                        "schema_path": "tests/ldml-minimal.xsd",
+                        "etag_method": "content_hash"
                     }
                 }
             )
@@ -329,6 +1154,7 @@ mod test {
                 "names": [ "Tahaggart Tamahaq", "Tamachek", "Tamachek’", "Tamahaq", "Tamashekin", "Tamasheq", "Tomachek", "Touareg", "Tourage", "Tuareg" ],
                 "region": "DZ",
                 "regionname": "Algeria",
+                "rod": "10081",
                 "script": "Latn",
                 "sldr": true,
                 "tag": "thv-Latn-DZ-x-ahaggar",
@@ -336,25 +1162,36 @@ mod test {
             }
         ]).to_string();
         let langtags_json = langtags_json.as_bytes();
+        let langtags = |msg| {
+            let mut langtags = LangTags::from_reader(langtags_json).expect(msg);
+            langtags.build_all_tags_index();
+            Arc::new(ArcSwap::from_pointee(LoadedLangTags::from(langtags)))
+        };
         let mut expected = Profiles::new();
         expected.insert(
             "production".into(),
             Arc::new(Config {
                 sendfile_method: Some("X-Accel-Redirect".into()),
-                langtags: LangTags::from_reader(langtags_json)
-                    .expect("LangTags production test case."),
+                langtags: langtags("LangTags production test case."),
                 langtags_dir: "tests/short/".into(),
                 sldr_dir: "/data/sldr/".into(),
+                max_upload_bytes: 1 << 20,
+                schema: Some(Arc::new(
+                    Schema::new("tests/ldml-minimal.xsd".as_ref()).expect("schema"),
+                )),
+                etag_method: EtagMethod::ContentHash,
             }),
         );
         expected.insert(
             "staging".into(),
             Config {
                 sendfile_method: None,
-                langtags: LangTags::from_reader(langtags_json)
-                    .expect("LangTags staging test case."),
+                langtags: langtags("LangTags staging test case."),
                 langtags_dir: "tests/short/".into(),
                 sldr_dir: "/staging/data/sldr/".into(),
+                max_upload_bytes: DEFAULT_MAX_UPLOAD_BYTES,
+                schema: None,
+                etag_method: EtagMethod::Metadata,
             }
             .into(),
         );
@@ -0,0 +1,79 @@
+use crate::config::Config;
+use axum::{
+    body::{self, Body},
+    extract::Request,
+    http::{header::CONTENT_LENGTH, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::{borrow::Cow, sync::Arc};
+use tracing::{Instrument, Level};
+
+const PREVIEW_BYTES: usize = 4096;
+
+// The first PREVIEW_BYTES of a body, as text.
+fn preview(bytes: &[u8]) -> Cow<'_, str> {
+    String::from_utf8_lossy(&bytes[..bytes.len().min(PREVIEW_BYTES)])
+}
+
+// Debug builds only: log each request's URI and headers, the start of any
+// POSTed body, and the start of any error response body. Bodies are buffered
+// to do this, so keep it off routes serving large files. Nothing is buffered
+// unless debug logging is enabled.
+pub async fn layer(req: Request, next: Next) -> Response {
+    if !tracing::enabled!(Level::DEBUG) {
+        return next.run(req).await;
+    }
+    let span = tracing::debug_span!("debug_log", method = %req.method(), uri = %req.uri());
+    async move {
+        tracing::debug!(headers = ?req.headers(), "request");
+        let req = if req.method() == Method::POST {
+            let limit = req
+                .extensions()
+                .get::<Arc<Config>>()
+                .map_or(usize::MAX, |cfg| cfg.max_upload_bytes);
+            let (parts, body) = req.into_parts();
+            let Ok(bytes) = body::to_bytes(body, limit).await else {
+                return (
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    format!("LDML uploads are limited to {limit} bytes."),
+                )
+                    .into_response();
+            };
+            tracing::debug!(body = %preview(&bytes), "request body");
+            Request::from_parts(parts, Body::from(bytes))
+        } else {
+            req
+        };
+
+        let rsp = next.run(req).await;
+        if !(rsp.status().is_client_error() || rsp.status().is_server_error()) {
+            return rsp;
+        }
+        let (mut parts, body) = rsp.into_parts();
+        match body::to_bytes(body, usize::MAX).await {
+            Ok(bytes) => {
+                tracing::debug!(status = %parts.status, body = %preview(&bytes), "error response");
+                Response::from_parts(parts, Body::from(bytes))
+            }
+            Err(err) => {
+                tracing::debug!(status = %parts.status, "error response body unreadable: {err}");
+                parts.headers.remove(CONTENT_LENGTH);
+                Response::from_parts(parts, Body::empty())
+            }
+        }
+    }
+    .instrument(span)
+    .await
+}
+
+#[cfg(test)]
+mod test {
+    use super::{preview, PREVIEW_BYTES};
+
+    #[test]
+    fn preview_truncates() {
+        assert_eq!(preview(b"<ldml/>"), "<ldml/>");
+        assert_eq!(preview(&[b'a'; PREVIEW_BYTES + 10]).len(), PREVIEW_BYTES);
+    }
+}
@@ -1,9 +1,16 @@
+use crate::config::EtagMethod;
 use axum::{extract::Request, http::StatusCode, middleware::Next, response::Response};
 use axum_extra::headers::{ETag, Header, HeaderMapExt, IfNoneMatch};
+use lru::LruCache;
+use sha2::{Digest, Sha256};
 use std::{
     fs,
     hash::{Hash, Hasher},
-    path::Path,
+    io::Read,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock, PoisonError},
+    time::SystemTime,
 };
 
 pub async fn layer(req: Request, next: Next) -> Response {
@@ -33,6 +40,92 @@ pub fn from_metadata(path: &Path) -> Option<ETag> {
     token.parse::<ETag>().ok()
 }
 
+// How many files' content hashes are remembered.
+const CONTENT_HASH_CACHE_SIZE: usize = 1 << 12;
+
+type Stamp = (SystemTime, u64);
+
+fn content_hash_cache() -> &'static Mutex<LruCache<PathBuf, (Stamp, ETag)>> {
+    static CACHE: OnceLock<Mutex<LruCache<PathBuf, (Stamp, ETag)>>> = OnceLock::new();
+    CACHE.get_or_init(|| {
+        Mutex::new(LruCache::new(
+            NonZeroUsize::new(CONTENT_HASH_CACHE_SIZE).unwrap(),
+        ))
+    })
+}
+
+// Forget every remembered content hash, eg. when files are seen changing on
+// disk, so edits that kept their size and mtime are picked up.
+pub fn clear_content_hashes() {
+    content_hash_cache()
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clear();
+}
+
+// An ETag from the SHA-256 hash of the file's content, for filesystems whose
+// mtimes are too coarse to tell every change apart, eg. FAT32 or NFS. Each
+// file is hashed once and the hash reused until its size or mtime changes,
+// so an edit that leaves both alone is only noticed once the file falls out
+// of the cache, the cache is cleared, or the server restarts. This reads the
+// whole file, so keep it off the async runtime, see from_file.
+pub fn from_content_hash(path: &Path) -> Option<ETag> {
+    let meta = fs::metadata(path).ok()?;
+    let stamp = (meta.modified().ok()?, meta.len());
+    let cache = content_hash_cache();
+    if let Some((cached, etag)) = cache
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(path)
+    {
+        if *cached == stamp {
+            return Some(etag.clone());
+        }
+    }
+
+    let mut file = fs::File::open(path).ok()?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 1 << 14]; // 16KiB chunks
+    loop {
+        match file.read(&mut buf).ok()? {
+            0 => break,
+            len => hasher.update(&buf[..len]),
+        }
+    }
+    let etag = sha256_etag(hasher)?;
+    cache
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .put(path.to_owned(), (stamp, etag.clone()));
+    Some(etag)
+}
+
+// An ETag from the SHA-256 hash of a response body generated in memory.
+pub fn from_content(content: &[u8]) -> Option<ETag> {
+    sha256_etag(Sha256::new_with_prefix(content))
+}
+
+fn sha256_etag(hasher: Sha256) -> Option<ETag> {
+    format!("\"sha256:{hash:x}\"", hash = hasher.finalize())
+        .parse()
+        .ok()
+}
+
+// The ETag for a file served as is, by the configured method. Content is
+// hashed on a blocking thread so large files do not stall the runtime.
+pub async fn from_file(path: &Path, method: EtagMethod) -> Option<ETag> {
+    match method {
+        EtagMethod::Metadata => from_metadata(path),
+        EtagMethod::ContentHash => {
+            let path = path.to_owned();
+            tokio::task::spawn_blocking(move || from_content_hash(&path))
+                .await
+                .ok()
+                .flatten()
+        }
+    }
+}
+
 #[inline]
 pub fn weaken(etag: ETag) -> ETag {
     let mut header = vec![];
@@ -82,11 +175,11 @@ pub mod revid {
 
     pub async fn converter(mut req: Request, next: Next) -> Result<Response, Response>
 where {
-        let header = req
+        let Query(param) = req
             .extract_parts::<Query<Param>>()
             .await
-            .map_err(|e| e.into_response())
-            .and_then(|Query(param)| param.into_header().map_err(|e| e.into_response()))?;
+            .map_err(|e| e.into_response())?;
+        let header = param.into_header().map_err(|e| e.into_response())?;
 
         if let Some(header) = header {
             tracing::info!("converted revid to {header:?}");
@@ -119,3 +212,55 @@ where {
         token.parse::<ETag>().ok()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{clear_content_hashes, from_content_hash, from_metadata};
+    use std::{
+        fs,
+        time::{Duration, SystemTime},
+    };
+
+    #[test]
+    fn content_hash() {
+        let tmp = tempfile::tempdir().expect("temp dir");
+        let dir = tmp.path();
+        let (a, b, c) = (dir.join("a.xml"), dir.join("b.xml"), dir.join("c.xml"));
+        let epoch = SystemTime::UNIX_EPOCH;
+        for (path, content, mtime) in [
+            (&a, "<ldml/>", epoch + Duration::from_secs(1 << 30)),
+            (&b, "<ldml/>", epoch + Duration::from_secs(1 << 31)),
+            (&c, "<ldml />", epoch + Duration::from_secs(1 << 31)),
+        ] {
+            fs::write(path, content).expect("test file");
+            fs::File::options()
+                .write(true)
+                .open(path)
+                .and_then(|f| f.set_modified(mtime))
+                .expect("set mtime");
+        }
+
+        let etag = from_content_hash(&a).expect("content hash");
+        assert_eq!(from_content_hash(&b), Some(etag.clone()));
+        assert_ne!(from_content_hash(&c), Some(etag.clone()));
+        assert_ne!(from_metadata(&a), from_metadata(&b));
+        let expected =
+            "\"sha256:b821bb65559b9b164271a397240c8da0dbd7f8593f3dcd6f568ee235923dfa20\"";
+        assert_eq!(etag, expected.parse().unwrap());
+
+        // A rewritten file is hashed afresh.
+        fs::write(&a, "<ldml></ldml>").expect("test file");
+        assert_ne!(from_content_hash(&a), Some(etag.clone()));
+        // But one keeping its size and mtime only once the cache is cleared.
+        fs::write(&b, "<LDML/>").expect("test file");
+        fs::File::options()
+            .write(true)
+            .open(&b)
+            .and_then(|f| f.set_modified(epoch + Duration::from_secs(1 << 31)))
+            .expect("set mtime");
+        assert_eq!(from_content_hash(&b), Some(etag.clone()));
+        clear_content_hashes();
+        assert_ne!(from_content_hash(&b), Some(etag));
+        assert_eq!(from_content_hash(&dir.join("missing.xml")), None);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{
+    config::{find_ldml_file, Config, FlattenMode, SharedProfiles},
+    equivalence_sets,
+};
+use language_tag::Tag;
+use std::{
+    net::SocketAddr,
+    sync::{Arc, PoisonError},
+};
+use tonic::{transport::Server, Request, Response, Status};
+
+mod proto {
+    tonic::include_proto!("ldml");
+}
+
+pub use proto::{
+    ldml_client::LdmlClient, ldml_server::LdmlServer, LdmlResponse, TagRequest, TagSetResponse,
+    TagsResponse,
+};
+
+pub struct LdmlService {
+    profiles: SharedProfiles,
+}
+
+impl LdmlService {
+    pub fn new(profiles: SharedProfiles) -> Self {
+        LdmlService { profiles }
+    }
+
+    // Status is large, but it is what every handler returns anyway.
+    #[allow(clippy::result_large_err)]
+    fn resolve(&self, req: &TagRequest) -> Result<(Tag, Arc<Config>), Status> {
+        let tag = req
+            .tag
+            .parse::<Tag>()
+            .map_err(|err| Status::invalid_argument(format!("{tag}: {err}", tag = req.tag)))?;
+        let profiles = self.profiles.read().unwrap_or_else(PoisonError::into_inner);
+        let cfg = profiles
+            .get(&req.profile)
+            .ok_or_else(|| Status::not_found(format!("Unknown profile: {}", req.profile)))?;
+        Ok((tag, cfg.clone()))
+    }
+}
+
+fn no_tagset(ws: &Tag) -> Status {
+    Status::not_found(format!("No tagsets found for tag: {ws}"))
+}
+
+#[tonic::async_trait]
+impl proto::ldml_server::Ldml for LdmlService {
+    async fn resolve_tag(
+        &self,
+        req: Request<TagRequest>,
+    ) -> Result<Response<TagSetResponse>, Status> {
+        let (ws, cfg) = self.resolve(req.get_ref())?;
+        let langtags = cfg.langtags.load();
+        let ts = langtags
+            .orthographic_normal_form(&ws)
+            .ok_or_else(|| no_tagset(&ws))?;
+        Ok(Response::new(TagSetResponse {
+            full: ts.full.to_string(),
+            tag: ts.tag.to_string(),
+            tags: ts.tags.iter().map(Tag::to_string).collect(),
+            name: ts.name.clone(),
+            windows: ts.windows.to_string(),
+            sldr: ts.sldr,
+        }))
+    }
+
+    async fn get_ldml(&self, req: Request<TagRequest>) -> Result<Response<LdmlResponse>, Status> {
+        let (ws, cfg) = self.resolve(req.get_ref())?;
+        let path = find_ldml_file(&ws, &cfg.sldr_path(FlattenMode::Flat), &cfg.langtags.load())
+            .ok_or_else(|| Status::not_found(format!("No LDML for {ws}")))?;
+        let ldml = tokio::fs::read(&path)
+            .await
+            .map_err(|err| Status::internal(err.to_string()))?;
+        Ok(Response::new(LdmlResponse { ldml }))
+    }
+
+    async fn lookup_tags(
+        &self,
+        req: Request<TagRequest>,
+    ) -> Result<Response<TagsResponse>, Status> {
+        let (ws, cfg) = self.resolve(req.get_ref())?;
+        let sets = equivalence_sets(&ws, &cfg.langtags.load())
+            .ok_or_else(|| no_tagset(&ws))?
+            .collect();
+        Ok(Response::new(TagsResponse { sets }))
+    }
+}
+
+pub fn service(profiles: SharedProfiles) -> LdmlServer<LdmlService> {
+    LdmlServer::new(LdmlService::new(profiles))
+}
+
+pub async fn serve(
+    addr: SocketAddr,
+    profiles: SharedProfiles,
+) -> Result<(), tonic::transport::Error> {
+    Server::builder()
+        .add_service(service(profiles))
+        .serve(addr)
+        .await
+}
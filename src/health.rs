@@ -0,0 +1,73 @@
+use crate::config::{profiles, Config, SharedProfiles};
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde_json::json;
+use std::sync::{Arc, PoisonError};
+use tokio::fs;
+
+// Fewer spare file descriptors than this and we report ourselves degraded,
+// as every LDML request needs at least one.
+const MIN_FREE_FDS: usize = 64;
+
+pub async fn live() -> impl IntoResponse {
+    Json(json!({"status": "ok"}))
+}
+
+pub async fn ready(State(shared): State<SharedProfiles>) -> impl IntoResponse {
+    let mut checks = Vec::new();
+    let unique: Vec<(String, Arc<Config>)> = {
+        let shared = shared.read().unwrap_or_else(PoisonError::into_inner);
+        profiles::unique(&shared)
+            .map(|(name, cfg)| (name.to_owned(), cfg.clone()))
+            .collect()
+    };
+    for (name, cfg) in unique {
+        if cfg.langtags.load().tagsets().next().is_none() {
+            checks.push(format!("profile {name}: langtags not loaded"));
+        }
+        if !fs::metadata(&cfg.sldr_dir).await.is_ok_and(|m| m.is_dir()) {
+            checks.push(format!(
+                "profile {name}: SLDR directory {dir} is inaccessible",
+                dir = cfg.sldr_dir.display()
+            ));
+        }
+    }
+    if let Some(free) = free_fds().await {
+        if free < MIN_FREE_FDS {
+            checks.push(format!("only {free} file descriptors available"));
+        }
+    }
+
+    if checks.is_empty() {
+        (StatusCode::OK, Json(json!({"status": "ok"})))
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({"status": "degraded", "checks": checks})),
+        )
+    }
+}
+
+#[cfg(target_os = "linux")]
+async fn free_fds() -> Option<usize> {
+    let limits = fs::read_to_string("/proc/self/limits").await.ok()?;
+    let limit = limits
+        .lines()
+        .find_map(|l| l.strip_prefix("Max open files"))?
+        .split_whitespace()
+        .next()?;
+    if limit == "unlimited" {
+        return None;
+    }
+    let limit: usize = limit.parse().ok()?;
+    let mut open = 0;
+    let mut fds = fs::read_dir("/proc/self/fd").await.ok()?;
+    while fds.next_entry().await.ok()?.is_some() {
+        open += 1;
+    }
+    Some(limit.saturating_sub(open))
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn free_fds() -> Option<usize> {
+    None
+}
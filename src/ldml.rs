@@ -1,16 +1,250 @@
 use core::fmt;
+use language_tag::Tag;
 use libxml::{
+    bindings,
+    error::StructuredError,
     parser::{Parser, ParserOptions},
     readonly::RoNode,
+    schemas::{SchemaParserContext, SchemaValidationContext},
     tree::{self, document::SaveOptions},
     xpath,
 };
-use std::{io, path::Path};
+use lru::LruCache;
+use std::{
+    ffi::{c_char, c_int, c_void},
+    fs,
+    io::{self, Write},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    slice,
+    sync::{Mutex, PoisonError},
+    time::{Instant, SystemTime},
+};
 
 pub struct Document {
     inner: tree::Document,
 }
 
+// How many stored files' validation results a schema remembers.
+const VALIDATED_FILES_CACHE_SIZE: usize = 1 << 12;
+
+type Stamp = (SystemTime, u64);
+
+// An XML schema, compiled when its profile is loaded and then shared by every
+// request checking documents against it. A libxml validation context may only
+// be used by one thread at a time, so the schema keeps a pool of them,
+// compiling another when every one is in use.
+pub struct Schema {
+    path: PathBuf,
+    validators: Mutex<Vec<Validator>>,
+    // Results for stored files, reused until a file's size or mtime changes.
+    validated: Mutex<LruCache<PathBuf, (Stamp, Result<(), Vec<String>>)>>,
+}
+
+struct Validator(SchemaValidationContext);
+
+// SAFETY: a validation context, and the compiled schema it owns, are heap
+// allocations with no tie to the thread that made them. libxml's per-thread
+// state is only touched during a call, and errors are collected in a log the
+// context owns. Validator is Send but not Sync, and the pool hands each one
+// to a single thread at a time, so no context is ever used concurrently.
+unsafe impl Send for Validator {}
+
+// libxml's description of each problem, with its line number where known.
+fn describe(errors: Vec<StructuredError>) -> Vec<String> {
+    errors
+        .into_iter()
+        .map(|err| {
+            let message = err.message.as_deref().unwrap_or("unknown error").trim();
+            match err.line {
+                Some(line) => format!("line {line}: {message}"),
+                None => message.to_owned(),
+            }
+        })
+        .collect()
+}
+
+fn utf8_path(path: &Path) -> Result<&str, Vec<String>> {
+    path.to_str()
+        .ok_or_else(|| vec![format!("{path:?}: path is not UTF-8")])
+}
+
+impl Schema {
+    pub fn new(path: &Path) -> Result<Self, Vec<String>> {
+        let validator = Schema::compile(path)?;
+        Ok(Schema {
+            path: path.to_owned(),
+            validators: Mutex::new(vec![validator]),
+            validated: Mutex::new(LruCache::new(
+                NonZeroUsize::new(VALIDATED_FILES_CACHE_SIZE).unwrap(),
+            )),
+        })
+    }
+
+    fn compile(path: &Path) -> Result<Validator, Vec<String>> {
+        let mut parser = SchemaParserContext::from_file(utf8_path(path)?);
+        SchemaValidationContext::from_parser(&mut parser)
+            .map(Validator)
+            .map_err(describe)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Run validate with a validation context from the pool, returning it
+    // afterwards. The schema file is compiled again if the pool is empty.
+    fn with_validator<F>(&self, validate: F) -> Result<(), Vec<String>>
+    where
+        F: FnOnce(&mut SchemaValidationContext) -> Result<(), Vec<StructuredError>>,
+    {
+        let pooled = self
+            .validators
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop();
+        let mut validator = match pooled {
+            Some(validator) => validator,
+            None => Schema::compile(&self.path)?,
+        };
+        let result = validate(&mut validator.0).map_err(describe);
+        self.validators
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(validator);
+        result
+    }
+
+    // Check the stored LDML file at path, which is streamed through the
+    // validator rather than parsed into a document. The result is remembered
+    // until the file's size or mtime changes, so a file is normally checked
+    // once however often it is served. This reads the file, so keep it off
+    // the async runtime.
+    pub fn validate_file(&self, path: &Path) -> Result<(), Vec<String>> {
+        let meta = fs::metadata(path).map_err(|err| vec![format!("{path:?}: {err}")])?;
+        let stamp = (
+            meta.modified()
+                .map_err(|err| vec![format!("{path:?}: {err}")])?,
+            meta.len(),
+        );
+        if let Some((checked, result)) = self
+            .validated
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(path)
+        {
+            if *checked == stamp {
+                return result.clone();
+            }
+        }
+        let utf8 = utf8_path(path)?;
+        let result = self.with_validator(|validator| validator.validate_file(utf8));
+        self.validated
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .put(path.to_owned(), (stamp, result.clone()));
+        result
+    }
+}
+
+impl fmt::Debug for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Schema").field(&self.path).finish()
+    }
+}
+
+// Functions which may appear in subset XPath predicates, anything else is
+// refused so clients cannot reach extension or document loading functions.
+const PREDICATE_FUNCTIONS: &[&str] = &[
+    "contains",
+    "last",
+    "normalize-space",
+    "not",
+    "position",
+    "starts-with",
+    "string",
+    "string-length",
+    "text",
+];
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+// Split a comma separated list of subset XPaths, ignoring commas nested in
+// predicates, function arguments or string literals.
+pub fn split_xpaths(list: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in list.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '[' | '(') => depth += 1,
+            (None, ']' | ')') => depth = depth.saturating_sub(1),
+            (None, ',') if depth == 0 => {
+                items.push(&list[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&list[start..]);
+    items
+}
+
+// Check a subset XPath is either a bare top-level element name or a
+// restricted expression rooted at one.
+pub fn check_xpath(xpath: &str) -> Result<(), String> {
+    if xpath.is_empty() {
+        return Err("empty XPath".into());
+    }
+    if xpath.chars().all(is_name_char) {
+        return Ok(());
+    }
+    if let Some(forbidden) = ["//", "..", "::", "|"].iter().find(|&&f| xpath.contains(f)) {
+        return Err(format!("XPath may not contain \"{forbidden}\": {xpath}"));
+    }
+    // A call is a name before an opening bracket outside any string literal.
+    // A prefix is taken as part of the name, so prefix:contains(..) cannot
+    // pass for contains(..).
+    let mut quote = None;
+    for (i, c) in xpath.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(') => {
+                let head = xpath[..i].trim_end();
+                let start = head
+                    .rfind(|c| !is_name_char(c) && c != ':')
+                    .map_or(0, |p| p + 1);
+                let name = &head[start..];
+                if !PREDICATE_FUNCTIONS.contains(&name) {
+                    return Err(format!("XPath function \"{name}\" not permitted: {xpath}"));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// Run one phase of LDML customisation inside its own span, reporting how
+// long it took when debug logging is on.
+pub(crate) fn timed<T>(span: tracing::Span, phase: impl FnOnce() -> T) -> T {
+    let _phase = span.enter();
+    let start = Instant::now();
+    let result = phase();
+    if tracing::enabled!(tracing::Level::DEBUG) {
+        tracing::info!(elapsed = ?start.elapsed(), "phase complete");
+    }
+    result
+}
+
 impl Document {
     pub fn new<'a>(path: impl AsRef<Path> + 'a) -> io::Result<Self> {
         // let doc = fs::read(path)?;
@@ -18,19 +252,70 @@ impl Document {
         let inner = parser
             .parse_file_with_options(
                 path.as_ref().to_str().ok_or(io::ErrorKind::InvalidInput)?,
+                Document::parser_options(),
+            )
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(Document { inner })
+    }
+
+    // Unlike SLDR files, submitted documents are untrusted, so malformed
+    // input is refused rather than recovered from.
+    pub fn from_bytes(src: &[u8]) -> io::Result<Self> {
+        let parser: Parser = Default::default();
+        let inner = parser
+            .parse_string_with_options(
+                src,
                 ParserOptions {
-                    no_def_dtd: true,
-                    no_blanks: true,
-                    no_net: true,
-                    no_implied: true,
-                    compact: true,
-                    ..Default::default()
+                    recover: false,
+                    ..Document::parser_options()
                 },
             )
             .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
         Ok(Document { inner })
     }
 
+    fn parser_options<'a>() -> ParserOptions<'a> {
+        ParserOptions {
+            no_def_dtd: true,
+            no_blanks: true,
+            no_net: true,
+            no_implied: true,
+            compact: true,
+            ..Default::default()
+        }
+    }
+
+    // Check the document against schema, giving libxml's description of
+    // each problem found on failure.
+    pub fn validate_schema(&self, schema: &Schema) -> Result<(), Vec<String>> {
+        schema.with_validator(|validator| validator.validate_document(&self.inner))
+    }
+
+    // Reconstruct the language tag described by the identity element.
+    pub fn identity_tag(&self) -> Option<Tag> {
+        let subtags = |name: &str| {
+            self.findnodes(&format!("/ldml/identity/{name}"))
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|node| node.get_attribute("type"))
+                .collect::<Vec<_>>()
+        };
+        let lang = subtags("language");
+        let script = subtags("script");
+        let region = subtags("territory");
+        let variants = subtags("variant");
+        fn first(v: &[String]) -> &str {
+            v.first().map_or("", String::as_str)
+        }
+        Tag::builder()
+            .lang(lang.first()?)
+            .script(first(&script))
+            .region(first(&region))
+            .variants(variants.iter().map(String::as_str).collect::<Vec<_>>())
+            .try_build()
+            .ok()
+    }
+
     fn get_context(&self) -> Option<xpath::Context> {
         let ctxt = xpath::Context::new(&self.inner).ok()?;
         ctxt.register_namespace("sil", "urn://www.sil.org/ldml/0.1")
@@ -51,7 +336,12 @@ impl Document {
             .and_then(|mut ctxt| ctxt.findvalue(xpath, None).ok())
     }
 
+    // Keep only the top-level elements selected by xpaths, plus identity.
+    // Each xpath is either a bare element name or an expression such as
+    // collations[collation/@type='standard'], evaluated against each
+    // top-level element in turn.
     pub fn subset(&mut self, xpaths: &[&str]) -> Result<(), String> {
+        xpaths.iter().copied().try_for_each(check_xpath)?;
         let compound =
             "/ldml/*[self::".to_string() + &xpaths.join(" or self::") + " or self::identity]";
         let nodes = self.findnodes(&compound).ok_or("XPath evalution failed")?;
@@ -69,7 +359,7 @@ impl Document {
         Ok(())
     }
 
-    pub fn set_uid(&mut self, uid: u32) -> Result<(), String> {
+    pub fn set_uid(&mut self, uid: impl fmt::Display) -> Result<(), String> {
         let mut ctxt = self.get_context().ok_or("XPath context creation failed")?;
         let mut nodes = ctxt
             .findnodes("//sil:identity", None)
@@ -80,6 +370,71 @@ impl Document {
             .map_err(|err| format!("Failed to set uid attribute: {err}"))?;
         Ok(())
     }
+
+    pub fn write_subset_to<W: Write>(&mut self, xpaths: &[&str], writer: &mut W) -> io::Result<()> {
+        timed(tracing::info_span!("ldml_subset", ?xpaths), || {
+            self.subset(xpaths)
+        })
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        timed(tracing::info_span!("ldml_serialise"), || {
+            self.write_to(writer)
+        })
+    }
+
+    // Serialise the document straight into writer using libxml's IO callback
+    // API, so output is passed on in chunks as it is produced rather than
+    // being built up in a single string first.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        struct Sink<'w, W> {
+            writer: &'w mut W,
+            error: Option<io::Error>,
+        }
+
+        unsafe extern "C" fn write_callback<W: Write>(
+            context: *mut c_void,
+            buffer: *const c_char,
+            len: c_int,
+        ) -> c_int {
+            let sink = &mut *(context as *mut Sink<W>);
+            let data = slice::from_raw_parts(buffer as *const u8, len as usize);
+            match sink.writer.write_all(data) {
+                Ok(_) => len,
+                Err(err) => {
+                    sink.error = Some(err);
+                    -1
+                }
+            }
+        }
+
+        let mut sink = Sink {
+            writer,
+            error: None,
+        };
+        let options = bindings::xmlSaveOption_XML_SAVE_FORMAT
+            | bindings::xmlSaveOption_XML_SAVE_NO_XHTML
+            | bindings::xmlSaveOption_XML_SAVE_WSNONSIG;
+        // SAFETY: sink outlives the save context, which is closed before
+        // this function returns, and the callback only ever sees it as a Sink<W>.
+        let status = unsafe {
+            let ctxt = bindings::xmlSaveToIO(
+                Some(write_callback::<W>),
+                None,
+                &mut sink as *mut Sink<W> as *mut c_void,
+                b"UTF-8\0".as_ptr() as *const c_char,
+                options as c_int,
+            );
+            if ctxt.is_null() {
+                return Err(io::Error::other("failed to create XML save context"));
+            }
+            bindings::xmlSaveDoc(ctxt, self.inner.doc_ptr());
+            bindings::xmlSaveClose(ctxt)
+        };
+        match sink.error {
+            Some(err) => Err(err),
+            None if status < 0 => Err(io::Error::other("XML serialisation failed")),
+            None => sink.writer.flush(),
+        }
+    }
 }
 
 impl fmt::Display for Document {
@@ -105,6 +460,64 @@ mod test {
         assert!(doc.is_ok());
     }
 
+    #[test]
+    fn identity_from_bytes() {
+        let src = std::fs::read("tests/en_US.xml").expect("LDML test file");
+        let doc = Document::from_bytes(&src).expect("LDML failed parse.");
+        assert_eq!(
+            doc.identity_tag().map(|t| t.to_string()).as_deref(),
+            Some("en-US")
+        );
+        assert!(Document::from_bytes(b"<ldml><identity>").is_err());
+    }
+
+    #[test]
+    fn validate_schema() {
+        let schema = super::Schema::new("tests/ldml-minimal.xsd".as_ref()).expect("schema");
+        let doc = Document::new("tests/en_US.xml").expect("LDML failed parse.");
+        assert_eq!(doc.validate_schema(&schema), Ok(()));
+
+        let doc = Document::from_bytes(b"<ldml><characters/></ldml>").expect("LDML");
+        let errors = doc.validate_schema(&schema).expect_err("no identity");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("identity"), "{errors:?}");
+        // Pooled validation contexts carry nothing over between documents.
+        let doc = Document::new("tests/en_US.xml").expect("LDML failed parse.");
+        assert_eq!(doc.validate_schema(&schema), Ok(()));
+
+        assert!(super::Schema::new("tests/missing.xsd".as_ref()).is_err());
+    }
+
+    #[test]
+    fn validate_file() {
+        let schema = super::Schema::new("tests/ldml-minimal.xsd".as_ref()).expect("schema");
+        let dir = tempfile::tempdir().expect("temp dir");
+        let path = dir.path().join("eka.xml");
+        std::fs::write(&path, "<ldml><layout/></ldml>").expect("LDML test file");
+        assert!(schema.validate_file(&path).is_err());
+        // The remembered result goes once the file changes size.
+        std::fs::write(
+            &path,
+            r#"<ldml><identity><language type="eka"/></identity><layout/></ldml>"#,
+        )
+        .expect("LDML test file");
+        assert_eq!(schema.validate_file(&path), Ok(()));
+        assert!(schema
+            .validate_file(&dir.path().join("missing.xml"))
+            .is_err());
+
+        // Each thread validating at once gets a context of its own.
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    let doc = Document::new("tests/en_US.xml").expect("LDML failed parse.");
+                    assert_eq!(doc.validate_schema(&schema), Ok(()));
+                    assert_eq!(schema.validate_file("tests/en_US.xml".as_ref()), Ok(()));
+                });
+            }
+        });
+    }
+
     #[test]
     fn find_revid() {
         let doc = Document::new("tests/en_US.xml").expect("LDML failed parse.");
@@ -118,7 +531,7 @@ mod test {
     #[test]
     fn update_uid() {
         let mut doc = Document::new("tests/en_US.xml").expect("LDML failed parse.");
-        doc.set_uid(012345678).expect("uid update failed.");
+        doc.set_uid(12345678).expect("uid update failed.");
         let uid = doc
             ._findvalue("//sil:identity/@uid")
             .expect("uid attribute not found.");
@@ -202,6 +615,80 @@ CLDR data files are interpreted according to the LDML specification (http://unic
         );
     }
 
+    #[test]
+    fn streamed_subset() {
+        let mut expected = Document::new("tests/en_US.xml").expect("LDML failed parse.");
+        expected
+            .subset(&["metadata", "layout"])
+            .expect("Subsetting failed");
+
+        let mut doc = Document::new("tests/en_US.xml").expect("LDML failed parse.");
+        let mut out = Vec::new();
+        doc.write_subset_to(&["metadata", "layout"], &mut out)
+            .expect("Streamed subsetting failed");
+
+        assert_eq!(String::from_utf8(out).unwrap(), expected.to_string());
+    }
+
+    #[test]
+    fn xpath_subsetting() {
+        let toplevel = |doc: &Document| {
+            doc.findnodes("/ldml/*")
+                .expect("top-level nodes")
+                .iter()
+                .map(|n| n.get_name())
+                .collect::<Vec<_>>()
+        };
+        let mut doc = Document::new("tests/en_US.xml").expect("LDML failed parse.");
+        doc.subset(&[
+            "layout",
+            "collations[collation/@type='eor']",
+            "numbers[@bogus]",
+        ])
+        .expect("Subsetting failed");
+        assert_eq!(toplevel(&doc), ["identity", "layout", "collations"]);
+
+        let mut doc = Document::new("tests/en_US.xml").expect("LDML failed parse.");
+        doc.subset(&["collations[not(contains(defaultCollation, 'standard'))]"])
+            .expect("Subsetting failed");
+        assert_eq!(toplevel(&doc), ["identity"]);
+    }
+
+    #[test]
+    fn xpath_checks() {
+        use super::{check_xpath, split_xpaths};
+
+        assert_eq!(
+            split_xpaths("layout,collations[contains(@a, 'x,y')],numbers"),
+            ["layout", "collations[contains(@a, 'x,y')]", "numbers"]
+        );
+        assert_eq!(split_xpaths("layout"), ["layout"]);
+
+        assert!(check_xpath("layout").is_ok());
+        assert!(check_xpath("collations[collation/@type='standard']").is_ok());
+        assert!(check_xpath("dates[starts-with(calendars/@x, 'g')]").is_ok());
+        assert!(check_xpath("").is_err());
+        assert!(check_xpath("//identity").is_err());
+        assert!(check_xpath("layout[..]").is_err());
+        assert!(check_xpath("layout | /ldml").is_err());
+        assert!(check_xpath("ancestor::ldml").is_err());
+        assert!(check_xpath("layout[document('/etc/passwd')]").is_err());
+        assert!(check_xpath("layout[system-property ('xsl:vendor')]").is_err());
+        assert!(check_xpath("layout[php:string(@x)]").is_err());
+        assert!(check_xpath("layout[fn:contains(@x, 'a')]").is_err());
+        assert!(check_xpath("numbers[@type='system(x)']").is_ok());
+        assert!(check_xpath("numbers[contains(@x, \"a(b\")]").is_ok());
+    }
+
+    #[test]
+    fn streamed_document() {
+        let doc = Document::new("tests/en_US.xml").expect("LDML failed parse.");
+        let mut out = Vec::new();
+        doc.write_to(&mut out).expect("Streaming failed");
+
+        assert_eq!(String::from_utf8(out).unwrap(), doc.to_string());
+    }
+
     #[test]
     fn find_identity() {
         let doc = Document::new("tests/en_US.xml").expect("LDML failed parse.");
@@ -210,7 +697,7 @@ CLDR data files are interpreted according to the LDML specification (http://unic
             .expect("Node not found");
         let res = silid
             .iter()
-            .map(|n| doc.inner.ronode_to_string(&n))
+            .map(|n| doc.inner.ronode_to_string(n))
             .collect::<Vec<_>>();
 
         assert_eq!(silid.len(), 2);
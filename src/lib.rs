@@ -1,56 +1,142 @@
 use axum::{
-    body::Body,
-    extract::{Extension, Path, Query, Request, State},
-    http::{header::CONTENT_DISPOSITION, HeaderMap, StatusCode},
+    body::{Body, Bytes},
+    error_handling::HandleErrorLayer,
+    extract::{DefaultBodyLimit, Extension, FromRequest, Multipart, Path, Query, Request, State},
+    handler::Handler,
+    http::{
+        header::{CONTENT_DISPOSITION, RETRY_AFTER},
+        HeaderMap, HeaderName, HeaderValue, StatusCode,
+    },
     middleware::{self, Next},
     response::{Html, IntoResponse, Redirect, Response},
     routing::get,
-    Router,
+    BoxError, Json, Router,
 };
 use axum_extra::headers::{ContentType, ETag, HeaderMapExt};
 use language_tag::Tag;
+use mime_guess::mime;
 use serde::Deserialize;
-use std::{collections::HashMap, io, iter, path, str, sync::Arc};
-use tokio::{fs, task};
-use tracing::instrument;
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    io, iter, path, str,
+    sync::{Arc, PoisonError, RwLock},
+};
+use tokio::{
+    fs,
+    sync::{mpsc, oneshot},
+    task,
+};
+use tokio_stream::wrappers::ReceiverStream;
+use tower::{limit::GlobalConcurrencyLimitLayer, load_shed::LoadShedLayer, ServiceBuilder};
+use tracing::{instrument, Instrument};
 
+pub mod admin;
+mod client_addr;
 pub mod config;
+#[cfg(debug_assertions)]
+mod debug_log;
 mod etag;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+mod health;
 mod ldml;
+#[cfg(feature = "otel")]
+pub mod otel;
+mod request_id;
 mod toggle;
 mod unique_id;
 
 /*
 /<ws_id>                => /<ws_id> [Accept:application/x.vnd.sil.ldml.v2+xml]
     [ext=<type>]        => [Accept: application/vnd.sil.ldml.v2+<type>...]
-    [flatten=<bool>]    => [flatten=<bool>]
-    [inc=<top>[,..]]    => [inc=<top>[,..]]
+    [flatten=<mode>]    => [flatten=<mode>]      <mode> is flat, unflat or a bool
+    [inc=<top>[,..]]    => [inc=<top>[,..]]      <top> is a name or a restricted XPath
     [revid=<etag>]      => [If-Not-Match: <etag>][Accept: application/vnd.sil.ldml.v2+<type>...]
     [uid=<uuid>]        => [uid=<uuid>]
     [staging=<bool>]    => [Accept: application/vnd.sil.ldml.v2+<type>+staging,...]
 /?query=langtags[&ext=<type>]           => /langtags [Accept: application/vnd.sil.ldml.v2+<type>...]
+/langtags.csv                           => the langtags database as CSV, one row per tagset
 /<ws_id>?query=tags[&ext=<type>]        => /tagset/<ws_id> [Accept: application/vnd.sil.ldml.v2+txt]
+/<ws_id>?query=info                     => JSON metadata from the tagset <ws_id> belongs to
 /?ws_id=<ws_id>                         => /<ws_id> [Accept:application/x.vnd.sil.ldml.v2+xml]
+/?query=conformance&tag=<tag>           => JSON conformance report for <tag>, also /<tag>?query=conformance
+POST /<ws_id>[inc=..][uid=..] <ldml>    => normalised <ldml>, identity must match <ws_id>
+                                           <ldml> is the body, or the first file of a form
+/health/live, /health/ready             => liveness and readiness probes, JSON status
 */
 
-use config::{Config, Profiles};
+use client_addr::ClientAddr;
+use config::{find_ldml_file, Config, EtagMethod, FlattenMode, Profiles, SharedProfiles};
+pub use etag::clear_content_hashes;
 use langtags::json::LangTags;
+use request_id::RequestId;
+pub use request_id::X_REQUEST_ID;
 use toggle::Toggle;
 use unique_id::UniqueID;
 
 pub fn app(cfg: Profiles) -> io::Result<Router> {
+    app_with_shared_profiles(Arc::new(RwLock::new(cfg)), None)
+}
+
+// As app, but serving profiles which may be swapped out while running, and
+// the admin API for doing so when given its token.
+pub fn app_with_shared_profiles(
+    cfg: SharedProfiles,
+    admin_token: Option<String>,
+) -> io::Result<Router> {
+    // Conditional requests only make sense for GET and HEAD. Uploads are held
+    // to their profile's max_upload_bytes instead of axum's default body limit.
+    let fetch = demux_writing_system
+        .layer(middleware::from_fn(etag::layer))
+        .layer(middleware::from_fn(etag::revid::converter));
+    let writing_system = get(fetch).post(upload_writing_system.layer(DefaultBodyLimit::disable()));
+    // Not on /langtags.:ext, whose bodies are too large to be worth buffering.
+    #[cfg(debug_assertions)]
+    let writing_system = writing_system.layer(middleware::from_fn(debug_log::layer));
+    let admin = match admin_token {
+        Some(token) => admin::routes(cfg.clone(), token),
+        None => Router::new(),
+    };
     Ok(Router::new()
-        .route("/langtags.:ext", get(langtags))
         .route(
-            "/:ws_id",
-            get(demux_writing_system)
-                .layer(middleware::from_fn(etag::layer))
-                .layer(middleware::from_fn(etag::revid::converter)),
+            "/langtags.csv",
+            get(langtags_csv).layer(middleware::from_fn(etag::layer)),
         )
-        .layer(middleware::from_fn_with_state(cfg.into(), profile_selector))
+        .route("/langtags.:ext", get(langtags))
+        .route("/:ws_id", writing_system)
         .route("/", get(query_only))
         .route("/index.html", get(query_only))
-        .fallback(query_only))
+        .fallback(query_only)
+        .layer(middleware::from_fn_with_state(
+            cfg.clone(),
+            profile_selector,
+        ))
+        .route("/health/live", get(health::live))
+        .route("/health/ready", get(health::ready).with_state(cfg))
+        .merge(admin)
+        .layer(middleware::from_fn(client_addr::layer))
+        .layer(middleware::from_fn(request_id::layer)))
+}
+
+// Cap the number of requests being handled at once across every route,
+// shedding any excess immediately with a 503 rather than queueing it.
+pub fn limit_concurrency(app: Router, max_requests: usize) -> Router {
+    app.layer(
+        ServiceBuilder::new()
+            .layer(HandleErrorLayer::new(overloaded))
+            .layer(LoadShedLayer::new())
+            .layer(GlobalConcurrencyLimitLayer::new(max_requests)),
+    )
+}
+
+async fn overloaded(err: BoxError) -> impl IntoResponse {
+    tracing::warn!("shedding request: {err}");
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(RETRY_AFTER, "1")],
+        "LDML SERVER ERROR: Too many concurrent requests, please retry.",
+    )
 }
 
 async fn static_help() -> impl IntoResponse {
@@ -58,39 +144,57 @@ async fn static_help() -> impl IntoResponse {
 }
 
 async fn profile_selector(
-    State(profiles): State<Box<Profiles>>,
+    State(profiles): State<SharedProfiles>,
     mut req: Request,
     next: Next,
 ) -> Response {
-    let config = req
-        .uri()
-        .query()
-        .and_then(|q| serde_urlencoded::from_str::<HashMap<String, Toggle>>(q).ok())
-        .and_then(|qs| {
-            profiles
-                .iter()
-                .find_map(|(k, v)| qs.get(k).and_then(|&t| if *t { Some(v) } else { None }))
-        })
-        .unwrap_or_else(|| &profiles[""])
-        .clone();
+    let config = {
+        let profiles = profiles.read().unwrap_or_else(PoisonError::into_inner);
+        req.uri()
+            .query()
+            .and_then(|q| serde_urlencoded::from_str::<HashMap<String, Toggle>>(q).ok())
+            .and_then(|qs| {
+                profiles
+                    .iter()
+                    .find_map(|(k, v)| qs.get(k).filter(|t| t.is_on()).map(|_| v))
+            })
+            .unwrap_or_else(|| &profiles[""])
+            .clone()
+    };
 
+    let span = tracing::info_span!(
+        "request",
+        request_id = %req.extensions().get::<RequestId>().map_or("", RequestId::as_str),
+        client = req.extensions().get::<ClientAddr>().map(tracing::field::display),
+    );
+    #[cfg(feature = "otel")]
+    otel::set_remote_parent(&span, req.headers());
     req.extensions_mut().insert(config);
-    next.run(req).await
+    next.run(req).instrument(span).await
 }
 
-async fn stream_file(path: &path::Path) -> Result<impl IntoResponse, Response> {
+async fn stream_file(
+    path: &path::Path,
+    sendfile: Option<&str>,
+    etag_method: EtagMethod,
+) -> Result<impl IntoResponse, Response> {
     let attachment: &path::Path = path
         .file_name()
         .ok_or_else(|| (StatusCode::BAD_REQUEST, String::default()).into_response())?
         .as_ref();
-    stream_file_as(path, attachment).await
+    stream_file_as(path, attachment, sendfile, etag_method).await
 }
 
+// Respond with the file at path as an attachment named filename. When a
+// sendfile method is configured the body is left empty and the header it
+// names carries the file's absolute path, for the front end proxy to serve.
 #[instrument]
 async fn stream_file_as(
     path: &path::Path,
     filename: &path::Path,
-) -> Result<impl IntoResponse, Response> {
+    sendfile: Option<&str>,
+    etag_method: EtagMethod,
+) -> Result<Response, Response> {
     let mime = mime_guess::from_path(filename).first_or_octet_stream();
     let disposition = format!(
         "attachment; filename=\"{name}\"",
@@ -101,7 +205,7 @@ async fn stream_file_as(
     let mut headers = HeaderMap::new();
     headers.typed_insert(ContentType::from(mime));
     headers.insert(CONTENT_DISPOSITION, disposition);
-    let file = fs::File::open(path).await.map_err(|err| {
+    let not_found = |err: io::Error| {
         (
             StatusCode::NOT_FOUND,
             format!(
@@ -110,13 +214,27 @@ async fn stream_file_as(
             ),
         )
             .into_response()
-    })?;
-    if let Some(etag) = etag::from_metadata(path) {
+    };
+    if let Some(etag) = etag::from_file(path, etag_method).await {
         headers.typed_insert(etag);
     }
+    if let Some(method) = sendfile {
+        let path = fs::canonicalize(path).await.map_err(not_found)?;
+        match (
+            HeaderName::try_from(method),
+            HeaderValue::try_from(path.to_string_lossy().as_ref()),
+        ) {
+            (Ok(name), Ok(value)) => {
+                headers.insert(name, value);
+                return Ok((headers, Body::empty()).into_response());
+            }
+            _ => tracing::warn!("Cannot send {path:?} with {method}, streaming it instead"),
+        }
+    }
+    let file = fs::File::open(path).await.map_err(not_found)?;
     let stream = tokio_util::io::ReaderStream::with_capacity(file, 1 << 14); // 16KiB buffer
 
-    Ok((headers, Body::from_stream(stream)))
+    Ok((headers, Body::from_stream(stream)).into_response())
 }
 
 async fn langtags(
@@ -124,13 +242,63 @@ async fn langtags(
     Extension(cfg): Extension<Arc<Config>>,
 ) -> impl IntoResponse {
     tracing::debug!("langtags.{ext}");
-    stream_file(&cfg.langtags_dir.join("langtags").with_extension(ext)).await
+    stream_file(
+        &cfg.langtags_dir.join("langtags").with_extension(ext),
+        cfg.sendfile_method.as_deref(),
+        cfg.etag_method,
+    )
+    .await
+}
+
+// The loaded langtags database as CSV. There is no langtags.csv on disk, so
+// it is generated off the async runtime the first time each database loaded
+// is asked for, and kept with that database until it is replaced.
+async fn langtags_csv(Extension(cfg): Extension<Arc<Config>>) -> Result<Response, Response> {
+    let langtags = cfg.langtags.load_full();
+    let (csv, etag) = match langtags.csv.get() {
+        Some(cached) => cached.clone(),
+        None => {
+            let generated = task::spawn_blocking({
+                let langtags = langtags.clone();
+                move || {
+                    let mut csv = Vec::new();
+                    langtags.to_csv_writer(&mut csv).map(|_| csv)
+                }
+            })
+            .await;
+            let failed = |err: &dyn std::fmt::Display| {
+                tracing::error!("Generating langtags.csv failed: {err}");
+                (StatusCode::INTERNAL_SERVER_ERROR, String::default()).into_response()
+            };
+            let csv = match generated {
+                Ok(Ok(csv)) => Bytes::from(csv),
+                Ok(Err(err)) => return Err(failed(&err)),
+                Err(err) => return Err(failed(&err)),
+            };
+            let etag = etag::from_content(&csv);
+            // Requests racing to generate it all produce the same CSV.
+            langtags.csv.get_or_init(|| (csv, etag)).clone()
+        }
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.typed_insert(ContentType::from(mime::TEXT_CSV_UTF_8));
+    headers.insert(
+        CONTENT_DISPOSITION,
+        HeaderValue::from_static("attachment; filename=\"langtags.csv\""),
+    );
+    if let Some(etag) = etag {
+        headers.typed_insert(etag);
+    }
+    Ok((headers, csv).into_response())
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "lowercase")]
 enum LDMLQuery {
     AllTags,
+    Conformance,
+    Info,
     LangTags,
     Tags,
 }
@@ -141,10 +309,14 @@ struct QueryParams {
     query: Option<LDMLQuery>,
     ext: Option<String>,
     staging: Option<Toggle>,
+    tag: Option<String>,
 }
 
-#[instrument(ret)]
-async fn query_only(Query(params): Query<QueryParams>) -> impl IntoResponse {
+#[instrument(ret, skip(cfg))]
+async fn query_only(
+    Query(params): Query<QueryParams>,
+    Extension(cfg): Extension<Arc<Config>>,
+) -> impl IntoResponse {
     match params.query {
         Some(LDMLQuery::AllTags) => Err((
             StatusCode::NOT_FOUND,
@@ -153,7 +325,7 @@ async fn query_only(Query(params): Query<QueryParams>) -> impl IntoResponse {
         Some(LDMLQuery::LangTags) => {
             let ext = params.ext.as_deref().unwrap_or("txt");
             let mut target = format!("/langtags.{ext}");
-            if *params.staging.unwrap_or_default() {
+            if params.staging.unwrap_or_default().as_bool() {
                 target += "?staging=1";
             }
             Ok(Redirect::permanent(&target).into_response())
@@ -162,15 +334,48 @@ async fn query_only(Query(params): Query<QueryParams>) -> impl IntoResponse {
             StatusCode::BAD_REQUEST,
             "LDML SERVER ERROR: query=tags requires a ws_id",
         )),
+        Some(LDMLQuery::Info) => Err((
+            StatusCode::BAD_REQUEST,
+            "LDML SERVER ERROR: query=info requires a ws_id",
+        )),
+        Some(LDMLQuery::Conformance) => {
+            let tag = params.tag.as_deref().ok_or((
+                StatusCode::BAD_REQUEST,
+                "LDML SERVER ERROR: query=conformance requires a tag",
+            ))?;
+            let tag = tag.parse::<Tag>().map_err(|_| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    "LDML SERVER ERROR: query=conformance tag is malformed",
+                )
+            })?;
+            Ok(conformance(&tag, &cfg.langtags.load()).into_response())
+        }
         None => Ok(static_help().await.into_response()),
     }
 }
 
+// Whether tag conforms to the langtags database, with its canonical form if
+// so, or what is wrong with it if not.
+fn conformance(tag: &Tag, langtags: &LangTags) -> Json<serde_json::Value> {
+    let issues = langtags.why_not_conformant(tag);
+    Json(if issues.is_empty() {
+        let canonical = langtags.orthographic_normal_form(tag).map_or_else(
+            || tag.display_canonical().to_string(),
+            |ts| ts.full.to_string(),
+        );
+        json!({ "conformant": true, "canonical": canonical })
+    } else {
+        let issues: Vec<_> = issues.iter().map(ToString::to_string).collect();
+        json!({ "conformant": false, "issues": issues })
+    })
+}
+
 #[derive(Debug, Deserialize)]
 struct WSParams {
     query: Option<LDMLQuery>,
     ext: Option<String>,
-    flatten: Option<Toggle>,
+    flatten: Option<FlattenMode>,
     #[serde(rename = "inc[]")]
     inc: Option<String>,
     uid: Option<UniqueID>,
@@ -178,7 +383,7 @@ struct WSParams {
 
 #[instrument(skip(cfg))]
 async fn writing_system_tags(ws: &Tag, cfg: &Config) -> impl IntoResponse {
-    query_tags(ws, &cfg.langtags).ok_or_else(|| {
+    query_tags(ws, &cfg.langtags.load()).ok_or_else(|| {
         (
             StatusCode::NOT_FOUND,
             format!("No tagsets found for tag: {ws}"),
@@ -186,24 +391,72 @@ async fn writing_system_tags(ws: &Tag, cfg: &Config) -> impl IntoResponse {
     })
 }
 
+#[instrument(skip(cfg))]
+async fn writing_system_info(ws: &Tag, cfg: &Config) -> impl IntoResponse {
+    let langtags = cfg.langtags.load();
+    let ts = langtags.orthographic_normal_form(ws).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            format!("No tagsets found for tag: {ws}"),
+        )
+    })?;
+    let mut info = json!({
+        "full": ts.full,
+        "tag": ts.tag,
+        "name": ts.name,
+        "localname": ts.localname,
+        "iso639_3": ts.iso639_3,
+        "windows": ts.windows,
+        "sldr": ts.sldr,
+        "regions": ts.regions,
+        "script": ts.script(),
+        "region": ts.region(),
+    });
+    if let Some(rod) = ts.rod_code() {
+        info["rod"] = rod.into();
+    }
+    Ok::<_, (StatusCode, String)>(Json(info))
+}
+
 #[instrument(skip(cfg))]
 async fn fetch_writing_system_ldml(ws: &Tag, params: WSParams, cfg: &Config) -> impl IntoResponse {
     let ext = params.ext.as_deref().unwrap_or("xml");
-    let flatten = *params.flatten.unwrap_or(Toggle::ON);
+    let flatten = params.flatten.unwrap_or_default();
 
     tracing::debug!(
         "find writing system in {path} with {params:?}",
         path = cfg.sldr_path(flatten).to_string_lossy()
     );
-    let path = find_ldml_file(ws, &cfg.sldr_path(flatten), &cfg.langtags)
-        .ok_or_else(|| (StatusCode::NOT_FOUND, format!("No LDML for {ws}")).into_response())?;
-    let etag = etag::revid::from_ldml(&path).or_else(|| etag::from_metadata(&path));
+    let langtags = cfg.langtags.load();
+    let path = find_ldml_file(ws, &cfg.sldr_path(flatten), &langtags).ok_or_else(|| {
+        let msg = match langtags.orthographic_normal_form(ws) {
+            Some(ts) if !ts.sldr => {
+                format!("No LDML available for {} (not in SLDR)", ts.full)
+            }
+            _ => format!("No LDML for {ws}"),
+        };
+        (StatusCode::NOT_FOUND, msg).into_response()
+    })?;
+    let etag = match etag::revid::from_ldml(&path) {
+        Some(revid) => Some(revid),
+        None => etag::from_file(&path, cfg.etag_method).await,
+    };
     let mut headers = HeaderMap::new();
 
     if let Some(tag) = etag {
         headers.typed_insert(tag);
     }
     if params.inc.is_none() && params.uid.is_none() {
+        // Files served as they are still have to conform to the schema. Each
+        // is checked the first time it is served, and again only once it
+        // changes on disk.
+        if let Some(schema) = cfg.schema.clone() {
+            let path = path.clone();
+            task::spawn_blocking(move || conform_file(&schema, &path))
+                .await
+                .unwrap_or(Err((StatusCode::INTERNAL_SERVER_ERROR, String::default())))
+                .map_err(IntoResponse::into_response)?;
+        }
         stream_file_as(
             path.as_ref(),
             path.with_extension(ext)
@@ -216,14 +469,15 @@ async fn fetch_writing_system_ldml(ws: &Tag, params: WSParams, cfg: &Config) ->
                         .into_response()
                 })?
                 .as_ref(),
+            cfg.sendfile_method.as_deref(),
+            cfg.etag_method,
         )
         .await
-        .map(IntoResponse::into_response)
     } else {
         if let Some(etag) = headers.typed_get::<ETag>() {
             headers.typed_insert(etag::weaken(etag))
         }
-        ldml_customisation(path.as_ref(), params.inc, params.uid)
+        ldml_customisation(path.as_ref(), cfg.schema.clone(), params.inc, params.uid)
             .await
             .map(IntoResponse::into_response)
     }
@@ -245,6 +499,8 @@ async fn demux_writing_system(
             )
                 .into_response(),
             LDMLQuery::Tags => writing_system_tags(&ws, &cfg).await.into_response(),
+            LDMLQuery::Info => writing_system_info(&ws, &cfg).await.into_response(),
+            LDMLQuery::Conformance => conformance(&ws, &cfg.langtags.load()).into_response(),
         }
     } else {
         fetch_writing_system_ldml(&ws, params, &cfg)
@@ -255,53 +511,279 @@ async fn demux_writing_system(
 
 #[instrument(skip(langtags))]
 fn query_tags(ws: &Tag, langtags: &LangTags) -> Option<String> {
+    equivalence_sets(ws, langtags)?.reduce(|resp, ref set| resp + "\n" + set)
+}
+
+// The tagset for ws followed by its region and variant sets, one per line of
+// a ?query=tags response.
+fn equivalence_sets<'a>(
+    ws: &Tag,
+    langtags: &'a LangTags,
+) -> Option<impl Iterator<Item = String> + 'a> {
     use langtags::tagset::render_equivalence_set;
 
     let tagset = langtags.orthographic_normal_form(ws)?;
     let regionsets = tagset.region_sets().map(render_equivalence_set);
     let variantsets = tagset.variant_sets().map(render_equivalence_set);
-    iter::once(tagset.to_string())
-        .chain(regionsets)
-        .chain(variantsets)
-        .reduce(|resp, ref set| resp + "\n" + set)
+    Some(
+        iter::once(render_equivalence_set(tagset.iter_by_specificity()))
+            .chain(regionsets)
+            .chain(variantsets),
+    )
 }
 
-#[instrument(ret, skip(langtags))]
-fn find_ldml_file(ws: &Tag, sldr_dir: &path::Path, langtags: &LangTags) -> Option<path::PathBuf> {
-    // Lookup the tag set and generate a prefered sorted list.
-    let tagset = langtags.orthographic_normal_form(ws)?;
-    let tags: Vec<_> = tagset.iter().collect();
+#[instrument]
+async fn ldml_customisation(
+    path: &path::Path,
+    schema: Option<Arc<ldml::Schema>>,
+    xpaths: Option<String>,
+    uid: Option<UniqueID>,
+) -> Result<impl IntoResponse, Response> {
+    let path = path.to_owned();
+    let load = move || {
+        if let Some(schema) = &schema {
+            conform_file(schema, &path)?;
+        }
+        ldml::Document::new(&path).map_err(|err| {
+            tracing::error!("Loading {path:?} failed: {err}");
+            (StatusCode::INTERNAL_SERVER_ERROR, String::default())
+        })
+    };
+    customise_ldml(load, None, xpaths, uid).await
+}
 
-    let mut path = sldr_dir.to_path_buf();
-    path.push(&tagset.lang()[0..1]);
+fn nonconformant(errs: Vec<String>, status: StatusCode) -> (StatusCode, String) {
+    let msg = format!("LDML does not conform to its schema:\n{}", errs.join("\n"));
+    if status.is_server_error() {
+        tracing::error!("{msg}");
+    }
+    (status, msg)
+}
 
-    tags.iter()
-        .map(|&tag| {
-            let mut path = path.clone();
-            path.push(tag.to_string().replace('-', "_"));
-            path.with_extension("xml")
-        })
-        .rfind(|path| path.exists())
+// Check doc conforms to schema, failing with status if not.
+fn conform(
+    doc: &ldml::Document,
+    schema: &ldml::Schema,
+    status: StatusCode,
+) -> Result<(), (StatusCode, String)> {
+    ldml::timed(
+        tracing::info_span!("ldml_validate", schema = ?schema.path()),
+        || doc.validate_schema(schema),
+    )
+    .map_err(|errs| nonconformant(errs, status))
 }
 
-#[instrument]
-async fn ldml_customisation(
-    path: &path::Path,
+// Check the stored file at path conforms to schema, reusing the result of an
+// earlier check while the file is unchanged. A stored file that does not
+// conform is our problem rather than the client's, so it is a logged server
+// error.
+fn conform_file(schema: &ldml::Schema, path: &path::Path) -> Result<(), (StatusCode, String)> {
+    ldml::timed(
+        tracing::info_span!("ldml_validate", schema = ?schema.path(), ?path),
+        || schema.validate_file(path),
+    )
+    .map_err(|errs| nonconformant(errs, StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+// Parse, check and customise an LDML document off the async runtime. When a
+// schema is given the document must conform to it before anything else is
+// done to it, failing with the status paired with it.
+async fn customise_ldml<F>(
+    load: F,
+    schema: Option<(Arc<ldml::Schema>, StatusCode)>,
     xpaths: Option<String>,
     uid: Option<UniqueID>,
+) -> Result<impl IntoResponse, Response>
+where
+    F: FnOnce() -> Result<ldml::Document, (StatusCode, String)> + Send + 'static,
+{
+    if let Some(xpaths) = &xpaths {
+        ldml::split_xpaths(xpaths)
+            .into_iter()
+            .try_for_each(ldml::check_xpath)
+            .map_err(|err| (StatusCode::BAD_REQUEST, err).into_response())?;
+    }
+    let (ready, prepared) = oneshot::channel();
+    let (tx, rx) = mpsc::channel(8);
+    // libxml documents cannot cross threads, so the whole parse, customise
+    // and serialise pipeline lives on one blocking thread which hands output
+    // back through the channel as it is written.
+    let span = tracing::Span::current();
+    task::spawn_blocking(move || {
+        let _request = span.enter();
+        let prepare = || -> Result<_, (StatusCode, String)> {
+            let mut doc = ldml::timed(tracing::info_span!("ldml_parse"), load)?;
+            if let Some((schema, status)) = schema {
+                conform(&doc, &schema, status)?;
+            }
+            if let Some(uid) = uid {
+                ldml::timed(tracing::info_span!("ldml_set_uid", ?uid), || {
+                    doc.set_uid(uid)
+                })
+                .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err))?;
+            }
+            Ok(doc)
+        };
+        let mut doc = match prepare() {
+            Ok(doc) => doc,
+            Err(err) => {
+                let _ = ready.send(Err(err));
+                return;
+            }
+        };
+        // The response status is only settled by the first chunk written, so
+        // a subsetting failure, which happens before any output, still gets
+        // an error status rather than a truncated 200.
+        let mut writer = ChannelWriter {
+            tx,
+            ready: Some(ready),
+        };
+        let result = match xpaths {
+            Some(xpaths) => doc.write_subset_to(&ldml::split_xpaths(&xpaths), &mut writer),
+            None => ldml::timed(tracing::info_span!("ldml_serialise"), || {
+                doc.write_to(&mut writer)
+            }),
+        };
+        if let Err(err) = result {
+            tracing::error!("LDML customisation failed: {err}");
+            match writer.ready.take() {
+                Some(ready) => {
+                    let status = match err.kind() {
+                        io::ErrorKind::InvalidData => StatusCode::BAD_REQUEST,
+                        _ => StatusCode::INTERNAL_SERVER_ERROR,
+                    };
+                    let _ = ready.send(Err((status, err.to_string())));
+                }
+                None => {
+                    let _ = writer.tx.blocking_send(Err(err));
+                }
+            }
+        } else if let Some(ready) = writer.ready.take() {
+            let _ = ready.send(Ok(()));
+        }
+    });
+    prepared
+        .await
+        .unwrap_or(Err((StatusCode::INTERNAL_SERVER_ERROR, String::default())))
+        .map_err(IntoResponse::into_response)?;
+
+    let mut headers = HeaderMap::new();
+    headers.typed_insert(ContentType::text_utf8());
+    Ok((headers, Body::from_stream(ReceiverStream::new(rx))))
+}
+
+// Accept an LDML document for ws_id, returning it normalised and customised
+// by the same parameters, and in the same form, as a GET would.
+#[instrument(skip(cfg, req))]
+async fn upload_writing_system(
+    Path(ws): Path<Tag>,
+    Query(params): Query<WSParams>,
+    Extension(cfg): Extension<Arc<Config>>,
+    req: Request,
 ) -> Result<impl IntoResponse, Response> {
-    task::block_in_place(|| {
-        let mut doc = ldml::Document::new(path)
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
-        if let Some(xpaths) = xpaths {
-            let xpaths = xpaths.split(',').collect::<Vec<_>>();
-            doc.subset(&xpaths)
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+    let mime = req
+        .headers()
+        .typed_get::<ContentType>()
+        .map(mime::Mime::from);
+    let is_xml = mime
+        .as_ref()
+        .is_some_and(|m| m.subtype() == mime::XML || m.suffix() == Some(mime::XML));
+    let is_form = mime
+        .as_ref()
+        .is_some_and(|m| m.type_() == mime::MULTIPART && m.subtype() == mime::FORM_DATA);
+    if !is_xml && !is_form {
+        return Err((
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "LDML uploads must be sent as application/xml or multipart/form-data.",
+        )
+            .into_response());
+    }
+    let limit = cfg.max_upload_bytes;
+    let (parts, body) = req.into_parts();
+    let mut src = axum::body::to_bytes(body, limit).await.map_err(|_| {
+        (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("LDML uploads are limited to {limit} bytes."),
+        )
+            .into_response()
+    })?;
+    if is_form {
+        src = first_file_part(Request::from_parts(parts, Body::from(src))).await?;
+    }
+
+    let schema = cfg
+        .schema
+        .clone()
+        .map(|schema| (schema, StatusCode::BAD_REQUEST));
+    let load = move || {
+        let doc = ldml::Document::from_bytes(&src)
+            .map_err(|err| (StatusCode::BAD_REQUEST, format!("Malformed LDML: {err}")))?;
+        let identity = doc.identity_tag().ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                "LDML identity has no language".to_string(),
+            )
+        })?;
+        let langtags = cfg.langtags.load();
+        let same_tagset = match (
+            langtags.orthographic_normal_form(&identity),
+            langtags.orthographic_normal_form(&ws),
+        ) {
+            (Some(a), Some(b)) => std::ptr::eq(a, b),
+            _ => identity == ws,
+        };
+        if !same_tagset {
+            return Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("LDML identity {identity} does not match {ws}"),
+            ));
+        }
+        Ok(doc)
+    };
+    customise_ldml(load, schema, params.inc, params.uid).await
+}
+
+// The content of the first file in a multipart/form-data upload, any other
+// form fields are ignored.
+async fn first_file_part(req: Request) -> Result<Bytes, Response> {
+    let mut form = Multipart::from_request(req, &())
+        .await
+        .map_err(IntoResponse::into_response)?;
+    while let Some(field) = form
+        .next_field()
+        .await
+        .map_err(IntoResponse::into_response)?
+    {
+        if field.file_name().is_some() {
+            return field.bytes().await.map_err(IntoResponse::into_response);
         }
-        if let Some(uid) = uid {
-            doc.set_uid(*uid)
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+    }
+    Err((
+        StatusCode::BAD_REQUEST,
+        "No file found in the uploaded form.",
+    )
+        .into_response())
+}
+
+struct ChannelWriter {
+    tx: mpsc::Sender<io::Result<Vec<u8>>>,
+    ready: Option<oneshot::Sender<Result<(), (StatusCode, String)>>>,
+}
+
+impl io::Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(ready) = self.ready.take() {
+            ready
+                .send(Ok(()))
+                .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
         }
-        Ok(doc.to_string())
-    })
+        self.tx
+            .blocking_send(Ok(buf.to_vec()))
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
 }
@@ -1,15 +1,30 @@
-use std::{io, net::SocketAddr, ops::Not, path};
+use std::{
+    io,
+    net::SocketAddr,
+    ops::Not,
+    path,
+    sync::{mpsc, Arc, PoisonError, RwLock},
+    time::Duration,
+};
 
-use clap::Parser;
-use ldml_api::{app, config};
+use clap::{Parser, Subcommand, ValueEnum};
+use ldml_api::{
+    app_with_shared_profiles, clear_content_hashes,
+    config::{self, FlattenMode, SharedProfiles},
+    limit_concurrency,
+};
+use notify::RecursiveMode;
 use tokio::net::TcpListener;
 use tower_http::{compression::CompressionLayer, trace::TraceLayer};
+#[cfg(feature = "otel")]
+use tracing_subscriber::reload;
+use tracing_subscriber::{fmt::writer::BoxMakeWriter, prelude::*, EnvFilter};
 
 #[derive(Debug, Parser)]
 #[clap(author, version, about)]
 struct Args {
     #[clap(long, default_value = "/etc/ldml-api.json")]
-    /// Path to config file
+    /// Path to config file, either JSON or, with a .toml extension, TOML
     config: path::PathBuf,
 
     #[clap(long, default_value = "production")]
@@ -18,52 +33,226 @@ struct Args {
 
     #[clap(short, long, default_value = "0.0.0.0:3000")]
     listen: SocketAddr,
+
+    #[cfg(feature = "grpc")]
+    #[clap(long)]
+    /// Also serve the gRPC interface, see proto/ldml.proto, at this address,
+    /// overriding the config's grpc_listen
+    grpc_listen: Option<SocketAddr>,
+
+    #[clap(long)]
+    /// Maximum number of requests handled at once, excess requests get a 503
+    max_connections: Option<usize>,
+
+    #[clap(long)]
+    /// Check every tag with SLDR data has an LDML file, then exit
+    check: bool,
+
+    #[clap(long, requires = "check")]
+    /// With --check, also check the unflat SLDR tree and report LDML files
+    /// for tags the langtags database does not know
+    strict: bool,
+
+    #[clap(long, visible_alias = "dry-run")]
+    /// Load the config, its langtags.json files and SLDR directories, then
+    /// exit with status 1 if any of them fail
+    validate: bool,
+
+    #[clap(long)]
+    /// As --validate, but also fail if any tag with SLDR data has no LDML file
+    validate_strict: bool,
+
+    #[clap(long)]
+    /// Reload langtags.json and log SLDR changes as they happen on disk
+    watch: bool,
+
+    #[cfg(feature = "otel")]
+    #[clap(long)]
+    /// Export traces over OTLP/gRPC to this endpoint, eg.
+    /// http://localhost:4317, overriding the config's otel_endpoint
+    otel_endpoint: Option<String>,
+
+    #[clap(long, env = "LDML_ADMIN_TOKEN", hide_env_values = true)]
+    /// Serve the admin API, for adding and removing profiles while running,
+    /// to requests bearing this token. Prefer setting it in the environment,
+    /// where other users cannot read it as they can the command line.
+    /// Profiles added this way are not watched by --watch
+    admin_token: Option<String>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Write the langtags database loaded for a profile to stdout
+    DumpLangtags {
+        #[clap(long)]
+        /// Profile to dump, defaults to the --profile argument
+        profile: Option<String>,
+
+        #[clap(long, value_enum, default_value_t = DumpFormat::Json)]
+        /// Output format
+        format: DumpFormat,
+
+        #[clap(long)]
+        /// Only include tagsets with an SLDR entry
+        only_sldr: bool,
+    },
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum DumpFormat {
+    Json,
+    Txt,
 }
 
 #[tokio::main]
 async fn main() -> io::Result<()> {
+    let args = Args::parse();
+
+    // Subcommands write their output to stdout, so keep logging out of it.
+    let log_writer = if args.command.is_some() {
+        BoxMakeWriter::new(io::stderr)
+    } else {
+        BoxMakeWriter::new(io::stdout)
+    };
     //console_subscriber::init();
     // Set the RUST_LOG, if it hasn't been explicitly defined
-    if cfg!(debug_assertions) && std::env::var_os("RUST_LOG").is_none() {
-        tracing_subscriber::fmt()
-            .with_env_filter(concat!(
-                env!("CARGO_CRATE_NAME"),
-                "=debug,tower_http=debug,axum::rejection=trace"
-            ))
-            .init();
+    let filter = if cfg!(debug_assertions) && std::env::var_os("RUST_LOG").is_none() {
+        EnvFilter::new(concat!(
+            env!("CARGO_CRATE_NAME"),
+            "=debug,tower_http=debug,axum::rejection=trace"
+        ))
     } else {
-        tracing_subscriber::fmt::init();
-    }
+        EnvFilter::from_default_env()
+    };
+    // The OTLP exporter's endpoint may come from the config, which is loaded
+    // after logging starts, so its layer is slotted in later.
+    #[cfg(feature = "otel")]
+    let (otel_layer, otel_handle) = reload::Layer::new(None::<ldml_api::otel::Layer>);
+    let subscriber = tracing_subscriber::registry();
+    #[cfg(feature = "otel")]
+    let subscriber = subscriber.with(otel_layer);
+    subscriber
+        .with(tracing_subscriber::fmt::layer().with_writer(log_writer))
+        .with(filter)
+        .init();
 
-    let args = Args::parse();
+    if args.validate || args.validate_strict {
+        std::process::exit(validate_config(&args));
+    }
 
     // Load configuraion
     let cfg =
-        config::profiles::from(&args.config, &args.profile).unwrap_or_else(|err: io::Error| {
+        config::profiles::from(&args.config, &args.profile).unwrap_or_else(|err: config::Error| {
             tracing::error!(
                 "Error loading config: {file}: {message}",
                 file = args.config.to_string_lossy(),
                 message = err.to_string()
             );
-            std::process::exit(err.raw_os_error().unwrap_or_default());
+            std::process::exit(err.raw_os_error().unwrap_or(1));
         });
-    tracing::info!(
-        "loaded profiles: {profiles}",
-        profiles = cfg
-            .keys()
-            .filter_map(|p| p.is_empty().not().then_some(p.as_ref()))
-            .collect::<Vec<_>>()
-            .join(", ")
-    );
+    let settings = config::settings::from(&args.config).unwrap_or_else(|err| {
+        tracing::error!(
+            "Error loading config: {file}: {err}",
+            file = args.config.to_string_lossy()
+        );
+        std::process::exit(err.raw_os_error().unwrap_or(1));
+    });
+    #[cfg(not(feature = "grpc"))]
+    if settings.grpc_listen.is_some() {
+        tracing::warn!("grpc_listen is set, but this build lacks the grpc feature");
+    }
+    #[cfg(not(feature = "otel"))]
+    if settings.otel_endpoint.is_some() {
+        tracing::warn!("otel_endpoint is set, but this build lacks the otel feature");
+    }
+    if let Some(Command::DumpLangtags {
+        profile,
+        format,
+        only_sldr,
+    }) = &args.command
+    {
+        let name = profile.as_deref().unwrap_or(&args.profile);
+        let Some(profile) = cfg.get(name) else {
+            tracing::error!("Unknown profile: {name}");
+            std::process::exit(1);
+        };
+        return dump_langtags(&profile.langtags.load(), *format, *only_sldr);
+    }
+
+    if args.check && args.strict {
+        std::process::exit(check_strict(&cfg));
+    }
+    if args.check {
+        let missing = config::profiles::validate_sldr_integrity(&cfg);
+        for (tag, path) in &missing {
+            tracing::error!("No LDML for {tag}: expected {path}", path = path.display());
+        }
+        tracing::info!("{count} tags missing LDML files", count = missing.len());
+        std::process::exit(missing.is_empty().not().into());
+    }
+
+    #[cfg(feature = "otel")]
+    let otel_provider = {
+        let endpoint = args
+            .otel_endpoint
+            .as_deref()
+            .or(settings.otel_endpoint.as_deref());
+        match endpoint.map(ldml_api::otel::init).transpose() {
+            Ok(provider) => {
+                if let Some(provider) = &provider {
+                    otel_handle
+                        .reload(Some(ldml_api::otel::layer(provider)))
+                        .map_err(io::Error::other)?;
+                    tracing::info!("exporting traces to {}", endpoint.unwrap_or_default());
+                }
+                provider
+            }
+            Err(err) => {
+                tracing::error!("Error starting trace export: {err}");
+                std::process::exit(1);
+            }
+        }
+    };
 
     tracing::debug!("listening on {addr}", addr = args.listen);
     let listener = TcpListener::bind(&args.listen).await?;
+    log_startup_banner(&cfg);
+    let cfg = Arc::new(RwLock::new(cfg));
+    if args.watch {
+        let cfg = cfg.clone();
+        // The watcher loops for as long as the process lives, so it gets a
+        // detached thread rather than a blocking task, which the runtime
+        // would wait on at shutdown.
+        std::thread::spawn(move || {
+            if let Err(err) = watch(cfg) {
+                tracing::error!("Error watching for data changes: {err}");
+            }
+        });
+    }
+    #[cfg(feature = "grpc")]
+    if let Some(addr) = args.grpc_listen.or(settings.grpc_listen) {
+        let cfg = cfg.clone();
+        tracing::info!("gRPC listening on {addr}");
+        tokio::spawn(async move {
+            if let Err(err) = ldml_api::grpc::serve(addr, cfg).await {
+                tracing::error!("Error serving gRPC at {addr}: {err}");
+            }
+        });
+    }
+    let mut app = app_with_shared_profiles(cfg, args.admin_token)?;
+    // Apply the limit innermost so shed requests are still traced, and their
+    // 503 responses pass through compression like any other response.
+    if let Some(max) = args.max_connections {
+        app = limit_concurrency(app, max);
+    }
     axum::serve(
         listener,
-        app(cfg)?
-            .layer(CompressionLayer::new())
+        app.layer(CompressionLayer::new())
             .layer(TraceLayer::new_for_http())
-            .into_make_service(),
+            .into_make_service_with_connect_info::<SocketAddr>(),
     )
     .with_graceful_shutdown(shutdown_signal())
     .await
@@ -73,13 +262,192 @@ async fn main() -> io::Result<()> {
             addr = args.listen,
             message = err.to_string()
         );
-        std::process::exit(err.raw_os_error().unwrap_or_default());
+        std::process::exit(err.raw_os_error().unwrap_or(1));
     });
+    // Flush any spans still waiting to be exported.
+    #[cfg(feature = "otel")]
+    if let Some(provider) = otel_provider {
+        if let Err(err) = provider.shutdown() {
+            tracing::error!("Error flushing traces: {err}");
+        }
+    }
 
     tracing::info!("shutting down");
     Ok(())
 }
 
+// The exit status for --validate and --validate-strict, with any problems
+// reported on stderr.
+fn validate_config(args: &Args) -> i32 {
+    let report = |err: config::Error| {
+        eprintln!("{file}: {err}", file = args.config.display());
+        1
+    };
+    let profiles = match config::profiles::from(&args.config, &args.profile) {
+        Ok(profiles) => profiles,
+        Err(err) => return report(err),
+    };
+    if let Err(err) = config::settings::from(&args.config) {
+        return report(err);
+    }
+    if let Err(err) = config::profiles::validate_sldr_dirs(&profiles) {
+        return report(err);
+    }
+    if args.validate_strict {
+        let missing = config::profiles::validate_sldr_integrity(&profiles);
+        for (tag, path) in &missing {
+            eprintln!("No LDML for {tag}: expected {path}", path = path.display());
+        }
+        if !missing.is_empty() {
+            return 1;
+        }
+    }
+    0
+}
+
+// Cross check each profile's langtags database against both its SLDR trees,
+// returning the exit status.
+fn check_strict(profiles: &config::Profiles) -> i32 {
+    let mut ok = true;
+    for (name, cfg) in config::profiles::unique(profiles) {
+        let report = cfg.langtags.load().validate_tag_file_correspondence(
+            &cfg.sldr_path(FlattenMode::Flat),
+            &cfg.sldr_path(FlattenMode::Unflat),
+        );
+        for tag in &report.missing_files {
+            tracing::error!(profile = name, "No LDML for {tag}");
+        }
+        for path in &report.extra_files {
+            tracing::error!(
+                profile = name,
+                "LDML for unknown tag: {path}",
+                path = path.display()
+            );
+        }
+        for (tag, expected, found) in &report.mismatched_revids {
+            tracing::error!(
+                profile = name,
+                "LDML for {tag} has revid {found}, expected {expected}"
+            );
+        }
+        tracing::info!(
+            profile = name,
+            "{missing} tags missing LDML files, {extra} LDML files for unknown tags",
+            missing = report.missing_files.len(),
+            extra = report.extra_files.len()
+        );
+        ok &= report.is_ok();
+    }
+    i32::from(!ok)
+}
+
+fn log_startup_banner(profiles: &config::Profiles) {
+    tracing::info!(
+        "{name} {version} starting",
+        name = env!("CARGO_PKG_NAME"),
+        version = env!("CARGO_PKG_VERSION")
+    );
+    let mut names: Vec<_> = profiles.keys().filter(|p| !p.is_empty()).collect();
+    names.sort_unstable();
+    for name in names {
+        let cfg = &profiles[name];
+        let langtags = cfg.langtags.load();
+        tracing::info!(
+            profile = name.as_str(),
+            langtags_api = langtags.version(),
+            langtags_date = langtags.date(),
+            tagsets = langtags.tagsets().count(),
+            sldr_tagsets = langtags.tagsets_with_sldr().count(),
+            sldr_flat = %cfg.sldr_path(FlattenMode::Flat).display(),
+            sldr_unflat = %cfg.sldr_path(FlattenMode::Unflat).display(),
+            memory_bytes = langtags.estimate_memory_bytes(),
+            "loaded profile"
+        );
+    }
+}
+
+fn dump_langtags(
+    langtags: &langtags::json::LangTags,
+    format: DumpFormat,
+    only_sldr: bool,
+) -> io::Result<()> {
+    let stdout = io::BufWriter::new(io::stdout().lock());
+    let filter = |ts: &langtags::tagset::TagSet| !only_sldr || ts.sldr;
+    match format {
+        DumpFormat::Json => langtags.to_writer_filtered(stdout, filter),
+        DumpFormat::Txt => langtags::text::write_tagsets(
+            stdout,
+            langtags
+                .tagsets()
+                .filter(|ts| filter(ts))
+                .map(|ts| ts.iter()),
+        ),
+    }
+}
+
+// Watch each profile's langtags and SLDR directories. SLDR files are read
+// from disk for every request, so changes to those only need logging and
+// any remembered content hashes forgetting, but a changed langtags.json is
+// reloaded into every profile using it.
+fn watch(profiles: SharedProfiles) -> notify::Result<()> {
+    let (tx, rx) = mpsc::channel();
+    // Debounce so editors and sync tools doing atomic writes settle first.
+    let mut debouncer = notify_debouncer_mini::new_debouncer(Duration::from_millis(500), tx)?;
+    for cfg in profiles
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .values()
+    {
+        let watcher = debouncer.watcher();
+        watcher.watch(&cfg.langtags_dir, RecursiveMode::NonRecursive)?;
+        watcher.watch(&cfg.sldr_dir, RecursiveMode::Recursive)?;
+    }
+
+    for events in rx {
+        let events = match events {
+            Ok(events) => events,
+            Err(err) => {
+                tracing::error!("Error watching for data changes: {err}");
+                continue;
+            }
+        };
+        for event in events {
+            let path = event.path;
+            if path.file_name() == Some("langtags.json".as_ref()) {
+                reload_langtags(&profiles, &path);
+            } else if path.extension() == Some("xml".as_ref()) {
+                tracing::debug!("SLDR file changed: {path}", path = path.to_string_lossy());
+                clear_content_hashes();
+            }
+        }
+    }
+    Ok(())
+}
+
+fn reload_langtags(profiles: &SharedProfiles, path: &path::Path) {
+    let same_file = |other: &path::Path| {
+        other == path
+            || matches!((other.canonicalize(), path.canonicalize()), (Ok(a), Ok(b)) if a == b)
+    };
+    let stale: Vec<(String, Arc<config::Config>)> = {
+        let profiles = profiles.read().unwrap_or_else(PoisonError::into_inner);
+        config::profiles::unique(&profiles)
+            .filter(|(_, cfg)| same_file(&cfg.langtags_path()))
+            .map(|(name, cfg)| (name.to_owned(), cfg.clone()))
+            .collect()
+    };
+
+    for (name, cfg) in stale {
+        match cfg.reload_langtags() {
+            Ok(()) => tracing::debug!(
+                "reloaded {path} for profile \"{name}\"",
+                path = path.to_string_lossy()
+            ),
+            Err(err) => tracing::error!("Error reloading langtags: {err}"),
+        }
+    }
+}
+
 async fn shutdown_signal() {
     use tokio::signal;
 
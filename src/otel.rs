@@ -0,0 +1,100 @@
+use axum::http::{HeaderMap, HeaderName};
+use opentelemetry::{
+    global,
+    propagation::Extractor,
+    trace::{TraceError, TracerProvider as _},
+    KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator,
+    runtime,
+    trace::{Tracer, TracerProvider},
+    Resource,
+};
+use tracing::Span;
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::Registry;
+
+pub type Layer = OpenTelemetryLayer<Registry, Tracer>;
+
+const TRACEPARENT: &str = "traceparent";
+
+// Export spans over OTLP/gRPC to endpoint, eg. http://localhost:4317.
+// Batches are sent from the tokio runtime, so this must be called from
+// within it. Shut the provider down before exiting to flush the last batch.
+pub fn init(endpoint: &str) -> Result<TracerProvider, TraceError> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new([KeyValue::new(
+            "service.name",
+            env!("CARGO_PKG_NAME"),
+        )]))
+        .build();
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    global::set_tracer_provider(provider.clone());
+    Ok(provider)
+}
+
+pub fn layer(provider: &TracerProvider) -> Layer {
+    tracing_opentelemetry::layer().with_tracer(provider.tracer(env!("CARGO_PKG_NAME")))
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+// Continue the trace a reverse proxy passed on in a W3C traceparent header,
+// making span a child of the proxy's span.
+pub fn set_remote_parent(span: &Span, headers: &HeaderMap) {
+    if headers.contains_key(TRACEPARENT) {
+        let parent =
+            global::get_text_map_propagator(|prop| prop.extract(&HeaderExtractor(headers)));
+        span.set_parent(parent);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::http::HeaderValue;
+    use opentelemetry::trace::{TraceContextExt, TraceId};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn adopts_traceparent() {
+        const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let provider = TracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry().with(layer(&provider));
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            TRACEPARENT,
+            HeaderValue::from_static("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("request");
+            set_remote_parent(&span, &headers);
+            let trace_id = span.context().span().span_context().trace_id();
+            assert_eq!(trace_id, TraceId::from_hex(TRACE_ID).unwrap());
+
+            let span = tracing::info_span!("request");
+            set_remote_parent(&span, &HeaderMap::new());
+            let trace_id = span.context().span().span_context().trace_id();
+            assert_ne!(trace_id, TraceId::from_hex(TRACE_ID).unwrap());
+        });
+    }
+}
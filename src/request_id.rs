@@ -0,0 +1,58 @@
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use std::fmt::{self, Display};
+use uuid::Uuid;
+
+pub static X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+// Identifies a request in the logs, either as passed on by a proxy in front of
+// us, or generated afresh when there is none.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RequestId(HeaderValue);
+
+impl RequestId {
+    fn new() -> Self {
+        let id = Uuid::new_v4().hyphenated().to_string();
+        RequestId(HeaderValue::from_str(&id).expect("UUID is a valid header value"))
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.to_str().unwrap_or_default()
+    }
+}
+
+impl Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+pub async fn layer(mut req: Request, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get(&X_REQUEST_ID)
+        .filter(|v| !v.is_empty() && v.to_str().is_ok())
+        .cloned()
+        .map_or_else(RequestId::new, RequestId);
+    req.extensions_mut().insert(id.clone());
+    let mut rsp = next.run(req).await;
+    rsp.headers_mut().insert(X_REQUEST_ID.clone(), id.0);
+    rsp
+}
+
+#[cfg(test)]
+mod test {
+    use super::RequestId;
+
+    #[test]
+    fn generated_ids_are_unique() {
+        let (a, b) = (RequestId::new(), RequestId::new());
+        assert_ne!(a, b);
+        assert_eq!(a.as_str().len(), 36);
+        assert_eq!(a.to_string(), a.as_str());
+    }
+}
@@ -1,12 +1,57 @@
-use serde_with::DeserializeFromStr;
-use std::{ops::Deref, str::FromStr};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, ops::Deref, str::FromStr};
 
-#[derive(Debug, Clone, Copy, Default, DeserializeFromStr, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
 pub struct Toggle(bool);
 
 impl Toggle {
     pub const ON: Toggle = Toggle(true);
     pub const OFF: Toggle = Toggle(false);
+
+    #[inline]
+    pub const fn from_bool(b: bool) -> Toggle {
+        Toggle(b)
+    }
+
+    #[inline]
+    pub const fn as_bool(&self) -> bool {
+        self.0
+    }
+
+    #[inline]
+    pub const fn is_on(&self) -> bool {
+        self.0
+    }
+
+    #[inline]
+    pub const fn is_off(&self) -> bool {
+        !self.0
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn toggle(self) -> Toggle {
+        Toggle(!self.0)
+    }
+
+    #[inline]
+    pub const fn to_bool(self) -> bool {
+        self.0
+    }
+}
+
+impl From<bool> for Toggle {
+    #[inline]
+    fn from(b: bool) -> Self {
+        Toggle(b)
+    }
+}
+
+impl From<Toggle> for bool {
+    #[inline]
+    fn from(t: Toggle) -> Self {
+        t.0
+    }
 }
 
 impl Deref for Toggle {
@@ -27,6 +72,46 @@ impl FromStr for Toggle {
     }
 }
 
+// Accept JSON booleans and numbers as well as the strings query parameters
+// arrive as.
+impl<'de> Deserialize<'de> for Toggle {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl de::Visitor<'_> for Visitor {
+            type Value = Toggle;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a boolean, number or string toggle")
+            }
+
+            fn visit_bool<E: de::Error>(self, v: bool) -> Result<Toggle, E> {
+                Ok(Toggle(v))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Toggle, E> {
+                Ok(Toggle(v != 0))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Toggle, E> {
+                Ok(Toggle(v != 0))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Toggle, E> {
+                Ok(v.parse().unwrap_or_else(|never| match never {}))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+impl Serialize for Toggle {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bool(self.0)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::Toggle;
@@ -35,8 +120,20 @@ mod test {
     fn default_value() {
         let t: Toggle = Default::default();
         assert_eq!(t, Toggle::OFF);
-        assert_eq!(*Toggle::OFF, false);
-        assert_eq!(*Toggle::ON, true);
+        assert!(!(*Toggle::OFF));
+        assert!(*Toggle::ON);
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(Toggle::from_bool(true), Toggle::ON);
+        assert_eq!(Toggle::from(false), Toggle::OFF);
+        assert!(bool::from(Toggle::ON));
+        assert!(Toggle::ON.to_bool() && !Toggle::OFF.to_bool());
+        assert!(Toggle::ON.as_bool() && Toggle::ON.is_on() && !Toggle::ON.is_off());
+        assert!(Toggle::OFF.is_off());
+        assert_eq!(Toggle::ON.toggle(), Toggle::OFF);
+        assert_eq!(Toggle::OFF.toggle(), Toggle::ON);
     }
 
     #[test]
@@ -57,4 +154,22 @@ mod test {
         assert_eq!("maybe".parse::<Toggle>(), Ok(Toggle::ON));
         assert_eq!("😼".parse::<Toggle>(), Ok(Toggle::ON));
     }
+
+    #[test]
+    fn json() {
+        use serde_json::{from_str, json, to_value};
+        use std::collections::HashMap;
+
+        let parse = |src| from_str::<HashMap<String, Toggle>>(src).expect("JSON toggle")["enabled"];
+        assert_eq!(parse(r#"{"enabled": true}"#), Toggle::ON);
+        assert_eq!(parse(r#"{"enabled": false}"#), Toggle::OFF);
+        assert_eq!(parse(r#"{"enabled": "yes"}"#), Toggle::ON);
+        assert_eq!(parse(r#"{"enabled": "off"}"#), Toggle::OFF);
+        assert_eq!(parse(r#"{"enabled": 0}"#), Toggle::OFF);
+        assert_eq!(parse(r#"{"enabled": 1}"#), Toggle::ON);
+        assert!(from_str::<Toggle>("null").is_err());
+
+        assert_eq!(to_value(Toggle::ON).unwrap(), json!(true));
+        assert_eq!(to_value(Toggle::OFF).unwrap(), json!(false));
+    }
 }
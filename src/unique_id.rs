@@ -1,6 +1,11 @@
 use rand::prelude::*;
 use serde_with::DeserializeFromStr;
-use std::{num::NonZeroU32, ops::Deref, str::FromStr};
+use std::{
+    fmt::{self, Display},
+    num::NonZeroU32,
+    ops::Deref,
+    str::FromStr,
+};
 
 #[derive(Debug, Clone, Copy, DeserializeFromStr, Eq, PartialEq)]
 pub struct UniqueID(u32);
@@ -9,6 +14,21 @@ impl UniqueID {
     fn new() -> Self {
         UniqueID(random())
     }
+
+    pub fn into_string(self) -> String {
+        self.to_string()
+    }
+
+    // Lowercase hex, for embedding in XML or URLs.
+    pub fn to_hex_string(self) -> String {
+        format!("{:x}", self.0)
+    }
+}
+
+impl Display for UniqueID {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
 }
 
 impl Deref for UniqueID {
@@ -35,10 +55,19 @@ mod test {
 
     #[test]
     fn deref() {
-        let t = UniqueID(012345678);
+        let t = UniqueID(12345678);
         assert_eq!(*t, 12345678u32);
     }
 
+    #[test]
+    fn display() {
+        let t = UniqueID(12345678);
+        assert_eq!(format!("{t}"), "12345678");
+        assert_eq!(t.into_string(), "12345678");
+        assert_eq!(t.to_hex_string(), "bc614e");
+        assert_eq!(UniqueID(u32::MAX).to_hex_string(), "ffffffff");
+    }
+
     #[test]
     fn parses() {
         use std::num::IntErrorKind;
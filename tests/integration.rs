@@ -1,17 +1,24 @@
 use axum::{
     body::Body,
-    http::{Request, StatusCode},
+    http::{Request, Response, StatusCode},
     Router,
 };
-use hyper::header::LOCATION;
+use hyper::header::{
+    AUTHORIZATION, CONTENT_DISPOSITION, CONTENT_TYPE, ETAG, IF_NONE_MATCH, LOCATION,
+};
 use langtags::json::LangTags;
 use language_tag::Tag;
 use ldml_api::{
-    app,
+    app, app_with_shared_profiles,
     config::{self, Profiles},
 };
 use serde_json::json;
-use std::{path::Path, str::FromStr};
+use std::{
+    collections::HashSet,
+    path::Path,
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
 use tower::{util::ServiceExt, Service};
 
 fn parse_config(langtags: impl AsRef<Path>, sldr: impl AsRef<Path>) -> Profiles {
@@ -101,6 +108,509 @@ async fn index_page() {
     assert_eq!(&body[..], INDEX_BODY);
 }
 
+#[tokio::test]
+async fn health_checks() {
+    let get = |app: Router, uri: &'static str| async move {
+        let response = app
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .expect("Response");
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).expect("JSON body");
+        (status, body)
+    };
+
+    assert_eq!(
+        get(get_app(), "/health/live").await,
+        (StatusCode::OK, json!({"status": "ok"}))
+    );
+    assert_eq!(
+        get(get_app(), "/health/ready").await,
+        (StatusCode::OK, json!({"status": "ok"}))
+    );
+
+    let broken = app(parse_config("tests/short", "tests/no-such-sldr")).expect("Router");
+    let (status, body) = get(broken, "/health/ready").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["status"], "degraded");
+    assert_eq!(body["checks"].as_array().map(Vec::len), Some(1));
+}
+
+#[tokio::test]
+async fn request_id() {
+    use ldml_api::X_REQUEST_ID;
+
+    let mut app = get_app();
+    let mut ids = Vec::new();
+    for uri in ["/", "/en", "/langtags.txt", "/nonexistent/path"] {
+        let response = app
+            .call(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .expect("Response");
+        let id = response.headers().get(&X_REQUEST_ID).expect("X-Request-ID");
+        ids.push(id.clone());
+    }
+    ids.dedup();
+    assert_eq!(ids.len(), 4);
+
+    let response = app
+        .call(
+            Request::builder()
+                .uri("/en")
+                .header(&X_REQUEST_ID, "from-the-proxy")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .expect("Response");
+    assert_eq!(response.headers()[&X_REQUEST_ID], "from-the-proxy");
+}
+
+#[tokio::test]
+async fn concurrency_limit() {
+    use axum::routing::get;
+    use hyper::header::RETRY_AFTER;
+    use tokio::sync::{mpsc, Semaphore};
+
+    // Each request reports it has started, then holds its slot until the
+    // test releases it, so the limit is full exactly when both have reported.
+    let (started, mut running) = mpsc::unbounded_channel();
+    let release = Arc::new(Semaphore::new(0));
+    let app = ldml_api::limit_concurrency(
+        Router::new().route(
+            "/",
+            get({
+                let release = release.clone();
+                || async move {
+                    started.send(()).expect("test listening");
+                    release.acquire().await.expect("open semaphore").forget();
+                }
+            }),
+        ),
+        2,
+    );
+    let request = || {
+        let app = app.clone();
+        tokio::spawn(async move {
+            app.oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+                .await
+                .expect("Response")
+        })
+    };
+
+    let held = [request(), request()];
+    for _ in &held {
+        running.recv().await.expect("request started");
+    }
+    let response = request().await.expect("Request task");
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers()[RETRY_AFTER], "1");
+
+    release.add_permits(held.len());
+    for request in held {
+        let response = request.await.expect("Request task");
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = request();
+    running.recv().await.expect("request started");
+    release.add_permits(1);
+    assert_eq!(
+        response.await.expect("Request task").status(),
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn subset_ldml_request() {
+    let sldr = Path::new(env!("CARGO_TARGET_TMPDIR")).join("subset_ldml_request");
+    std::fs::create_dir_all(sldr.join("flat/e")).expect("SLDR test directory");
+    std::fs::copy("tests/en_US.xml", sldr.join("flat/e/eka.xml")).expect("LDML test file");
+    let app = app(parse_config("tests/short", &sldr)).expect("Router");
+
+    let request = |inc: &str| {
+        let query = serde_urlencoded::to_string([("inc[]", inc)]).unwrap();
+        app.clone().oneshot(
+            Request::builder()
+                .uri(format!("/eka?{query}"))
+                .body(Body::empty())
+                .expect("Request"),
+        )
+    };
+
+    let response = request("layout,collations[collation/@type='eor'],numbers[@bogus]")
+        .await
+        .expect("Response");
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = std::str::from_utf8(&body).expect("UTF-8 body");
+    assert!(body.contains("<identity>"));
+    assert!(body.contains("<layout>"));
+    assert!(body.contains("<collations>"));
+    assert!(!body.contains("<numbers>"));
+    assert!(!body.contains("<dates>"));
+
+    for inc in ["//identity", "layout[document('/etc/passwd')]", "layout/.."] {
+        assert_eq!(
+            request(inc).await.expect("Response").status(),
+            StatusCode::BAD_REQUEST,
+            "{inc}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn revid_conditional_request() {
+    const REVID: &str = "b83dea0b8c92193966b10b85c823a22479d1c3ed";
+    let sldr = Path::new(env!("CARGO_TARGET_TMPDIR")).join("revid_conditional_request");
+    std::fs::create_dir_all(sldr.join("flat/e")).expect("SLDR test directory");
+    std::fs::copy("tests/en_US.xml", sldr.join("flat/e/eka.xml")).expect("LDML test file");
+    let app = app(parse_config("tests/short", &sldr)).expect("Router");
+
+    let request = |uri: String, if_none_match: Option<&str>| {
+        let mut request = Request::builder().uri(uri);
+        if let Some(etag) = if_none_match {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        app.clone()
+            .oneshot(request.body(Body::empty()).expect("Request"))
+    };
+
+    let response = request("/eka".into(), None).await.expect("Response");
+    assert_eq!(response.status(), StatusCode::OK);
+    let etag = response
+        .headers()
+        .get(ETAG)
+        .and_then(|v| v.to_str().ok())
+        .expect("ETag header")
+        .to_owned();
+    assert_eq!(etag, format!("\"{REVID}\""));
+
+    let status = |response: Result<Response<Body>, _>| response.expect("Response").status();
+    assert_eq!(
+        status(request(format!("/eka?revid={REVID}"), None).await),
+        StatusCode::NOT_MODIFIED
+    );
+    assert_eq!(
+        status(request("/eka".into(), Some(&etag)).await),
+        StatusCode::NOT_MODIFIED
+    );
+    assert_eq!(
+        status(request("/eka".into(), Some("\"0123456789abcdef\"")).await),
+        StatusCode::OK
+    );
+    assert_eq!(
+        status(request("/eka?revid=0123456789abcdef".into(), None).await),
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn sendfile() {
+    let profiles = config::profiles::from_reader(
+        json!({"": {
+            "langtags": "tests/short",
+            "sldr": "tests",
+            "sendfile_method": "X-Accel-Redirect"
+        }})
+        .to_string()
+        .as_bytes(),
+    )
+    .expect("profiles");
+    let app = app(profiles).expect("Router");
+
+    for (uri, file) in [
+        (
+            "/eka-Latn-NG-x-ekajuk",
+            "tests/flat/e/eka_Latn_NG_x_ekajuk.xml",
+        ),
+        ("/langtags.json", "tests/short/langtags.json"),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .expect("Response");
+        assert_eq!(response.status(), StatusCode::OK, "{uri}");
+        let expected = std::fs::canonicalize(file).expect("absolute path");
+        assert_eq!(
+            response.headers()["X-Accel-Redirect"],
+            expected.to_str().unwrap(),
+            "{uri}"
+        );
+        assert!(response.headers().contains_key(CONTENT_DISPOSITION));
+        assert!(response.headers().contains_key(CONTENT_TYPE));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.is_empty(), "{uri}");
+    }
+}
+
+#[tokio::test]
+async fn upload_ldml() {
+    use hyper::header::CONTENT_TYPE;
+
+    let src = std::fs::read("tests/en_US.xml").expect("LDML test file");
+    let upload = |app: Router, uri: &str, content_type: &str, body: Vec<u8>| {
+        app.oneshot(
+            Request::post(uri)
+                .header(CONTENT_TYPE, content_type)
+                .body(Body::from(body))
+                .expect("Request"),
+        )
+    };
+
+    let query = serde_urlencoded::to_string([("inc[]", "layout"), ("uid", "12345678")]).unwrap();
+    let response = upload(
+        get_app(),
+        &format!("/en-US?{query}"),
+        "application/xml",
+        src.clone(),
+    )
+    .await
+    .expect("Response");
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = std::str::from_utf8(&body).expect("UTF-8 body");
+    assert!(body.contains("<identity>"));
+    assert!(body.contains("uid=\"12345678\""));
+    assert!(body.contains("<layout>"));
+    assert!(!body.contains("<numbers>"));
+
+    let mut form = b"--XyZ\r\n\
+        Content-Disposition: form-data; name=\"comment\"\r\n\r\n\
+        not the file\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"ldml\"; filename=\"en_US.xml\"\r\n\
+        Content-Type: application/xml\r\n\r\n"
+        .to_vec();
+    form.extend_from_slice(&src);
+    form.extend_from_slice(b"\r\n--XyZ--\r\n");
+    let response = upload(
+        get_app(),
+        &format!("/en-US?{query}"),
+        "multipart/form-data; boundary=XyZ",
+        form,
+    )
+    .await
+    .expect("Response");
+    assert_eq!(response.status(), StatusCode::OK);
+    // Uploads are not conditional, so have no ETag to match against.
+    assert!(response.headers().get(ETAG).is_none());
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(std::str::from_utf8(&body).unwrap().contains("<layout>"));
+
+    let status =
+        |response: Result<axum::response::Response, _>| response.expect("Response").status();
+    assert_eq!(
+        status(upload(get_app(), "/eka", "application/xml", src.clone()).await),
+        StatusCode::UNPROCESSABLE_ENTITY
+    );
+    assert_eq!(
+        status(upload(get_app(), "/en-US", "text/plain", src.clone()).await),
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    );
+    assert_eq!(
+        status(
+            upload(
+                get_app(),
+                "/en-US",
+                "multipart/form-data; boundary=XyZ",
+                b"--XyZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nb\r\n--XyZ--\r\n"
+                    .to_vec()
+            )
+            .await
+        ),
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        status(
+            upload(
+                get_app(),
+                "/en-US",
+                "text/xml",
+                b"<ldml><identity>".to_vec()
+            )
+            .await
+        ),
+        StatusCode::BAD_REQUEST
+    );
+
+    let small = config::profiles::from_reader(
+        json!({"": {"langtags": "tests/short", "sldr": "tests", "max_upload_bytes": 1024}})
+            .to_string()
+            .as_bytes(),
+    )
+    .expect("profiles");
+    assert_eq!(
+        status(upload(app(small).unwrap(), "/en-US", "application/xml", src).await),
+        StatusCode::PAYLOAD_TOO_LARGE
+    );
+}
+
+#[tokio::test]
+async fn schema_validation() {
+    let profiles = config::profiles::from_reader(
+        json!({"": {"langtags": "tests/short", "sldr": "tests", "schema_path": "tests/ldml-minimal.xsd"}})
+            .to_string()
+            .as_bytes(),
+    )
+    .expect("profiles");
+    let upload = |body: &[u8]| {
+        app(profiles.clone()).expect("Router").oneshot(
+            Request::post("/en-US?inc[]=layout")
+                .header(CONTENT_TYPE, "application/xml")
+                .body(Body::from(body.to_vec()))
+                .expect("Request"),
+        )
+    };
+
+    let src = std::fs::read("tests/en_US.xml").expect("LDML test file");
+    let response = upload(&src).await.expect("Response");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = upload(
+        br#"<ldml><layout/><identity><language type="en"/><territory type="US"/></identity></ldml>"#,
+    )
+    .await
+    .expect("Response");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = std::str::from_utf8(&body).expect("UTF-8 body");
+    assert!(body.contains("schema"), "{body}");
+
+    // A stored file failing the schema is the server's fault, whether it is
+    // customised or served as it is.
+    let sldr = Path::new(env!("CARGO_TARGET_TMPDIR")).join("schema_validation");
+    std::fs::create_dir_all(sldr.join("flat/e")).expect("SLDR test directory");
+    std::fs::write(
+        sldr.join("flat/e/eka.xml"),
+        r#"<ldml><layout/><identity><language type="eka"/></identity></ldml>"#,
+    )
+    .expect("LDML test file");
+    let profiles = config::profiles::from_reader(
+        json!({"": {"langtags": "tests/short", "sldr": sldr, "schema_path": "tests/ldml-minimal.xsd"}})
+            .to_string()
+            .as_bytes(),
+    )
+    .expect("profiles");
+    let app = app(profiles).expect("Router");
+    for uri in ["/eka", "/eka?inc[]=layout"] {
+        let response = app
+            .clone()
+            .oneshot(Request::get(uri).body(Body::empty()).expect("Request"))
+            .await
+            .expect("Response");
+        assert_eq!(
+            response.status(),
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "{uri}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn langtags_csv() {
+    let request = |if_none_match: Option<&str>| {
+        let mut request = Request::builder().uri("/langtags.csv");
+        if let Some(etag) = if_none_match {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        get_app().oneshot(request.body(Body::empty()).expect("Request"))
+    };
+
+    let response = request(None).await.expect("Response");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CONTENT_TYPE], "text/csv; charset=utf-8");
+    assert_eq!(
+        response.headers()[CONTENT_DISPOSITION],
+        "attachment; filename=\"langtags.csv\""
+    );
+    let etag = response.headers()[ETAG].to_str().expect("ETag").to_owned();
+    assert!(etag.starts_with("\"sha256:"), "{etag}");
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = std::str::from_utf8(&body).expect("UTF-8 body");
+    assert!(body.starts_with(
+        "tag,full,name,localname,iso639_3,script,region,regions,sldr,obsolete,windows\n"
+    ));
+    assert!(body.contains("\naa-Arab,aa-Arab-ET,Afar,"), "{body}");
+
+    let response = request(Some(&etag)).await.expect("Response");
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    let response = get_app()
+        .oneshot(
+            Request::builder()
+                .uri("/?query=langtags&ext=csv")
+                .body(Body::empty())
+                .expect("Request"),
+        )
+        .await
+        .expect("Response");
+    assert_eq!(response.headers()[LOCATION], "/langtags.csv");
+}
+
+#[tokio::test]
+async fn langtags_csv_after_reload() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("langtags_csv_after_reload");
+    std::fs::create_dir_all(&dir).expect("langtags directory");
+    let write_langtags = |tag: &str, full: &str| {
+        std::fs::write(
+            dir.join("langtags.json"),
+            json!([
+                {"api": "1.3", "date": "2023-02-20", "tag": "_version"},
+                {"full": full, "tag": tag, "windows": full}
+            ])
+            .to_string(),
+        )
+        .expect("langtags.json");
+    };
+    write_langtags("zza", "zza-Latn-TR");
+    let profiles = parse_config(&dir, "tests");
+    let cfg = profiles[""].clone();
+    let app = app(profiles).expect("Router");
+    let request = || async {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/langtags.csv")
+                    .body(Body::empty())
+                    .expect("Request"),
+            )
+            .await
+            .expect("Response");
+        let etag = response.headers()[ETAG].to_str().expect("ETag").to_owned();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (etag, String::from_utf8(body.to_vec()).expect("UTF-8 body"))
+    };
+
+    let (etag, body) = request().await;
+    assert!(body.contains("\nzza,zza-Latn-TR,"), "{body}");
+    assert_eq!(request().await, (etag.clone(), body));
+
+    // A reloaded database gets its own CSV rather than the cached one.
+    write_langtags("aa", "aa-Latn-ET");
+    cfg.reload_langtags().expect("reloaded langtags");
+    let (reloaded, body) = request().await;
+    assert_ne!(reloaded, etag);
+    assert!(body.contains("\naa,aa-Latn-ET,"), "{body}");
+}
+
 async fn request_ldml_file(app: &mut Router, tag: &Tag) -> StatusCode {
     let response = app
         .oneshot(
@@ -122,7 +632,7 @@ async fn query_tags() {
     let response = app
         .oneshot(
             Request::builder()
-                .uri(format!("/frm?query=tags"))
+                .uri("/frm?query=tags".to_string())
                 .body(Body::empty())
                 .expect("Request"),
         )
@@ -135,11 +645,354 @@ async fn query_tags() {
         .unwrap();
     assert_eq!(
         &body[..],
-        b"frm=frm-FR=frm-Latn=frm-Latn-FR\n\
-          frm-BE=frm-Latn-BE\n\
-          frm-1606nict=frm-FR-1606nict=frm-Latn-1606nict=frm-Latn-FR-1606nict\n\
-          frm-BE-1606nict=frm-Latn-BE-1606nict"
+        b"frm-Latn-FR=frm-FR=frm-Latn=frm\n\
+          frm-Latn-BE=frm-BE\n\
+          frm-Latn-FR-1606nict=frm-FR-1606nict=frm-Latn-1606nict=frm-1606nict\n\
+          frm-Latn-BE-1606nict=frm-BE-1606nict"
+    );
+}
+
+#[tokio::test]
+async fn query_conformance() {
+    let mut app = get_app();
+    let mut query = |uri: &'static str| {
+        let request = Request::builder()
+            .uri(uri)
+            .body(Body::empty())
+            .expect("Request");
+        let response = app.call(request);
+        async move {
+            let response = response.await.expect("Response");
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), 1024)
+                .await
+                .unwrap();
+            (
+                status,
+                serde_json::from_slice::<serde_json::Value>(&body).ok(),
+            )
+        }
+    };
+
+    assert_eq!(
+        query("/?query=conformance&tag=aa").await,
+        (
+            StatusCode::OK,
+            Some(json!({"conformant": true, "canonical": "aa-Latn-ET"}))
+        )
+    );
+    assert_eq!(
+        query("/aa-Latn?query=conformance").await,
+        (
+            StatusCode::OK,
+            Some(json!({"conformant": true, "canonical": "aa-Latn-ET"}))
+        )
+    );
+    assert_eq!(
+        query("/?query=conformance&tag=aa-Xxxx").await,
+        (
+            StatusCode::OK,
+            Some(json!({"conformant": false, "issues": ["unknown script: Xxxx"]}))
+        )
+    );
+    assert_eq!(
+        query("/?query=conformance&tag=en--US").await.0,
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        query("/?query=conformance").await.0,
+        StatusCode::BAD_REQUEST
+    );
+}
+
+#[tokio::test]
+async fn query_info() {
+    let mut app = get_app();
+
+    let response = app
+        .call(
+            Request::builder()
+                .uri("/aa?query=info")
+                .body(Body::empty())
+                .expect("Request"),
+        )
+        .await
+        .expect("Response");
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(&body).expect("JSON body"),
+        json!({
+            "full": "aa-Latn-ET",
+            "tag": "aa",
+            "name": "Afar",
+            "localname": "Qafar",
+            "iso639_3": "aar",
+            "windows": "aa-Latn-ET",
+            "sldr": true,
+            "regions": [],
+            "script": "Latn",
+            "region": "ET",
+        })
+    );
+
+    let response = app
+        .call(
+            Request::builder()
+                .uri("/thv-Latn-DZ-x-ahaggar?query=info")
+                .body(Body::empty())
+                .expect("Request"),
+        )
+        .await
+        .expect("Response");
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    let info = serde_json::from_slice::<serde_json::Value>(&body).expect("JSON body");
+    assert_eq!(info["rod"], "10081");
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/zz-XX?query=info")
+                .body(Body::empty())
+                .expect("Request"),
+        )
+        .await
+        .expect("Response");
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn reload_langtags_while_serving() {
+    let profiles = parse_config("tests/short", "tests");
+    let cfg = profiles[""].clone();
+    let before = cfg.langtags.load_full();
+    let mut app = app(profiles).expect("Router");
+
+    let reloads = std::thread::spawn({
+        let cfg = cfg.clone();
+        move || {
+            for _ in 0..20 {
+                cfg.reload_langtags().expect("reloaded langtags");
+            }
+        }
+    });
+    let mut lookups = 0;
+    while lookups < 10 || !reloads.is_finished() {
+        let response = app
+            .call(
+                Request::builder()
+                    .uri("/aa?query=info")
+                    .body(Body::empty())
+                    .expect("Request"),
+            )
+            .await
+            .expect("Response");
+        assert_eq!(response.status(), StatusCode::OK);
+        lookups += 1;
+    }
+    reloads.join().expect("reload thread");
+
+    let after = cfg.langtags.load_full();
+    assert!(!Arc::ptr_eq(&before, &after));
+    assert_eq!(before, after);
+    assert!(after
+        .orthographic_normal_form(&Tag::from_str("aa").unwrap())
+        .is_some());
+}
+
+#[tokio::test]
+async fn admin_profiles() {
+    let shared = Arc::new(RwLock::new(parse_config("tests/short", "tests")));
+    let mut app = app_with_shared_profiles(shared.clone(), Some("s3cret".into())).expect("Router");
+    let mut request = |method, uri, body: serde_json::Value, token| {
+        let mut req = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            req = req.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+        app.call(req.body(Body::from(body.to_string())).expect("Request"))
+    };
+    // beta knows a tag the default profile does not, to tell them apart.
+    let beta_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("admin_profiles");
+    std::fs::create_dir_all(&beta_dir).expect("beta langtags directory");
+    std::fs::write(
+        beta_dir.join("langtags.json"),
+        json!([
+            {"api": "1.3", "date": "2023-02-20", "tag": "_version"},
+            {"full": "zza-Latn-TR", "tag": "zza", "windows": "zza-Latn-TR"}
+        ])
+        .to_string(),
+    )
+    .expect("beta langtags.json");
+    let beta = json!({"beta": {"langtags": beta_dir, "sldr": "tests"}});
+
+    let response = request("POST", "/admin/profiles", beta.clone(), None)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    // Admin requests pass through the same request tagging as any other.
+    assert!(response.headers().contains_key(&ldml_api::X_REQUEST_ID));
+    let response = request("POST", "/admin/profiles", beta.clone(), Some("guess")).await;
+    assert_eq!(response.unwrap().status(), StatusCode::UNAUTHORIZED);
+    assert!(!shared.read().unwrap().contains_key("beta"));
+
+    let response = request("POST", "/admin/profiles", beta.clone(), Some("s3cret")).await;
+    assert_eq!(response.unwrap().status(), StatusCode::CREATED);
+    let beta_cfg = shared.read().unwrap()["beta"].clone();
+    assert!(!beta_cfg.langtags.load().is_empty());
+    let response = request("GET", "/zza?query=info&beta=1", json!(null), None).await;
+    assert_eq!(response.unwrap().status(), StatusCode::OK);
+    let response = request("GET", "/zza?query=info", json!(null), None).await;
+    assert_eq!(response.unwrap().status(), StatusCode::NOT_FOUND);
+
+    let response = request("POST", "/admin/profiles", beta, Some("s3cret")).await;
+    assert_eq!(response.unwrap().status(), StatusCode::CONFLICT);
+    let missing = json!({"gamma": {"langtags": "tests/missing", "sldr": "tests"}});
+    let response = request("POST", "/admin/profiles", missing, Some("s3cret")).await;
+    assert_eq!(response.unwrap().status(), StatusCode::BAD_REQUEST);
+
+    let response = request("DELETE", "/admin/profiles/", json!(null), Some("s3cret")).await;
+    assert_ne!(response.unwrap().status(), StatusCode::NO_CONTENT);
+    let response = request(
+        "DELETE",
+        "/admin/profiles/beta",
+        json!(null),
+        Some("s3cret"),
+    )
+    .await;
+    assert_eq!(response.unwrap().status(), StatusCode::NO_CONTENT);
+    let response = request(
+        "DELETE",
+        "/admin/profiles/beta",
+        json!(null),
+        Some("s3cret"),
+    )
+    .await;
+    assert_eq!(response.unwrap().status(), StatusCode::NOT_FOUND);
+    assert_eq!(shared.read().unwrap().len(), 1);
+}
+
+#[cfg(feature = "grpc")]
+#[tokio::test]
+async fn grpc_resolve_tag() {
+    use ldml_api::grpc::{self, LdmlClient, TagRequest};
+    use std::sync::{Arc, RwLock};
+    use tonic::{transport::server::TcpIncoming, Code};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("listener");
+    let addr = listener.local_addr().expect("address");
+    let incoming = TcpIncoming::from_listener(listener, true, None).expect("incoming");
+    let profiles = Arc::new(RwLock::new(get_profiles().clone()));
+    tokio::spawn(
+        tonic::transport::Server::builder()
+            .add_service(grpc::service(profiles))
+            .serve_with_incoming(incoming),
     );
+
+    let mut client = LdmlClient::connect(format!("http://{addr}"))
+        .await
+        .expect("gRPC client");
+    let request = |tag: &str| TagRequest {
+        tag: tag.into(),
+        profile: String::new(),
+    };
+    let ts = client
+        .resolve_tag(request("aa"))
+        .await
+        .expect("ResolveTag")
+        .into_inner();
+    assert_eq!(ts.full, "aa-Latn-ET");
+    assert!(ts.sldr);
+
+    let sets = client
+        .lookup_tags(request("aa"))
+        .await
+        .expect("LookupTags")
+        .into_inner()
+        .sets;
+    assert!(sets[0].contains("aa-Latn-ET"));
+
+    let status = client.resolve_tag(request("en-KP")).await.unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+    let status = client.resolve_tag(request("#aa")).await.unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+}
+
+#[test]
+fn sldr_integrity() {
+    assert_eq!(
+        config::profiles::validate_sldr_integrity(get_profiles()),
+        []
+    );
+    assert!(config::profiles::validate_sldr_dirs(get_profiles()).is_ok());
+
+    let sldr = Path::new(env!("CARGO_TARGET_TMPDIR")).join("sldr_integrity");
+    let _ = std::fs::remove_dir_all(&sldr);
+    let profiles = parse_config("tests/short", &sldr);
+    let err = config::profiles::validate_sldr_dirs(&profiles).expect_err("missing SLDR directory");
+    assert_eq!(err.field(), Some("sldr"));
+
+    // An empty SLDR is missing a file for every sldr tagset in the dataset.
+    std::fs::create_dir_all(sldr.join("flat")).expect("SLDR test directory");
+    assert_eq!(profiles[""].sldr_file_count().expect("file count"), 0);
+    let missing = config::profiles::validate_sldr_integrity(&profiles);
+    let expected = [
+        ("aa", "a/aa.xml"),
+        ("aa-DJ", "a/aa_DJ.xml"),
+        ("aa-ER", "a/aa_ER.xml"),
+        ("eka", "e/eka.xml"),
+        ("eka-Latn-NG-x-ekajuk", "e/eka_Latn_NG_x_ekajuk.xml"),
+        ("thv", "t/thv.xml"),
+        ("thv-Latn-DZ-x-ahaggar", "t/thv_Latn_DZ_x_ahaggar.xml"),
+    ]
+    .map(|(tag, file)| (tag.to_string(), sldr.join("flat").join(file)));
+    assert_eq!(
+        missing.iter().collect::<HashSet<_>>(),
+        expected.iter().collect::<HashSet<_>>()
+    );
+
+    // Filling in each reported file leaves nothing missing. Every XML file
+    // under the SLDR is counted, flattened or not, and nothing else is.
+    for (_, path) in &missing {
+        std::fs::create_dir_all(path.parent().unwrap()).expect("SLDR test directory");
+        std::fs::copy("tests/flat/a/aa.xml", path).expect("LDML test file");
+    }
+    std::fs::copy("tests/en_US.xml", sldr.join("en_US.xml")).expect("LDML test file");
+    std::fs::write(sldr.join("flat/README"), "not LDML").expect("test file");
+    assert_eq!(config::profiles::validate_sldr_integrity(&profiles), []);
+    assert_eq!(
+        profiles[""].sldr_file_count().expect("file count"),
+        expected.len() + 1
+    );
+}
+
+// A config error that is not an OS error, such as naming a profile the config
+// does not define, must still exit with a failure status.
+#[test]
+fn unknown_profile_exit_status() {
+    let file = tempfile::Builder::new()
+        .suffix(".json")
+        .tempfile()
+        .expect("temp file");
+    std::fs::write(
+        file.path(),
+        json!({"production": {"langtags": "tests/short", "sldr": "tests"}}).to_string(),
+    )
+    .expect("config file");
+    let status = std::process::Command::new(env!("CARGO_BIN_EXE_ldml-api"))
+        .arg("--config")
+        .arg(file.path())
+        .args(["--profile", "staging", "--listen", "127.0.0.1:0"])
+        .stdout(std::process::Stdio::null())
+        .status()
+        .expect("ran ldml-api");
+    assert_eq!(status.code(), Some(1));
 }
 
 #[tokio::test]
@@ -166,13 +1019,33 @@ async fn simple_writing_system_request() {
         request_ldml_file(&mut app, &Tag::from_str("en-KP").expect("Tag")).await,
         StatusCode::NOT_FOUND
     );
+
+    let body = |uri: &'static str| {
+        let app = get_app();
+        async move {
+            let response = app
+                .oneshot(Request::get(uri).body(Body::empty()).expect("Request"))
+                .await
+                .expect("Response");
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{uri}");
+            let body = axum::body::to_bytes(response.into_body(), 1024)
+                .await
+                .unwrap();
+            String::from_utf8(body.to_vec()).expect("UTF-8 body")
+        }
+    };
+    assert_eq!(
+        body("/aa-Arab").await,
+        "No LDML available for aa-Arab-ET (not in SLDR)"
+    );
+    assert_eq!(body("/en-KP").await, "No LDML for en-KP");
 }
 
 fn generate_testing_tag_list(langtags: &LangTags) -> impl Iterator<Item = Tag> + '_ {
     langtags
         .tagsets()
-        .filter_map(|ts| ts.sldr.then(|| ts.iter()))
-        .flatten()
+        .filter(|ts| ts.sldr)
+        .flat_map(|ts| ts.iter())
         .cloned()
 }
 
@@ -194,7 +1067,7 @@ async fn palaso_writing_systems_list(profile: &str) {
         src_top_level.join("data/langtags").join(profile),
         src_top_level.join("data/sldr").join(profile),
     );
-    let mut tags = generate_testing_tag_list(&cfg[""].langtags).collect::<Vec<_>>();
+    let mut tags = generate_testing_tag_list(&cfg[""].langtags.load()).collect::<Vec<_>>();
     tags.sort();
     let mut app = app(cfg).expect("Router");
     for (l, tag) in tags.into_iter().enumerate() {